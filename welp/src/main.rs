@@ -2,12 +2,14 @@ extern crate wisdom;
 extern crate clap;
 extern crate rustyline;
 
-use clap::{Arg, App};
+use clap::{Arg, App, ArgMatches};
 
 use std::io::{Write, BufReader};
 
-use wisdom::interpreter::{Interpreter, SlowInterpreter};
-use wisdom::ast::Value;
+use wisdom::interpreter::{Interpreter, SlowInterpreter, VmInterpreter, BUILTINS};
+use wisdom::ast::{Stmt, Value};
+use wisdom::ast::checker;
+use wisdom::tokenizer::{FromTokens, TokenStream};
 use wisdom::interpreter::error::{Error};
 use std::io::{self, BufRead};
 use std::fs::File;
@@ -15,7 +17,7 @@ use wisdom::common::{Position, WisdomError};
 use rustyline::Editor;
 
 fn do_write(msg: &str) {
-    std::io::stdout().write(msg.as_bytes()).unwrap();
+    std::io::stdout().write_all(msg.as_bytes()).unwrap();
     std::io::stdout().flush().unwrap();
 }
 
@@ -37,7 +39,7 @@ fn handle_err_with_line(desc: String, pos: Position, line: String) {
 }
 
 
-fn handle(err: Error, filename: &str) {
+fn handle<E: WisdomError>(err: E, filename: &str) {
     let position = err.position();
     if let Ok(line) = get_line(filename, position.line - 1) {
         do_write(format!("{}:{}:{}\n", filename, position.line, position.column).as_str());
@@ -49,8 +51,6 @@ fn handle(err: Error, filename: &str) {
 
 // TODO: support reading from file
 fn main() {
-    let mut interp = SlowInterpreter::new();
-    let mut rl = Editor::<()>::new();
     let args = App::new("WELP")
         .version("0.1")
         .author("Giles Hutton")
@@ -64,22 +64,102 @@ fn main() {
                 .short("e")
                 .help("run a given expression")
                 .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("vm")
+                .long("vm")
+                .help("use the bytecode interpreter")
+        )
+        .arg(
+            Arg::with_name("check")
+                .long("check")
+                .help("check a given wisdom file for errors, without running it")
+                .requires("file")
         ).get_matches();
 
+    if args.is_present("check") {
+        check(args.value_of("file").unwrap());
+    }
+
+    if args.is_present("vm") {
+        run(VmInterpreter::new(), &args);
+    } else {
+        run(SlowInterpreter::new(), &args);
+    }
+}
+
+///
+/// Checks a file and reports any errors and warnings found, exiting with
+/// an error status if there were any errors.
+///
+fn check(filename: &str) -> ! {
+    let script = match std::fs::read_to_string(filename) {
+        Ok(script) => script,
+        Err(e) => {
+            do_write(format!("failed to read {}: {}\n", filename, e).as_str());
+            std::process::exit(1);
+        }
+    };
+
+    let tokens = TokenStream::new(&script);
+    let mut stmts = Vec::new();
+    while !tokens.is_empty() {
+        match Stmt::from_tokens(&tokens) {
+            Ok(stmt) => stmts.push(stmt),
+            Err(e) => {
+                handle(e, filename);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let errors = checker::check(&stmts, BUILTINS);
+    for err in &errors {
+        if err.is_warning() {
+            do_write("warning: ");
+        }
+        handle(err.clone(), filename);
+    }
+    let count = errors.iter().filter(|err| !err.is_warning()).count();
+    if count == 0 {
+        do_write(format!("{}: no errors found\n", filename).as_str());
+        std::process::exit(0);
+    }
+    do_write(format!("{}: {} error(s) found\n", filename, count).as_str());
+    std::process::exit(1);
+}
+
+///
+/// Reports the warnings raised by the interpreter, pointing at their line
+/// of the source if it came from a file.
+///
+fn warn<I: Interpreter<Value, Error>>(interp: &mut I, filename: Option<&str>) {
+    for warning in interp.take_warnings() {
+        do_write("warning: ");
+        match filename {
+            Some(filename) => handle(warning, filename),
+            None => do_write(format!("{}:{}: {}\n", warning.position.line, warning.position.column, warning).as_str()),
+        }
+    }
+}
+
+fn run<I: Interpreter<Value, Error>>(mut interp: I, args: &ArgMatches) {
+    let mut rl = Editor::<()>::new();
     match args.value_of("file") {
         Some(filename) => {
-            match interp.eval_file(filename) {
-                Err(e) => {
-                    do_write(format!("failed to run {}\n", filename).as_str());
-                    handle(e, filename);
-                }
-                _ => {}
+            let result = interp.eval_file(filename);
+            warn(&mut interp, Some(filename));
+            if let Err(e) = result {
+                do_write(format!("failed to run {}\n", filename).as_str());
+                handle(e, filename);
             }
         }
         None => {
             match args.value_of("eval") {
                 Some(script) => {
-                    match interp.eval_script(script) {
+                    let result = interp.eval_script(script);
+                    warn(&mut interp, None);
+                    match result {
                         Ok(v) => do_write(format!("{}\n", v).as_str()),
                         Err(e) => do_write(format!("{}\n", e).as_str()),
                     }
@@ -97,7 +177,9 @@ fn main() {
 
                                 rl.add_history_entry(line.clone());
 
-                                match interp.eval_line(line.as_str()) {
+                                let result = interp.eval_line(line.as_str());
+                                warn(&mut interp, None);
+                                match result {
                                     Ok(v) => {
                                         if v != Value::None {
                                             do_write(format!("{}\n", v).as_str())
@@ -0,0 +1,1105 @@
+//!
+//! A static checker, which finds mistakes in a script without running it:
+//! undefined variables, calls with the wrong number of arguments, values
+//! that don't match their type annotations and operators applied to
+//! operands of the wrong types.
+//!
+//! Types are inferred from literals, annotations and the operations on them.
+//! Anything else is `any`, which is never reported, so that valid scripts
+//! are never rejected. For the same reason, variables that are reassigned
+//! anywhere in the script are always treated as `any`.
+//!
+//! Names are resolved lexically, as both interpreters resolve them: a
+//! function can use the globals and the variables of the functions that
+//! enclose it, including its own name, but not those of its caller.
+//!
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use common::{Position, WisdomError};
+
+use crate::{Arm, ArgSpec, BindError, BinOp, Block, Enum, Expr, ExprKind, Fragment, Function, Ident, Pattern, Stmt, StmtKind, Struct, Typ, UnaryOp, Value};
+
+///
+/// The statically known type of an expression
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum Type {
+    /// Not known until the script is run, and compatible with everything
+    Any,
+    None,
+    Int,
+    Float,
+    Bool,
+    Str,
+    Regex,
+    List,
+    Map,
+    Range,
+    Error,
+    /// A function, along with its signature if it is known
+    Fn(Option<Box<Signature>>),
+    /// An instance of the named struct, or a value of the named enum
+    Instance(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Signature {
+    /// The declared parameters, which the arguments of a call are bound to
+    pub params: Vec<ArgSpec>,
+    /// The annotated type of each parameter
+    pub args: Vec<Type>,
+    pub ret: Type,
+}
+
+/// Signatures are equal if their types are, regardless of the parameters' names and defaults
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.args == other.args && self.ret == other.ret
+    }
+}
+
+impl Type {
+    ///
+    /// The name of the type, as used in type annotations
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            Type::Any => "any",
+            Type::None => "none",
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Str => "str",
+            Type::Regex => "regex",
+            Type::List => "list",
+            Type::Map => "map",
+            Type::Range => "range",
+            Type::Error => "error",
+            Type::Fn(_) => "fn",
+            Type::Instance(name) => name,
+        }
+    }
+
+    ///
+    /// The type named by an annotation, if it is a valid type name other than a struct
+    ///
+    pub fn from_annotation(typ: &Typ) -> Option<Self> {
+        Some(match typ.ident.name.as_str() {
+            "any" => Type::Any,
+            "none" => Type::None,
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "bool" => Type::Bool,
+            "str" => Type::Str,
+            "regex" => Type::Regex,
+            "list" => Type::List,
+            "map" => Type::Map,
+            "range" => Type::Range,
+            "error" => Type::Error,
+            "fn" => Type::Fn(None),
+            _ => return None,
+        })
+    }
+
+    fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::String(_) => Type::Str,
+            Value::Regex(_) => Type::Regex,
+            Value::List(_) => Type::List,
+            Value::Map(_) => Type::Map,
+            Value::Range(_, _) => Type::Range,
+            Value::Error(_) => Type::Error,
+            Value::Func(_) | Value::Closure(_) | Value::Builtin(_) | Value::Struct(_) | Value::Variant(_, _) | Value::Method(_) => Type::Fn(None),
+            Value::Instance(instance) => Type::Instance(instance.name.clone()),
+            Value::None => Type::None,
+            Value::Named(_) | Value::Enum(_) => Type::Any,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    ///
+    /// Whether a value of this type may be given where expected is annotated.
+    /// Strings are compiled when given where a regex is expected.
+    ///
+    fn conforms_to(&self, expected: &Type) -> bool {
+        match (self, expected) {
+            (Type::Any, _) | (_, Type::Any) => true,
+            (Type::Str, Type::Regex) => true,
+            (actual, expected) => actual.name() == expected.name(),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct CheckError {
+    pub kind: CheckErrorKind,
+    pub position: Position,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum CheckErrorKind {
+    UndefinedVar(String),
+    /// A call with too many arguments, with the expected and actual counts
+    UnexpectedArgs(usize, usize),
+    UnknownArg(String),
+    DuplicateArg(String),
+    MissingArg(String),
+    /// A value that does not match its annotation, with the annotated and actual type names
+    TypeMismatch(String, String),
+    UnknownType(String),
+    InvalidOperands(BinOp, Type, Type),
+    InvalidOperand(UnaryOp, Type),
+    /// A pattern naming a variant that the enum does not have, with the enum and variant names
+    UnknownVariant(String, String),
+    /// A match on an enum without an arm for every variant, with the enum and missing variant names
+    NonExhaustiveMatch(String, Vec<String>),
+}
+
+impl CheckError {
+    pub fn new(kind: CheckErrorKind, position: Position) -> Self {
+        Self {
+            kind,
+            position,
+        }
+    }
+
+    ///
+    /// Whether this is only a warning, for a script that runs but may not
+    /// do what was intended, rather than an error
+    ///
+    pub fn is_warning(&self) -> bool {
+        matches!(self.kind, CheckErrorKind::NonExhaustiveMatch(_, _))
+    }
+}
+
+impl Display for CheckError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CheckErrorKind::UndefinedVar(name) => write!(f, "Undefined variable '{}'", name),
+            CheckErrorKind::UnexpectedArgs(exp, act) => write!(f, "Expected {} args, got {}", exp, act),
+            CheckErrorKind::UnknownArg(name) => write!(f, "Unknown argument '{}'", name),
+            CheckErrorKind::DuplicateArg(name) => write!(f, "Argument '{}' given more than once", name),
+            CheckErrorKind::MissingArg(name) => write!(f, "Missing argument '{}'", name),
+            CheckErrorKind::TypeMismatch(exp, act) => write!(f, "Expected a value of type '{}', got '{}'", exp, act),
+            CheckErrorKind::UnknownType(name) => write!(f, "Unknown type '{}'", name),
+            CheckErrorKind::InvalidOperands(op, lhs, rhs) => {
+                write!(f, "Unsupported operand types for '{}': '{}' and '{}'", op, lhs, rhs)
+            }
+            CheckErrorKind::InvalidOperand(op, operand) => {
+                write!(f, "Unsupported operand type for '{}': '{}'", op, operand)
+            }
+            CheckErrorKind::UnknownVariant(name, variant) => write!(f, "Enum '{}' has no variant '{}'", name, variant),
+            CheckErrorKind::NonExhaustiveMatch(name, missing) => {
+                write!(f, "Match on '{}' does not handle variants: {}", name, missing.join(", "))
+            }
+        }
+    }
+}
+
+impl From<BindError> for CheckErrorKind {
+    fn from(err: BindError) -> Self {
+        match err {
+            BindError::TooMany(exp, act) => CheckErrorKind::UnexpectedArgs(exp, act),
+            BindError::Unknown(name) => CheckErrorKind::UnknownArg(name),
+            BindError::Duplicate(name) => CheckErrorKind::DuplicateArg(name),
+            BindError::Missing(name) => CheckErrorKind::MissingArg(name),
+        }
+    }
+}
+
+impl WisdomError for CheckError {
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl Error for CheckError {}
+
+///
+/// Checks a whole script, returning every problem found, in source order.
+/// builtins are the names of the functions provided by the interpreter.
+///
+pub fn check(stmts: &[Stmt], builtins: &[&str]) -> Vec<CheckError> {
+    let mut checker = Checker {
+        scopes: vec![HashMap::new()],
+        builtins,
+        structs: HashSet::new(),
+        enums: HashMap::new(),
+        reassigned: reassigned_names(stmts),
+        returns: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // named functions, structs and enums can be used from anywhere once they are defined, including
+    // by each other, so function bodies are checked once all of the globals are known
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Struct(def) => {
+                checker.structs.insert(def.ident.name.clone());
+            }
+            StmtKind::Enum(def) => {
+                checker.structs.insert(def.ident.name.clone());
+                checker.enums.insert(def.ident.name.clone(), def.variants.iter().map(|variant| variant.ident.name.clone()).collect());
+            }
+            _ => {}
+        }
+    }
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Fn(func) => {
+                let signature = checker.signature(func);
+                checker.declare(&func.ident.name, Type::Fn(Some(signature.into())));
+            }
+            StmtKind::Struct(_) | StmtKind::Enum(_) => {
+                checker.check_stmt(stmt);
+            }
+            _ => {}
+        }
+    }
+    for stmt in stmts {
+        if !matches!(stmt.kind, StmtKind::Fn(_) | StmtKind::Struct(_) | StmtKind::Enum(_) | StmtKind::Impl(_)) {
+            checker.check_stmt(stmt);
+        }
+    }
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Fn(func) => {
+                checker.check_function(func);
+            }
+            StmtKind::Impl(_) => {
+                checker.check_stmt(stmt);
+            }
+            _ => {}
+        }
+    }
+    checker.errors.sort_by_key(|err| (err.position.line, err.position.column));
+    checker.errors
+}
+
+struct Checker<'a> {
+    scopes: Vec<HashMap<String, Type>>,
+    builtins: &'a [&'a str],
+    /// Names of the declared structs and enums, which may be used as type annotations
+    structs: HashSet<String>,
+    /// The variants of each declared enum
+    enums: HashMap<String, Vec<String>>,
+    /// Names that are assigned to, after being bound
+    reassigned: HashSet<String>,
+    /// Return types of the functions being checked, innermost last
+    returns: Vec<Type>,
+    errors: Vec<CheckError>,
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, kind: CheckErrorKind, position: Position) {
+        self.errors.push(CheckError::new(kind, position));
+    }
+
+    fn declare(&mut self, name: &str, typ: Type) {
+        let typ = if self.reassigned.contains(name) { Type::Any } else { typ };
+        self.scopes.last_mut().expect("there should always be a scope").insert(name.to_owned(), typ);
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        if let Some(typ) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return Some(typ.clone());
+        }
+        if self.builtins.contains(&name) {
+            return Some(Type::Fn(None));
+        }
+        // regex capture groups are bound by successful matches
+        if name.starts_with('$') {
+            return Some(Type::Any);
+        }
+        None
+    }
+
+    fn lookup_or_error(&mut self, ident: &Ident) -> Type {
+        match self.lookup(&ident.name) {
+            Some(typ) => typ,
+            None => {
+                self.error(CheckErrorKind::UndefinedVar(ident.name.clone()), ident.position);
+                Type::Any
+            }
+        }
+    }
+
+    ///
+    /// The type named by an annotation, which is either a builtin type or a struct
+    ///
+    fn annotated(&self, typ: &Typ) -> Option<Type> {
+        Type::from_annotation(typ).or_else(|| {
+            let name = &typ.ident.name;
+            self.structs.contains(name).then(|| Type::Instance(name.clone()))
+        })
+    }
+
+    fn annotation(&self, typ: &Option<Typ>) -> Type {
+        typ.as_ref().and_then(|typ| self.annotated(typ)).unwrap_or(Type::Any)
+    }
+
+    fn signature(&self, func: &Function) -> Signature {
+        Signature {
+            params: func.args.clone(),
+            args: func.args.iter().map(|arg| self.annotation(&arg.typ)).collect(),
+            ret: self.annotation(&func.ret_typ),
+        }
+    }
+
+    ///
+    /// Declares a struct, which is called with its fields to construct an instance
+    ///
+    fn declare_struct(&mut self, def: &Struct) {
+        self.structs.insert(def.ident.name.clone());
+        let signature = Signature {
+            params: def.fields.clone(),
+            args: def.fields.iter().map(|field| self.annotation(&field.typ)).collect(),
+            ret: Type::Instance(def.ident.name.clone()),
+        };
+        self.declare(&def.ident.name, Type::Fn(Some(signature.into())));
+    }
+
+    ///
+    /// Declares an enum, through which its variants are accessed
+    ///
+    fn declare_enum(&mut self, def: &Enum) {
+        self.structs.insert(def.ident.name.clone());
+        self.enums.insert(def.ident.name.clone(), def.variants.iter().map(|variant| variant.ident.name.clone()).collect());
+        self.declare(&def.ident.name, Type::Any);
+    }
+
+    fn check_annotation(&mut self, typ: &Option<Typ>) {
+        if let Some(typ) = typ {
+            if self.annotated(typ).is_none() {
+                self.error(CheckErrorKind::UnknownType(typ.ident.name.clone()), typ.ident.position);
+            }
+        }
+    }
+
+    fn check_conforms(&mut self, actual: &Type, expected: &Type, position: Position) {
+        if !actual.conforms_to(expected) {
+            self.error(CheckErrorKind::TypeMismatch(expected.name().to_owned(), actual.name().to_owned()), position);
+        }
+    }
+
+    ///
+    /// Checks a function's body, with its arguments in scope, returning the function's type
+    ///
+    fn check_function(&mut self, func: &Function) -> Type {
+        let signature = self.signature(func);
+        self.scopes.push(HashMap::new());
+        for (arg, typ) in func.args.iter().zip(&signature.args) {
+            self.check_annotation(&arg.typ);
+            if let Some(default) = &arg.default {
+                let default_type = self.check_expr(default);
+                self.check_conforms(&default_type, typ, default.position);
+            }
+            let typ = if arg.variadic { Type::List } else { typ.clone() };
+            self.declare(&arg.name.name, typ);
+        }
+        self.check_annotation(&func.ret_typ);
+
+        self.returns.push(signature.ret.clone());
+        let result = self.check_stmts(&func.block.stmts);
+        let position = func.block.stmts.last().map(|stmt| stmt.position).unwrap_or(func.position);
+        self.check_conforms(&result, &signature.ret, position);
+        self.returns.pop();
+        self.scopes.pop();
+        Type::Fn(Some(signature.into()))
+    }
+
+    fn check_stmts(&mut self, stmts: &[Stmt]) -> Type {
+        let mut result = Type::None;
+        for stmt in stmts {
+            result = self.check_stmt(stmt);
+        }
+        result
+    }
+
+    fn check_stmt(&mut self, stmt: &Stmt) -> Type {
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.check_expr(expr),
+            StmtKind::Fn(func) => {
+                // declared before its body is checked, so that it can call itself
+                let signature = self.signature(func);
+                self.declare(&func.ident.name, Type::Fn(Some(signature.into())));
+                self.check_function(func);
+                Type::None
+            }
+            StmtKind::Struct(def) => {
+                for field in &def.fields {
+                    self.check_annotation(&field.typ);
+                }
+                self.declare_struct(def);
+                Type::None
+            }
+            StmtKind::Enum(def) => {
+                for field in def.variants.iter().flat_map(|variant| &variant.fields) {
+                    self.check_annotation(&field.typ);
+                }
+                self.declare_enum(def);
+                Type::None
+            }
+            StmtKind::Impl(imp) => {
+                if !self.structs.contains(&imp.ident.name) {
+                    self.error(CheckErrorKind::UnknownType(imp.ident.name.clone()), imp.ident.position);
+                }
+                for method in &imp.methods {
+                    self.check_function(method);
+                }
+                Type::None
+            }
+        }
+    }
+
+    fn check_block(&mut self, block: &Block) -> Type {
+        self.scopes.push(HashMap::new());
+        let result = self.check_stmts(&block.stmts);
+        self.scopes.pop();
+        result
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Type {
+        use ExprKind::*;
+        match &expr.kind {
+            Let(ident, rhs) => {
+                let typ = match rhs {
+                    Some(rhs) => self.check_expr(rhs),
+                    None => Type::None,
+                };
+                self.declare(&ident.name, typ);
+                Type::None
+            }
+            Assign(lhs, rhs) => {
+                self.check_expr(lhs);
+                self.check_expr(rhs)
+            }
+            AssignOp(lhs, op, rhs) => {
+                let lhs_type = self.check_expr(lhs);
+                let rhs_type = self.check_expr(rhs);
+                self.check_binop(*op, lhs_type, rhs_type, expr.position)
+            }
+            BinOp(lhs, op, rhs) => {
+                let lhs_type = self.check_expr(lhs);
+                let rhs_type = self.check_expr(rhs);
+                self.check_binop(*op, lhs_type, rhs_type, expr.position)
+            }
+            Unary(op, operand) => {
+                let operand_type = self.check_expr(operand);
+                let result = match (op, &operand_type) {
+                    (UnaryOp::Not, _) => Some(Type::Bool),
+                    (_, Type::Any) => Some(Type::Any),
+                    (UnaryOp::Neg, Type::Int | Type::Float) => Some(operand_type.clone()),
+                    (UnaryOp::BitNot, Type::Int) => Some(Type::Int),
+                    _ => None,
+                };
+                result.unwrap_or_else(|| {
+                    self.error(CheckErrorKind::InvalidOperand(*op, operand_type), expr.position);
+                    Type::Any
+                })
+            }
+            For(_, first, second, iterable, block) => {
+                let iterable_type = self.check_expr(iterable);
+                self.scopes.push(HashMap::new());
+                match second {
+                    Some(second) => {
+                        let (key, value) = match iterable_type {
+                            Type::Range => (Type::Int, Type::Int),
+                            Type::Str => (Type::Int, Type::Str),
+                            Type::Map => (Type::Str, Type::Any),
+                            _ => (Type::Int, Type::Any),
+                        };
+                        self.declare(&first.name, key);
+                        self.declare(&second.name, value);
+                    }
+                    None => {
+                        let item = match iterable_type {
+                            Type::Range => Type::Int,
+                            Type::Str | Type::Map => Type::Str,
+                            _ => Type::Any,
+                        };
+                        self.declare(&first.name, item);
+                    }
+                }
+                self.check_block(block);
+                self.scopes.pop();
+                // loops are often left by returning, so what they evaluate to is not known
+                Type::Any
+            }
+            While(_, cond, block) => {
+                self.check_expr(cond);
+                self.check_block(block);
+                Type::Any
+            }
+            If(cond, block, maybe_else) => {
+                self.check_expr(cond);
+                let then_type = self.check_block(block);
+                match maybe_else {
+                    Some(expr) if self.check_expr(expr) == then_type => then_type,
+                    _ => Type::Any,
+                }
+            }
+            Match(subject, arms) => {
+                let subject_type = self.check_expr(subject);
+                let mut result: Option<Type> = None;
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    self.check_pattern(&arm.pattern);
+                    for ident in arm.pattern.bindings() {
+                        let typ = if arm.pattern.is_irrefutable() { subject_type.clone() } else { Type::Any };
+                        self.declare(&ident.name, typ);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.check_expr(guard);
+                    }
+                    let typ = self.check_expr(&arm.body);
+                    self.scopes.pop();
+                    result = match result {
+                        Some(prev) if prev != typ => Some(Type::Any),
+                        _ => Some(typ),
+                    };
+                }
+                self.check_exhaustive(arms, expr.position);
+                // without an arm that matches anything, the match may evaluate to none
+                match result {
+                    Some(typ) if arms.iter().any(|arm| arm.guard.is_none() && arm.pattern.is_irrefutable()) => typ,
+                    _ => Type::Any,
+                }
+            }
+            Block(block) => self.check_block(block),
+            Lambda(func) => self.check_function(func),
+            Call(callee, args, named) => {
+                let callee_type = self.check_expr(callee);
+                let args: Vec<(Type, Position)> = args.iter()
+                    .map(|arg| (self.check_expr(arg), arg.position))
+                    .collect();
+                let named: Vec<(String, (Type, Position))> = named.iter()
+                    .map(|(name, arg)| (name.name.clone(), (self.check_expr(arg), arg.position)))
+                    .collect();
+                match callee_type {
+                    Type::Fn(Some(signature)) => {
+                        self.check_call(&signature, args, named, expr.position);
+                        signature.ret
+                    }
+                    _ => Type::Any,
+                }
+            }
+            Literal(value) => Type::from_value(value),
+            Interpolate(fragments) => {
+                for fragment in fragments {
+                    if let Fragment::Expr(expr) = fragment {
+                        self.check_expr(expr);
+                    }
+                }
+                Type::Str
+            }
+            Ident(ident) => self.lookup_or_error(ident),
+            List(items) => {
+                for item in items {
+                    self.check_expr(item);
+                }
+                Type::List
+            }
+            Map(entries) => {
+                for (key, value) in entries {
+                    self.check_expr(key);
+                    self.check_expr(value);
+                }
+                Type::Map
+            }
+            Index(target, index) => {
+                self.check_expr(target);
+                self.check_expr(index);
+                Type::Any
+            }
+            Field(target, _) => {
+                self.check_expr(target);
+                Type::Any
+            }
+            Ret(value) => {
+                let typ = self.check_expr(value);
+                if let Some(expected) = self.returns.last().cloned() {
+                    self.check_conforms(&typ, &expected, value.position);
+                }
+                Type::Any
+            }
+            Break(_, value) => {
+                if let Some(value) = value {
+                    self.check_expr(value);
+                }
+                Type::Any
+            }
+            Continue(_) => Type::Any,
+            Throw(value) => {
+                self.check_expr(value);
+                Type::Any
+            }
+            Propagate(value) => match self.check_expr(value) {
+                // only values that aren't errors make it past the operator
+                Type::Error => Type::Any,
+                typ => typ,
+            },
+            Try(block, ident, catch) => {
+                let try_type = self.check_block(block);
+                self.scopes.push(HashMap::new());
+                if let Some(ident) = ident {
+                    self.declare(&ident.name, Type::Error);
+                }
+                let catch_type = self.check_block(catch);
+                self.scopes.pop();
+                if try_type == catch_type { try_type } else { Type::Any }
+            }
+        }
+    }
+
+    ///
+    /// Checks that the structs, enums and variants named by a pattern exist
+    ///
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Bind(_) | Pattern::Literal(_) | Pattern::Range(_, _) => {}
+            Pattern::List(items, rest) => {
+                items.iter().chain(rest.as_deref()).for_each(|item| self.check_pattern(item));
+            }
+            Pattern::Struct(name, fields) => {
+                if !self.structs.contains(&name.name) {
+                    self.error(CheckErrorKind::UnknownType(name.name.clone()), name.position);
+                }
+                fields.iter().for_each(|(_, field)| self.check_pattern(field));
+            }
+            Pattern::Variant(name, variant, fields) => {
+                match self.enums.get(&name.name) {
+                    None => self.error(CheckErrorKind::UnknownType(name.name.clone()), name.position),
+                    Some(variants) if !variants.contains(&variant.name) => {
+                        self.error(CheckErrorKind::UnknownVariant(name.name.clone(), variant.name.clone()), variant.position)
+                    }
+                    Some(_) => {}
+                }
+                fields.iter().flatten().for_each(|field| self.check_pattern(field));
+            }
+        }
+    }
+
+    ///
+    /// Reports a match whose arms are enum variants, but which has no arm for some of the
+    /// enum's variants and no arm that matches anything. Arms with guards, or with fields
+    /// that don't match anything, don't count as handling their variant.
+    ///
+    fn check_exhaustive(&mut self, arms: &[Arm], position: Position) {
+        let name = arms.iter().find_map(|arm| match &arm.pattern {
+            Pattern::Variant(name, _, _) => Some(&name.name),
+            _ => None,
+        });
+        let variants = match name.and_then(|name| self.enums.get(name)) {
+            Some(variants) => variants,
+            None => return,
+        };
+        let unguarded = || arms.iter().filter(|arm| arm.guard.is_none());
+        if unguarded().any(|arm| arm.pattern.is_irrefutable()) {
+            return;
+        }
+        let handles = |variant: &String| unguarded().any(|arm| matches!(
+            &arm.pattern,
+            Pattern::Variant(_, ident, fields) if ident.name == *variant && fields.iter().flatten().all(Pattern::is_irrefutable)
+        ));
+        let missing: Vec<String> = variants.iter().filter(|variant| !handles(variant)).cloned().collect();
+        if !missing.is_empty() {
+            let name = name.cloned().unwrap_or_default();
+            self.error(CheckErrorKind::NonExhaustiveMatch(name, missing), position);
+        }
+    }
+
+    ///
+    /// Checks that the arguments of a call can be bound to the parameters of signature,
+    /// and that each argument matches its parameter's annotation.
+    ///
+    fn check_call(&mut self, signature: &Signature, args: Vec<(Type, Position)>, named: Vec<(String, (Type, Position))>, position: Position) {
+        let bindings = match ArgSpec::bind(&signature.params, args, named) {
+            Ok(bindings) => bindings,
+            Err(err) => return self.error(err.into(), position),
+        };
+        for (arg, expected) in bindings.args.iter().zip(&signature.args) {
+            if let Some((actual, position)) = arg {
+                self.check_conforms(actual, expected, *position);
+            }
+        }
+        // the variadic parameter's annotation applies to each of the extra arguments
+        if let Some(expected) = signature.args.get(bindings.args.len()) {
+            for (actual, position) in &bindings.rest {
+                self.check_conforms(actual, expected, *position);
+            }
+        }
+    }
+
+    ///
+    /// Reports operands that the operator can never be applied to, returning
+    /// the type of the result.
+    ///
+    fn check_binop(&mut self, op: BinOp, lhs: Type, rhs: Type, position: Position) -> Type {
+        match binop_type(op, &lhs, &rhs) {
+            Some(result) => result,
+            None => {
+                self.error(CheckErrorKind::InvalidOperands(op, lhs, rhs), position);
+                Type::Any
+            }
+        }
+    }
+}
+
+///
+/// The type of the result of a binary operation, or None if the operands
+/// are not supported by the operator. This mirrors the interpreter's operations.
+///
+fn binop_type(op: BinOp, lhs: &Type, rhs: &Type) -> Option<Type> {
+    use BinOp::*;
+    use Type::{Any, Bool, Float, Int, List, Regex, Str};
+
+    let numeric = lhs.is_numeric() && rhs.is_numeric();
+    let ints = *lhs == Int && *rhs == Int;
+    match op {
+        EqEq | NotEq | Lt | Gt | LtEq | GtEq => Some(Bool),
+        // evaluates to one of the operands
+        And | Or => Some(if lhs == rhs { lhs.clone() } else { Any }),
+        _ if *lhs == Any || *rhs == Any => Some(match op {
+            Div => Float,
+            TildeEq => Bool,
+            Range | RangeInclusive => Type::Range,
+            _ => Any,
+        }),
+        Add => match (lhs, rhs) {
+            (Str, Str) => Some(Str),
+            (List, List) => Some(List),
+            _ if ints => Some(Int),
+            _ if numeric => Some(Float),
+            _ => None,
+        },
+        Sub | Mul | Mod if ints => Some(Int),
+        Sub | Mul | Mod | Div if numeric => Some(Float),
+        // negative integer powers result in a float
+        Pow if ints => Some(Any),
+        Pow if numeric => Some(Float),
+        Xor | BinAnd | BinOr | ShiftLeft | ShiftRight if ints => Some(Int),
+        TildeEq if *lhs == Str && matches!(rhs, Str | Regex) => Some(Bool),
+        Range | RangeInclusive if ints => Some(Type::Range),
+        Eq => Some(rhs.clone()),
+        _ => None,
+    }
+}
+
+///
+/// Finds the names that are assigned to directly, anywhere in the script.
+///
+fn reassigned_names(stmts: &[Stmt]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for stmt in stmts {
+        visit_stmt(stmt, &mut names);
+    }
+    names
+}
+
+fn visit_stmt(stmt: &Stmt, names: &mut HashSet<String>) {
+    match &stmt.kind {
+        StmtKind::Expr(expr) => visit_expr(expr, names),
+        StmtKind::Fn(func) => visit_function(func, names),
+        StmtKind::Struct(_) | StmtKind::Enum(_) => {}
+        StmtKind::Impl(imp) => {
+            for method in &imp.methods {
+                visit_function(method, names);
+            }
+        }
+    }
+}
+
+fn visit_function(func: &Function, names: &mut HashSet<String>) {
+    for default in func.args.iter().filter_map(|arg| arg.default.as_ref()) {
+        visit_expr(default, names);
+    }
+    visit_block(&func.block, names);
+}
+
+fn visit_block(block: &Block, names: &mut HashSet<String>) {
+    for stmt in &block.stmts {
+        visit_stmt(stmt, names);
+    }
+}
+
+fn visit_expr(expr: &Expr, names: &mut HashSet<String>) {
+    use ExprKind::*;
+    match &expr.kind {
+        Assign(lhs, rhs) | AssignOp(lhs, _, rhs) => {
+            if let Ident(ident) = &lhs.kind {
+                names.insert(ident.name.clone());
+            }
+            visit_expr(lhs, names);
+            visit_expr(rhs, names);
+        }
+        Let(_, rhs) => {
+            if let Some(rhs) = rhs {
+                visit_expr(rhs, names);
+            }
+        }
+        BinOp(lhs, _, rhs) | Index(lhs, rhs) => {
+            visit_expr(lhs, names);
+            visit_expr(rhs, names);
+        }
+        Unary(_, operand) | Field(operand, _) | Ret(operand) | Throw(operand) | Propagate(operand) => visit_expr(operand, names),
+        For(_, _, _, iterable, block) | While(_, iterable, block) => {
+            visit_expr(iterable, names);
+            visit_block(block, names);
+        }
+        If(cond, block, maybe_else) => {
+            visit_expr(cond, names);
+            visit_block(block, names);
+            if let Some(expr) = maybe_else {
+                visit_expr(expr, names);
+            }
+        }
+        Match(subject, arms) => {
+            visit_expr(subject, names);
+            for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    visit_expr(guard, names);
+                }
+                visit_expr(&arm.body, names);
+            }
+        }
+        Block(block) => visit_block(block, names),
+        Try(block, _, catch) => {
+            visit_block(block, names);
+            visit_block(catch, names);
+        }
+        Lambda(func) => visit_function(func, names),
+        Call(callee, args, named) => {
+            visit_expr(callee, names);
+            for arg in args {
+                visit_expr(arg, names);
+            }
+            for (_, arg) in named {
+                visit_expr(arg, names);
+            }
+        }
+        Interpolate(fragments) => {
+            for fragment in fragments {
+                if let Fragment::Expr(expr) = fragment {
+                    visit_expr(expr, names);
+                }
+            }
+        }
+        List(items) => {
+            for item in items {
+                visit_expr(item, names);
+            }
+        }
+        Map(entries) => {
+            for (key, value) in entries {
+                visit_expr(key, names);
+                visit_expr(value, names);
+            }
+        }
+        Break(_, value) => {
+            if let Some(value) = value {
+                visit_expr(value, names);
+            }
+        }
+        Literal(_) | Ident(_) | Continue(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use common::Position;
+    use tokenizer::{FromTokens, TokenStream};
+
+    use crate::{BinOp, Stmt, UnaryOp};
+    use crate::checker::{check, CheckError, CheckErrorKind, Type};
+    use crate::checker::CheckErrorKind::*;
+
+    fn check_script(script: &str) -> Vec<CheckError> {
+        let tokens = TokenStream::new(script);
+        let mut stmts = Vec::new();
+        while !tokens.is_empty() {
+            stmts.push(Stmt::from_tokens(&tokens).unwrap());
+        }
+        check(&stmts, &["print", "len"])
+    }
+
+    fn error(kind: CheckErrorKind, line: usize, column: usize) -> CheckError {
+        CheckError::new(kind, Position { line, column })
+    }
+
+    #[test]
+    fn test_valid_script() {
+        let script = r#"
+fn count(pattern: regex, text: str) -> int {
+    let total = 0;
+    for line in text {
+        if line ~= pattern {
+            total += 1;
+        }
+    }
+    total
+}
+let n = count("a+", "abc") + len([1, 2]) * 2.5;
+let add = |a, b| a + b;
+let name = none;
+name = "x";
+print(add(n, 1), name + "y", later(), $1)
+fn later() {
+    n / 2
+}
+"#;
+        assert_eq!(check_script(script), vec![]);
+    }
+
+    #[test]
+    fn test_undefined_var() {
+        assert_eq!(check_script("let a = 1;\na + b"), vec![error(UndefinedVar("b".to_string()), 2, 5)]);
+        assert_eq!(check_script("fn f() { x }"), vec![error(UndefinedVar("x".to_string()), 1, 10)]);
+        assert_eq!(check_script("{ let a = 1 }\na"), vec![error(UndefinedVar("a".to_string()), 2, 1)]);
+        let script = "fn outer() {\n    fn fact(n) { if n < 2 { 1 } else { n * fact(n - 1) } }\n    fact(3)\n}";
+        assert_eq!(check_script(script), vec![]);
+        // names are resolved lexically, not in the caller's scope
+        assert_eq!(
+            check_script("fn f() { x }\nfn g() { let x = 1; f() }"),
+            vec![error(UndefinedVar("x".to_string()), 1, 10)]
+        );
+    }
+
+    #[test]
+    fn test_unexpected_args() {
+        assert_eq!(check_script("fn f(a, b) { a }\nf(1)"), vec![error(MissingArg("b".to_string()), 2, 1)]);
+        assert_eq!(check_script("let g = || 1;\ng(1, 2)"), vec![error(UnexpectedArgs(0, 2), 2, 1)]);
+    }
+
+    #[test]
+    fn test_default_and_named_args() {
+        let script = "fn f(a: int, b: str = \"x\", ...rest: int) { a }\nf(1, \"z\", 2, 3);\nf(b = \"y\", a = 1)";
+        assert_eq!(check_script(script), vec![]);
+        assert_eq!(check_script("fn f(a, b = 1) { a }\nf(1, c = 2)"), vec![error(UnknownArg("c".to_string()), 2, 1)]);
+        assert_eq!(check_script("fn f(a, b = 1) { a }\nf(1, a = 2)"), vec![error(DuplicateArg("a".to_string()), 2, 1)]);
+        assert_eq!(
+            check_script("fn f(a: int = \"one\", ...rest: str) { a }\nf(1, \"two\", 3)"),
+            vec![
+                error(TypeMismatch("int".to_string(), "str".to_string()), 1, 15),
+                error(TypeMismatch("str".to_string(), "int".to_string()), 2, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        assert_eq!(
+            check_script("fn f(a: int) -> str { a }\nf(\"x\")"),
+            vec![
+                error(TypeMismatch("str".to_string(), "int".to_string()), 1, 23),
+                error(TypeMismatch("int".to_string(), "str".to_string()), 2, 3),
+            ]
+        );
+        assert_eq!(check_script("fn f(a: integer) { a }"), vec![error(UnknownType("integer".to_string()), 1, 9)]);
+        assert_eq!(check_script("fn f() -> int { return none }"), vec![error(TypeMismatch("int".to_string(), "none".to_string()), 1, 24)]);
+    }
+
+    #[test]
+    fn test_operand_types() {
+        assert_eq!(
+            check_script("let a = 1 + \"b\";\n-\"c\""),
+            vec![
+                error(InvalidOperands(BinOp::Add, Type::Int, Type::Str), 1, 9),
+                error(InvalidOperand(UnaryOp::Neg, Type::Str), 2, 1),
+            ]
+        );
+        assert_eq!(check_script("fn f(s: str) { s * 2 }"), vec![error(InvalidOperands(BinOp::Mul, Type::Str, Type::Int), 1, 16)]);
+        assert_eq!(check_script("let x = [1];\nlet y = x - 1;\ny -= 1"), vec![error(InvalidOperands(BinOp::Sub, Type::List, Type::Int), 2, 9)]);
+    }
+
+    #[test]
+    fn test_structs() {
+        let script = r#"
+fn norm(p: Point) -> int {
+    p.sum()
+}
+struct Point { x: int, y: int }
+impl Point {
+    fn sum(self) { self.x + self.y }
+}
+norm(Point(1, y = 2))
+"#;
+        assert_eq!(check_script(script), vec![]);
+        assert_eq!(check_script("struct Point { x: int }\nPoint(\"1\")"), vec![error(TypeMismatch("int".to_string(), "str".to_string()), 2, 7)]);
+        assert_eq!(check_script("struct Point { x }\nPoint()"), vec![error(MissingArg("x".to_string()), 2, 1)]);
+        assert_eq!(
+            check_script("struct Point { x }\nfn f(p: Point) { p }\nf(1)"),
+            vec![error(TypeMismatch("Point".to_string(), "int".to_string()), 3, 3)]
+        );
+        assert_eq!(check_script("struct Point { x: integer }"), vec![error(UnknownType("integer".to_string()), 1, 19)]);
+        assert_eq!(check_script("impl Point {}"), vec![error(UnknownType("Point".to_string()), 1, 6)]);
+    }
+
+    #[test]
+    fn test_match() {
+        let script = r#"
+enum Shape { Circle(r: float), Rect(w, h), Empty }
+fn area(s: Shape) -> float {
+    match s {
+        Shape.Circle(r) => 3.14 * r * r,
+        Shape.Rect(w, h) => w * h,
+        Shape.Empty => 0.0,
+    }
+}
+area(Shape.Circle(1.0))
+"#;
+        assert_eq!(check_script(script), vec![]);
+        let missing = |variants: &[&str]| NonExhaustiveMatch("Shape".to_string(), variants.iter().map(|v| v.to_string()).collect());
+        assert_eq!(
+            check_script("enum Shape { Circle(r), Empty }\nmatch Shape.Empty {\n    Shape.Circle(r) => r,\n}"),
+            vec![error(missing(&["Empty"]), 2, 1)]
+        );
+        assert!(error(missing(&["Empty"]), 2, 1).is_warning());
+        assert_eq!(
+            check_script("enum Shape { Circle(r), Empty }\nmatch Shape.Empty { Shape.Circle(r) if r > 1 => r, Shape.Circle(0) => 0, Shape.Empty => 0 }"),
+            vec![error(missing(&["Circle"]), 2, 1)]
+        );
+        assert_eq!(check_script("enum Shape { Circle(r), Empty }\nmatch Shape.Empty { Shape.Empty => 0, _ => 1 }"), vec![]);
+        assert_eq!(
+            check_script("enum Shape { Empty }\nmatch 1 { Shape.Square => 1, _ => 2 }"),
+            vec![error(UnknownVariant("Shape".to_string(), "Square".to_string()), 2, 17)]
+        );
+        assert_eq!(check_script("match 1 { Point { x } => x }"), vec![error(UnknownType("Point".to_string()), 1, 11)]);
+        assert_eq!(check_script("match [1, 2] { [a, ...rest] => a }\na"), vec![error(UndefinedVar("a".to_string()), 2, 1)]);
+        assert_eq!(
+            check_script("match 1 { n => n + \"1\" }"),
+            vec![error(InvalidOperands(BinOp::Add, Type::Int, Type::Str), 1, 16)]
+        );
+    }
+
+    #[test]
+    fn test_try() {
+        assert_eq!(check_script("fn f() -> int { try { 1 } catch { 2 } }\nthrow f()"), vec![]);
+        assert_eq!(check_script("try { 1 } catch e { e.message }\ne"), vec![error(UndefinedVar("e".to_string()), 2, 1)]);
+        assert_eq!(
+            check_script("try { 1 } catch e { e - 1 }"),
+            vec![error(InvalidOperands(BinOp::Sub, Type::Error, Type::Int), 1, 21)]
+        );
+        assert_eq!(
+            check_script("fn f() -> int { try { \"1\" } catch { \"2\" } }"),
+            vec![error(TypeMismatch("int".to_string(), "str".to_string()), 1, 17)]
+        );
+        assert_eq!(check_script("fn f() -> int { try { 1 } catch { \"none\" } }"), vec![]);
+        assert_eq!(check_script("fn f(s) -> int { parse(s)? }\nfn parse(s) { s }"), vec![]);
+    }
+}
@@ -15,12 +15,21 @@ impl Display for ParserError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let desc = match self.kind {
             ErrorKind::InvalidToken(tok) => format!("invalid token: {:?}", tok),
-            ErrorKind::InvalidLit => format!("invalid literal"),
-            ErrorKind::UnexpectedEOL => format!("unexpected end-of-line"),
-            ErrorKind::UnmatchedExpr => format!("unmatched expression. Probably contains too many operators, or too few operands"),
-            ErrorKind::ExpectedOperator => format!("expected operator, but didn't find one"),
+            ErrorKind::InvalidLit => "invalid literal".to_string(),
+            ErrorKind::UnexpectedEOL => "unexpected end-of-line".to_string(),
+            ErrorKind::UnmatchedExpr => "unmatched expression. Probably contains too many operators, or too few operands".to_string(),
+            ErrorKind::ExpectedOperator => "expected operator, but didn't find one".to_string(),
             ErrorKind::ExpectedIdent(ident) => format!("expected '{}'", ident),
-            ErrorKind::ExpectSemiColon => format!("expected semi-colon"),
+            ErrorKind::ExpectSemiColon => "expected semi-colon".to_string(),
+            ErrorKind::InvalidRegex => "invalid regex literal".to_string(),
+            ErrorKind::InvalidEscape(c) => format!("invalid escape sequence '\\{}'", c),
+            ErrorKind::UnterminatedString => "unterminated string literal".to_string(),
+            ErrorKind::UnterminatedComment => "unterminated block comment".to_string(),
+            ErrorKind::InvalidChar(c) => format!("invalid character '{}'", c),
+            ErrorKind::PositionalAfterNamed => "positional argument follows a named argument".to_string(),
+            ErrorKind::VariadicNotLast => "variadic parameter must be the last parameter".to_string(),
+            ErrorKind::ExpectedLoop => "expected a loop after the label".to_string(),
+            ErrorKind::UnknownLabel => "no enclosing loop has this label".to_string(),
             ErrorKind::ExpectedTokens(tokens) => {
                 // TODO: make ExpectedTokens description not a debug thing
                 format!("expected one of {:?}", tokens)
@@ -47,6 +56,15 @@ pub enum ErrorKind {
     ExpectedOperator,
     ExpectedIdent(&'static str),
     ExpectSemiColon,
+    InvalidEscape(char),
+    UnterminatedString,
+    UnterminatedComment,
+    InvalidChar(char),
+    InvalidRegex,
+    PositionalAfterNamed,
+    VariadicNotLast,
+    ExpectedLoop,
+    UnknownLabel,
     ExpectedTokens(&'static [TokenKind]),
 }
 
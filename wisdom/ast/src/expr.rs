@@ -1,14 +1,14 @@
 use std::fmt::{Debug, Formatter};
 use std::fmt;
+use std::str::FromStr;
 
 use common::Position;
-use tokenizer::{FromTokens, Token, TokenStream, TokenKind};
+use tokenizer::{BinOpKind, FromTokens, Segment, Token, TokenStream, TokenKind, LiteralKind};
 use tokenizer::TokenKind::*;
 
-use crate::{BinOp, Block, Ident, Value};
-use crate::error::ErrorKind::UnmatchedExpr;
-use crate::error::ParserError;
-use crate::ext::VecPopTwo;
+use crate::{ArgSpec, Associativity, BinOp, Block, Function, Ident, Pattern, Stmt, StmtKind, Typ, UnaryOp, Value, unescape};
+use crate::error::ErrorKind::{ExpectedIdent, ExpectedLoop, ExpectedOperator, InvalidChar, InvalidToken, PositionalAfterNamed, UnmatchedExpr};
+use crate::error::{ErrorKind, ParserError};
 
 use serde::{Serialize, Deserialize};
 
@@ -29,29 +29,55 @@ pub enum ExprKind {
     /// i.e. foo[1] = 10;
     Assign(Box<Expr>, Box<Expr>),
     /// a += 10
-    // TODO: AssignOp(Expr, Expr),
+    AssignOp(Box<Expr>, BinOp, Box<Expr>),
     /// a + 5
     BinOp(Box<Expr>, BinOp, Box<Expr>),
-    /// `for <expr> { <block> }`
-    // For(Expr, Block),
-    /// `while <expr> { <block> }`
-    While(Box<Expr>, Block),
+    /// -a, !a or ~a
+    Unary(UnaryOp, Box<Expr>),
+    /// `for <ident> in <expr> { <block> }` or `for <ident>, <ident> in <expr> { <block> }`,
+    /// with an optional label `'outer: for ...`
+    For(Option<Ident>, Ident, Option<Ident>, Box<Expr>, Block),
+    /// `while <expr> { <block> }`, with an optional label `'outer: while ...`
+    While(Option<Ident>, Box<Expr>, Block),
     /// `if <expr> { <block> } else { <block> }
     If(Box<Expr>, Block, Option<Box<Expr>>),
+    /// `match <expr> { <pattern> if <guard> => <expr>, ... }`, which evaluates the first
+    /// arm whose pattern matches
+    Match(Box<Expr>, Vec<Arm>),
     /// { <expr> }
     Block(Block),
-    /// foo(a, b)
-    Call(Box<Expr>, Vec<Box<Expr>>),
+    /// An anonymous function `|a, b| a + b` or `fn(a, b) { a + b }`
+    Lambda(Function),
+    /// foo(a, b), with any named arguments following the positional ones i.e. foo(a, sep = b)
+    Call(Box<Expr>, Vec<Box<Expr>>, Vec<(Ident, Expr)>),
     /// A literal `1`, `"two"` etc
     Literal(Value),
+    /// An interpolated string `"two is ${1 + 1}"`
+    Interpolate(Vec<Fragment>),
     /// A named identifier (variable)
     Ident(Ident),
+    /// A list literal `[1, "two", 3]`
+    List(Vec<Expr>),
+    /// A map literal `{"one": 1, "two": 2}`
+    Map(Vec<(Expr, Expr)>),
+    /// An index into a value `foo[1]`
+    Index(Box<Expr>, Box<Expr>),
+    /// A named field of a value `foo.bar`
+    Field(Box<Expr>, Ident),
     /// A return statement
     Ret(Box<Expr>),
-    /// A break expression, with optional label.
-    Break(Option<Ident>),
+    /// A break expression, with optional label and the value of the loop `break 'outer 10`
+    Break(Option<Ident>, Option<Box<Expr>>),
     /// A continue expression, with optional label
     Continue(Option<Ident>),
+    /// `throw <expr>` (or `raise <expr>`), which raises an error
+    Throw(Box<Expr>),
+    /// `try { <block> } catch <ident> { <block> }`, which evaluates the catch block
+    /// with the error bound to the (optional) name if the try block raises one
+    Try(Block, Option<Ident>, Block),
+    /// `<expr>?`, which returns an error value from the enclosing function,
+    /// or otherwise evaluates to the value
+    Propagate(Box<Expr>),
 }
 
 impl Debug for ExprKind {
@@ -59,20 +85,56 @@ impl Debug for ExprKind {
         match self {
             ExprKind::Let(_, _) => write!(f, "ExprKind::Let"),
             ExprKind::Assign(_, _) => write!(f, "ExprKind::Assign"),
+            ExprKind::AssignOp(_, _, _) => write!(f, "ExprKind::AssignOp"),
             ExprKind::BinOp(_, _, _) => write!(f, "ExprKind::BinOp"),
-            ExprKind::Call(_, _) => write!(f, "ExprKind::Call"),
+            ExprKind::Unary(_, _) => write!(f, "ExprKind::Unary"),
+            ExprKind::Lambda(_) => write!(f, "ExprKind::Lambda"),
+            ExprKind::Call(_, _, _) => write!(f, "ExprKind::Call"),
             ExprKind::Literal(_) => write!(f, "ExprKind::Literal"),
+            ExprKind::Interpolate(_) => write!(f, "ExprKind::Interpolate"),
             ExprKind::Ident(_) => write!(f, "ExprKind::Ident"),
-            ExprKind::While(_, _) => write!(f, "ExprKind::While"),
+            ExprKind::List(_) => write!(f, "ExprKind::List"),
+            ExprKind::Map(_) => write!(f, "ExprKind::Map"),
+            ExprKind::Index(_, _) => write!(f, "ExprKind::Index"),
+            ExprKind::Field(_, _) => write!(f, "ExprKind::Field"),
+            ExprKind::For(_, _, _, _, _) => write!(f, "ExprKind::For"),
+            ExprKind::While(_, _, _) => write!(f, "ExprKind::While"),
             ExprKind::If(_, _, _) => write!(f, "ExprKind::If"),
+            ExprKind::Match(_, _) => write!(f, "ExprKind::Match"),
             ExprKind::Block(_) => write!(f, "ExprKind::Block"),
             ExprKind::Ret(_) => write!(f, "ExprKind::Ret"),
-            ExprKind::Break(_) => write!(f, "ExprKind::Break"),
-            ExprKind::Continue(_) => write!(f, "ExprKind::Continue")
+            ExprKind::Break(_, _) => write!(f, "ExprKind::Break"),
+            ExprKind::Continue(_) => write!(f, "ExprKind::Continue"),
+            ExprKind::Throw(_) => write!(f, "ExprKind::Throw"),
+            ExprKind::Propagate(_) => write!(f, "ExprKind::Propagate"),
+            ExprKind::Try(_, _, _) => write!(f, "ExprKind::Try")
         }
     }
 }
 
+///
+/// A single part of an interpolated string
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Fragment {
+    /// Literal text, with escapes already processed
+    Text(String),
+    /// An expression to be evaluated and displayed in place
+    Expr(Expr),
+}
+
+///
+/// A single arm of a match expression `<pattern> if <guard> => <expr>`
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Arm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    #[serde(skip)]
+    pub position: Position,
+}
+
 // TODO: more error construction helpers would be very useful
 macro_rules! expect_or_error {
     ($tokens:ident, $token:ident) => {
@@ -94,123 +156,370 @@ impl Expr {
     /// Recursively parses an expression from the token stream.
     ///
     fn parse_expr(tokens: &TokenStream) -> Result<Self, ParserError> {
-        let mut operators: Vec<BinOp> = Vec::new();
-        let mut operands: Vec<Expr> = Vec::new();
-
-        let mut peeked = tokens.peek();
-        while let Some(tok) = &peeked {
-            match tok.kind {
-                Whitespace => {}
-                LeftParen => {
-                    tokens.consume();
-                    operands.push(Expr::from_tokens(tokens)?);
-                    expect_or_error!(tokens, RightParen)?;
+        Expr::parse_binary(tokens, BinOp::LOWEST_PRECEDENCE)
+    }
+
+    ///
+    /// Parses an expression statement. A statement that starts with control flow ends
+    /// with its block, so that `if a {b} -c` is two statements, whereas anywhere else
+    /// control flow is an operand like any other, as in `let x = if a {b} else {c} / 2`.
+    ///
+    pub(crate) fn parse_statement(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let lhs = Expr::parse_unary(tokens)?;
+        if lhs.is_control_flow() {
+            return Ok(lhs);
+        }
+        Expr::parse_operators(lhs, tokens, BinOp::LOWEST_PRECEDENCE)
+    }
+
+    ///
+    /// Whether this is a control flow expression, which ends in a block
+    ///
+    fn is_control_flow(&self) -> bool {
+        matches!(self.kind, ExprKind::If(..) | ExprKind::While(..) | ExprKind::For(..) | ExprKind::Match(..) | ExprKind::Try(..))
+    }
+
+    ///
+    /// Parses a chain of binary operations, using precedence climbing. Only operators
+    /// that bind at least as tightly as max_precedence are consumed, anything looser
+    /// is left for the caller.
+    ///
+    /// The right hand side of a left-associative operator may only contain tighter
+    /// operators, so that `a - b - c` groups as `(a - b) - c`, whereas right-associative
+    /// operators also allow the same precedence, so that `a = b = c` is `a = (b = c)`
+    ///
+    pub(crate) fn parse_binary(tokens: &TokenStream, max_precedence: usize) -> Result<Self, ParserError> {
+        let lhs = Expr::parse_unary(tokens)?;
+        Expr::parse_operators(lhs, tokens, max_precedence)
+    }
+
+    ///
+    /// Parses the binary operations that follow an already parsed left hand side.
+    ///
+    fn parse_operators(mut lhs: Expr, tokens: &TokenStream, max_precedence: usize) -> Result<Self, ParserError> {
+        while let Some(tok) = tokens.peek() {
+            if !tok.kind.is_operator() {
+                break;
+            }
+            // compound assignments group like plain assignment
+            let (op, compound) = match BinOp::from_compound_assignment(tok.kind) {
+                Some(op) => (op, true),
+                None => {
+                    let op = BinOp::from_str(&tok.literal).map_err(|_| ParserError::new(ExpectedOperator, Some(tok.position)))?;
+                    (op, false)
                 }
-                Literal { .. } => {
-                    // TODO: perhaps a literal should just contain the string repr (and move Value somewhere else)
-                    let value = Value::from_tokens(tokens)?;
-                    let value = ExprKind::Literal(value);
-                    operands.push(Expr::new(value, tok.position.clone()));
+            };
+            let grouping = if compound { BinOp::Eq } else { op };
+            let precedence = grouping.precedence();
+            if precedence > max_precedence {
+                break;
+            }
+            tokens.consume();
+
+            let rhs_precedence = match grouping.associativity() {
+                Associativity::Left => precedence - 1,
+                Associativity::Right => precedence,
+            };
+            let rhs = Expr::parse_binary(tokens, rhs_precedence)?;
+            let position = lhs.position;
+            let kind = match op {
+                _ if compound => ExprKind::AssignOp(lhs.into(), op, rhs.into()),
+                BinOp::Eq => ExprKind::Assign(lhs.into(), rhs.into()),
+                _ => ExprKind::BinOp(lhs.into(), op, rhs.into()),
+            };
+            lhs = Expr::new(kind, position);
+        }
+        Ok(lhs)
+    }
+
+    ///
+    /// Parses an operand, with any prefix operators. Prefix operators bind more tightly
+    /// than any binary operator other than `**`, and apply to the whole operand including
+    /// any indexing i.e. `-a[0]` is `-(a[0])` and `-2 ** 2` is `-(2 ** 2)`. Negation of a
+    /// numeric literal is folded into the literal.
+    ///
+    fn parse_unary(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.peek().ok_or(ParserError::new(UnmatchedExpr, tokens.position()))?;
+        match UnaryOp::from_token(tok.kind) {
+            Some(op) => {
+                tokens.consume();
+                let operand = Expr::parse_unary(tokens)?;
+                let operand = Expr::parse_operators(operand, tokens, BinOp::Pow.precedence())?;
+                let position = operand.position;
+                let kind = match (op, operand.kind) {
+                    (UnaryOp::Neg, ExprKind::Literal(Value::Int(n))) => ExprKind::Literal(Value::Int(-n)),
+                    (UnaryOp::Neg, ExprKind::Literal(Value::Float(n))) => ExprKind::Literal(Value::Float(-n)),
+                    (op, kind) => ExprKind::Unary(op, Expr::new(kind, position).into()),
+                };
+                Ok(Expr::new(kind, tok.position))
+            }
+            None => Expr::parse_postfix(tokens),
+        }
+    }
+
+    ///
+    /// Parses a primary expression, followed by any number of indexes, field accesses
+    /// or calls, i.e. `foo[0].bar(1)`. Anything can be called, so long as it evaluates
+    /// to a function.
+    ///
+    fn parse_postfix(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let mut expr = Expr::parse_primary(tokens)?;
+        if expr.is_control_flow() {
+            return Ok(expr);
+        }
+        while let Some(tok) = tokens.peek() {
+            expr = match tok.kind {
+                LeftBracket => Expr::parse_index(expr, tokens)?,
+                LeftParen => Expr::parse_call(expr, tokens)?,
+                Dot => Expr::parse_field(expr, tokens)?,
+                Question => {
+                    tokens.consume();
+                    let position = expr.position;
+                    Expr::new(ExprKind::Propagate(expr.into()), position)
                 }
-                Identifier => {
-                    match tok.literal.as_str() {
-                        // TODO: better way of doing this kind of literal processing?
-                        "true" | "false" => {
-                            let value = Value::from_tokens(tokens)?;
-                            let value = ExprKind::Literal(value);
-                            operands.push(Expr::new(value, tok.position.clone()));
-                        }
-                        "while" => return Expr::parse_while(tokens),
-                        "if" => return Expr::parse_if(tokens),
-                        "return" => return Expr::parse_return(tokens),
-                        "let" => return Expr::parse_let(tokens),
-                        "break" => return Expr::parse_break(tokens),
-                        "continue" => return Expr::parse_continue(tokens),
-                        _ => {
-                            operands.push(Expr::parse_ident(tok, tokens)?)
-                        }
+                _ => break,
+            };
+        }
+        Ok(expr)
+    }
+
+    ///
+    /// Parses a single operand: a literal, identifier, call, parenthesised expression,
+    /// list, map, block or lambda, or one of the keyword expressions.
+    ///
+    fn parse_primary(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.peek().ok_or(ParserError::new(UnmatchedExpr, tokens.position()))?;
+        match tok.kind {
+            LeftParen => {
+                tokens.consume();
+                let expr = Expr::from_tokens(tokens)?;
+                expect_or_error!(tokens, RightParen)?;
+                Ok(expr)
+            }
+            LeftBracket => Expr::parse_list(&tok, tokens),
+            LeftBrace if Expr::is_map_start(tokens) => Expr::parse_map(&tok, tokens),
+            LeftBrace => Ok(Expr::new(ExprKind::Block(Block::from_tokens(tokens)?), tok.position)),
+            OrOr | BinOp(BinOpKind::Or) => Expr::parse_lambda(&tok, tokens),
+            Label => Expr::parse_labeled(&tok, tokens),
+            Literal { kind: LiteralKind::InterpolatedString } => Expr::parse_interpolated(&tok, tokens),
+            Literal { .. } => {
+                // TODO: perhaps a literal should just contain the string repr (and move Value somewhere else)
+                let value = Value::from_tokens(tokens)?;
+                Ok(Expr::new(ExprKind::Literal(value), tok.position))
+            }
+            Identifier => {
+                match tok.literal.as_str() {
+                    // TODO: better way of doing this kind of literal processing?
+                    "true" | "false" | "none" => {
+                        let value = Value::from_tokens(tokens)?;
+                        Ok(Expr::new(ExprKind::Literal(value), tok.position))
                     }
+                    "while" => Expr::parse_while(None, tokens),
+                    "for" => Expr::parse_for(None, tokens),
+                    "if" => Expr::parse_if(tokens),
+                    "match" => Expr::parse_match(tokens),
+                    "return" => Expr::parse_return(tokens),
+                    "throw" | "raise" => Expr::parse_throw(tokens),
+                    "try" if matches!(tokens.second(), Some(Token { kind: LeftBrace, .. })) => Expr::parse_try(tokens),
+                    "let" => Expr::parse_let(tokens),
+                    "break" => Expr::parse_break(tokens),
+                    "continue" => Expr::parse_continue(tokens),
+                    "fn" if matches!(tokens.second(), Some(Token { kind: LeftParen, .. })) => Expr::parse_lambda(&tok, tokens),
+                    _ => Expr::parse_ident(&tok, tokens),
                 }
-                _ if tok.kind.is_operator() => {
-                    let op = BinOp::from_tokens(tokens)?;
-
-                    if !operators.is_empty() {
-                        // keep checking against stored operators until we have a higher
-                        // precedence
-                        while let Some(top) = operators.get(0) {
-                            if top.precedence() <= op.precedence() {
-                                //
-                                // If the top of the stack has higher precedence
-                                // or the same as the current op, then we can process a tree.
-                                // We continue to do this until we hit an operator of lower precedence
-                                // in the stack.
-                                // TODO: this will need to be tweaked when we introduce right-associative operators
-                                //
-                                let (rhs, lhs) = operands.pop_two().ok_or(
-                                    ParserError::new(UnmatchedExpr, Some(tok.position))
-                                )?;
-                                let position = lhs.position.clone();
-                                let expr = ExprKind::BinOp(lhs.into(), *top, rhs.into());
-                                // pop the 'top' operator cos we've just used it
-                                operators.pop();
-                                // push the result for next operator / unwinding later
-                                operands.push(Expr::new(expr, position));
-                            } else {
-                                operators.push(op);
-                                break;
-                            }
-                        }
-                        // no operators left and we haven't added the current
-                        // operator so add it now
-                        if operators.is_empty() {
-                            operators.push(op);
+            }
+            Unknown => {
+                let c = tok.literal.chars().next().unwrap_or_default();
+                Err(ParserError::new(InvalidChar(c), Some(tok.position)))
+            }
+            UnterminatedComment => Err(ParserError::new(ErrorKind::UnterminatedComment, Some(tok.position))),
+            _ => Err(ParserError::new(UnmatchedExpr, Some(tok.position))),
+        }
+    }
+
+    ///
+    /// Parses an interpolated string literal, expected to be the next token,
+    /// into its text and expression fragments.
+    ///
+    fn parse_interpolated(tok: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let mut fragments = Vec::new();
+        for segment in tokenizer::interpolation_segments(tok) {
+            fragments.push(match segment {
+                Segment::Text(text, position) => Fragment::Text(unescape(&text, position)?),
+                Segment::Expr(source, position) => {
+                    let inner = TokenStream::new_at(&source, position);
+                    // an error at the end of the expression has no token to point at,
+                    // so point at the closing brace instead
+                    let end = source.chars().fold(position, |mut end, c| {
+                        if c == '\n' {
+                            end.line += 1;
+                            end.column = 1;
+                        } else {
+                            end.column += 1;
                         }
-                    } else {
-                        // no operators so push
-                        operators.push(op);
+                        end
+                    });
+                    let expr = Expr::parse_expr(&inner).map_err(|mut err| {
+                        err.position.get_or_insert(end);
+                        err
+                    })?;
+                    if let Some(extra) = inner.peek() {
+                        return Err(ParserError::new(InvalidToken(extra.kind), Some(extra.position)));
                     }
+                    Fragment::Expr(expr)
                 }
-                _ => break,
+            });
+        }
+        Ok(Expr::new(ExprKind::Interpolate(fragments), tok.position))
+    }
+
+    ///
+    /// Parses a list literal. Expects that the stream is on the opening bracket.
+    /// A trailing comma is permitted after the last element.
+    ///
+    fn parse_list(tok: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let mut items = Vec::new();
+        while tokens.expect(RightBracket).is_none() {
+            items.push(Expr::parse_expr(tokens)?);
+            if tokens.expect(Comma).is_none() {
+                expect_or_error!(tokens, RightBracket)?;
+                break;
             }
+        }
+        Ok(Expr::new(ExprKind::List(items), tok.position))
+    }
 
-            peeked = tokens.peek();
+    ///
+    /// Whether the opening brace at the front of the stream starts a map literal,
+    /// rather than a block. A map is either empty `{}` or its first key is
+    /// followed by a colon `{"key": ...`
+    ///
+    fn is_map_start(tokens: &TokenStream) -> bool {
+        matches!(
+            (tokens.second(), tokens.third()),
+            (Some(Token { kind: RightBrace, .. }), _) | (_, Some(Token { kind: Colon, .. }))
+        )
+    }
+
+    ///
+    /// Parses a map literal. Expects that the stream is on the opening brace.
+    /// A trailing comma is permitted after the last entry.
+    ///
+    fn parse_map(tok: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let mut entries = Vec::new();
+        while tokens.expect(RightBrace).is_none() {
+            let key = Expr::parse_expr(tokens)?;
+            expect_or_error!(tokens, Colon)?;
+            let value = Expr::parse_expr(tokens)?;
+            entries.push((key, value));
+            if tokens.expect(Comma).is_none() {
+                expect_or_error!(tokens, RightBrace)?;
+                break;
+            }
         }
+        Ok(Expr::new(ExprKind::Map(entries), tok.position))
+    }
 
-        //
-        // Unwind the remaining expressions / operators in the stacks, to construct
-        // the full expression. This should be balanced (i.e. num_ops = (num_expr / 2); num_expr % 2 == 0)
-        // If it isn't then we've got an invalid expression.
-        //
-        while operators.len() > 0 {
-            let op = operators.pop().ok_or(ParserError::new(UnmatchedExpr, tokens.position()))?;
-            let (rhs, lhs) = operands.pop_two().ok_or(ParserError::new(UnmatchedExpr, tokens.position()))?;
-            let position = lhs.position.clone();
-            let kind = match op {
-                BinOp::Eq => ExprKind::Assign(lhs.into(), rhs.into()),
-                _ => ExprKind::BinOp(lhs.into(), op, rhs.into())
-            };
-            operands.push(Expr::new(kind, position));
+    ///
+    /// Parses an index into target. Expects that the stream is on the opening bracket.
+    ///
+    fn parse_index(target: Expr, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let index = Expr::parse_expr(tokens)?;
+        expect_or_error!(tokens, RightBracket)?;
+        let position = target.position;
+        Ok(Expr::new(ExprKind::Index(target.into(), index.into()), position))
+    }
+
+    ///
+    /// Parses a field access `.name` into target, with the `.` as the next token.
+    ///
+    fn parse_field(target: Expr, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let name = expect_or_error!(tokens, Identifier)?;
+        let position = target.position;
+        Ok(Expr::new(ExprKind::Field(target.into(), (&name).into()), position))
+    }
+
+    ///
+    /// Parses the arguments of a call to target, with the opening paren as the next token.
+    /// Named arguments `name = <expr>` may only follow the positional arguments.
+    ///
+    fn parse_call(target: Expr, tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let mut args = Vec::new();
+        let mut named = Vec::new();
+        while tokens.expect(RightParen).is_none() {
+            let is_named = matches!(
+                (tokens.first(), tokens.second()),
+                (Some(Token { kind: Identifier, .. }), Some(Token { kind: Eq, .. }))
+            );
+            if is_named {
+                let name = tokens.consume().expect("expected an argument name");
+                tokens.consume();
+                named.push(((&name).into(), Expr::parse_expr(tokens)?));
+            } else if !named.is_empty() {
+                return Err(ParserError::new(PositionalAfterNamed, tokens.position()));
+            } else {
+                args.push(Expr::parse_expr(tokens)?.into());
+            }
+            if let Some(Token { kind: Comma, .. }) = tokens.peek() {
+                tokens.consume();
+            }
         }
+        let position = target.position;
+        Ok(Expr::new(ExprKind::Call(target.into(), args, named), position))
+    }
+
+    ///
+    /// Parses an optional loop label, as used by break and continue.
+    ///
+    fn parse_label(tokens: &TokenStream) -> Option<Ident> {
+        tokens.expect(Label).map(|tok| Ident {
+            name: tok.literal.trim_start_matches('\'').to_owned(),
+            position: tok.position,
+        })
+    }
 
-        Ok(operands.pop().ok_or(ParserError::new(UnmatchedExpr, tokens.position()))?)
+    ///
+    /// Parses a labeled loop `'outer: while ...`, with the label as the next token.
+    ///
+    fn parse_labeled(tok: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
+        let label = Expr::parse_label(tokens);
+        expect_or_error!(tokens, Colon)?;
+        if tokens.peek_ident("while").is_some() {
+            Expr::parse_while(label, tokens)
+        } else if tokens.peek_ident("for").is_some() {
+            Expr::parse_for(label, tokens)
+        } else {
+            Err(ParserError::new(ExpectedLoop, tokens.position().or(Some(tok.position))))
+        }
     }
 
     ///
-    /// Parse a break expression from the token stream. Expects that the stream is
-    /// on the 'break' identifier
+    /// Parse a break expression from the token stream, with an optional label and value.
+    /// Expects that the stream is on the 'break' identifier
     ///
     fn parse_break(tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.consume().expect("expected 'break' identifier token");
-        Ok(Expr::new(ExprKind::Break(None), tok.position))
+        let label = Expr::parse_label(tokens);
+        let value = match tokens.peek() {
+            None | Some(Token { kind: SemiColon | RightBrace | RightParen | RightBracket | Comma, .. }) => None,
+            Some(_) => Some(Expr::parse_expr(tokens)?.into()),
+        };
+        Ok(Expr::new(ExprKind::Break(label, value), tok.position))
     }
 
     ///
-    /// Parse a continue expression from the token stream. Expects that the stream is
-    /// on the 'continue' identifier
+    /// Parse a continue expression from the token stream, with an optional label.
+    /// Expects that the stream is on the 'continue' identifier
     ///
     fn parse_continue(tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.consume().expect("expected 'continue' identifier token");
-        Ok(Expr::new(ExprKind::Continue(None), tok.position))
+        Ok(Expr::new(ExprKind::Continue(Expr::parse_label(tokens)), tok.position))
     }
 
     ///
@@ -224,27 +533,77 @@ impl Expr {
         let block = Block::from_tokens(tokens)?;
         let else_expr = if let Some(tok) = tokens.peek_ident("else") {
             tokens.consume();
-            if let Some(_) = tokens.peek_ident("if") {
-                Some(Box::new(Expr::parse_expr(tokens)?))
+            if tokens.peek_ident("if").is_some() {
+                Some(Box::new(Expr::parse_if(tokens)?))
             } else {
-                Some(Box::new(Expr::new(ExprKind::Block(Block::from_tokens(tokens)?), tok.position.clone())))
+                Some(Box::new(Expr::new(ExprKind::Block(Block::from_tokens(tokens)?), tok.position)))
             }
         } else {
             None
         };
 
-        Ok(Expr::new(ExprKind::If(condition.into(), block, else_expr), tok.position.clone()))
+        Ok(Expr::new(ExprKind::If(condition.into(), block, else_expr), tok.position))
+    }
+
+    ///
+    /// Parses a match expression, and each of its arms, from the token stream. Expects
+    /// that the stream is currently on the match identifier. Arms are separated by
+    /// commas, which are optional after an arm whose body ends in a block.
+    ///
+    fn parse_match(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.consume().expect("expected 'match' identifier token");
+        let subject = Expr::parse_expr(tokens)?;
+        expect_or_error!(tokens, LeftBrace)?;
+        let mut arms = Vec::new();
+        while tokens.expect(RightBrace).is_none() {
+            let position = tokens.peek().map(|tok| tok.position).unwrap_or(tok.position);
+            let pattern = Pattern::from_tokens(tokens)?;
+            let guard = match tokens.expect_ident("if") {
+                Some(_) => Some(Expr::parse_expr(tokens)?),
+                None => None,
+            };
+            expect_or_error!(tokens, FatArrow)?;
+            let body = Expr::parse_expr(tokens)?;
+            let ends_in_block = matches!(
+                body.kind,
+                ExprKind::Block(..) | ExprKind::If(..) | ExprKind::While(..) | ExprKind::For(..) | ExprKind::Match(..) | ExprKind::Try(..)
+            );
+            arms.push(Arm { pattern, guard, body, position });
+            if tokens.expect(Comma).is_none() && !ends_in_block {
+                expect_or_error!(tokens, RightBrace)?;
+                break;
+            }
+        }
+        Ok(Expr::new(ExprKind::Match(subject.into(), arms), tok.position))
     }
 
     ///
     /// Parses a while loop, including its condition and block, from the token stream.
     /// Expects that the stream is currently on the while identifier
     ///
-    fn parse_while(tokens: &TokenStream) -> Result<Self, ParserError> {
+    fn parse_while(label: Option<Ident>, tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.consume().expect("expected 'while' identifier token");
         let condition = Expr::parse_expr(tokens)?;
         let block = Block::from_tokens(tokens)?;
-        Ok(Expr::new(ExprKind::While(condition.into(), block), tok.position.clone()))
+        Ok(Expr::new(ExprKind::While(label, condition.into(), block), tok.position))
+    }
+
+    ///
+    /// Parses a for loop, including its loop variables, the expression being iterated
+    /// over and the block. Expects that the stream is currently on the for identifier
+    ///
+    fn parse_for(label: Option<Ident>, tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.consume().expect("expected 'for' identifier token");
+        let first = expect_or_error!(tokens, Identifier)?;
+        let second = if tokens.expect(Comma).is_some() {
+            Some((&expect_or_error!(tokens, Identifier)?).into())
+        } else {
+            None
+        };
+        tokens.expect_ident("in").ok_or(ParserError::new(ExpectedIdent("in"), tokens.position()))?;
+        let iterable = Expr::parse_expr(tokens)?;
+        let block = Block::from_tokens(tokens)?;
+        Ok(Expr::new(ExprKind::For(label, (&first).into(), second, iterable.into(), block), tok.position))
     }
 
     ///
@@ -253,7 +612,29 @@ impl Expr {
     fn parse_return(tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.consume().expect("expected 'return' identifier token");
         let expr = Expr::parse_expr(tokens)?;
-        Ok(Expr::new(ExprKind::Ret(expr.into()), tok.position.clone()))
+        Ok(Expr::new(ExprKind::Ret(expr.into()), tok.position))
+    }
+
+    ///
+    /// Parses a throw. Expects that the stream is currently on the throw (or raise) identifier.
+    ///
+    fn parse_throw(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.consume().expect("expected 'throw' identifier token");
+        let expr = Expr::parse_expr(tokens)?;
+        Ok(Expr::new(ExprKind::Throw(expr.into()), tok.position))
+    }
+
+    ///
+    /// Parses a try block and its catch block, with the optional name that the error is
+    /// bound to. Expects that the stream is currently on the try identifier.
+    ///
+    fn parse_try(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let tok = tokens.consume().expect("expected 'try' identifier token");
+        let block = Block::from_tokens(tokens)?;
+        tokens.expect_ident("catch").ok_or(ParserError::new(ExpectedIdent("catch"), tokens.position()))?;
+        let ident = tokens.expect(Identifier).map(|ident| (&ident).into());
+        let catch = Block::from_tokens(tokens)?;
+        Ok(Expr::new(ExprKind::Try(block, ident, catch), tok.position))
     }
 
     ///
@@ -263,7 +644,7 @@ impl Expr {
     fn parse_let(tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.consume().expect("expected 'let' identifier token");
         let ident = expect_or_error!(tokens, Identifier)?;
-        let expr = if let Some(_) = tokens.expect(TokenKind::Eq) {
+        let expr = if tokens.expect(TokenKind::Eq).is_some() {
             // we expect either nothing, or =
             Some(Box::new(Expr::parse_expr(tokens)?))
         } else {
@@ -273,39 +654,63 @@ impl Expr {
     }
 
     ///
-    /// Parses an identifier from the `TokenStream`. `ident` is expected to be the Identifier
-    /// token, and may refer to a variable name, or function call.
+    /// Parses an anonymous function, `tok` is expected to be the next token. The short form
+    /// `|a, b| <expr>` (or `|| <expr>` without arguments) has a single expression as its body,
+    /// whereas `fn(a, b) { <block> }` has a block, as with named functions. Either may
+    /// declare a return type, i.e. `|a| -> int <expr>`.
     ///
-    fn parse_ident(ident: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
-        // consume the ident
+    fn parse_lambda(tok: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
         tokens.consume();
-        match tokens.peek() {
-            Some(Token { kind: LeftParen, .. }) => {
-                // looks like a function call
-                // consume the Lparen
-                tokens.consume();
-                let mut args = Vec::new();
-                while let None = tokens.expect(RightParen) {
-                    args.push(Expr::parse_expr(tokens)?.into());
-                    if let Some(Token { kind: Comma, .. }) = tokens.peek() {
-                        tokens.consume();
-                    }
-                }
-                // TODO: definitely need a better way of constructing these
-                return Ok(Expr::new(
-                    ExprKind::Call(
-                        Expr::new(ExprKind::Ident(ident.into()), ident.position.clone()).into(),
-                        args,
-                    ),
-                    ident.position.clone(),
-                ));
+        let (args, ret_typ, block) = match tok.kind {
+            Identifier => {
+                expect_or_error!(tokens, LeftParen)?;
+                let args = ArgSpec::parse_list(tokens, RightParen)?;
+                let ret_typ = Typ::parse_return(tokens)?;
+                (args, ret_typ, Block::from_tokens(tokens)?)
             }
             _ => {
-                let position = ident.position.clone();
-                let ident = ExprKind::Ident(ident.into());
-                return Ok(Expr::new(ident, position));
+                let args = match tok.kind {
+                    OrOr => Vec::new(),
+                    _ => ArgSpec::parse_list(tokens, BinOp(BinOpKind::Or))?,
+                };
+                let ret_typ = Typ::parse_return(tokens)?;
+                let body = Expr::parse_expr(tokens)?;
+                let block = match body.kind {
+                    ExprKind::Block(block) => block,
+                    kind => {
+                        let position = body.position;
+                        Block {
+                            stmts: vec![Stmt { kind: StmtKind::Expr(Expr::new(kind, position)), position }],
+                            position,
+                        }
+                    }
+                };
+                (args, ret_typ, block)
             }
-        }
+        };
+        let func = Function {
+            ident: Ident {
+                name: Function::LAMBDA_NAME.to_owned(),
+                position: tok.position,
+            },
+            args,
+            ret_typ,
+            block,
+            position: tok.position,
+            id: None,
+        };
+        Ok(Expr::new(ExprKind::Lambda(func), tok.position))
+    }
+
+    ///
+    /// Parses an identifier from the `TokenStream`. `ident` is expected to be the Identifier
+    /// token, and may refer to a variable name or function, which is called if followed
+    /// by arguments.
+    ///
+    fn parse_ident(ident: &Token, tokens: &TokenStream) -> Result<Self, ParserError> {
+        // consume the ident
+        tokens.consume();
+        Ok(Expr::new(ExprKind::Ident(ident.into()), ident.position))
     }
 }
 
@@ -1,8 +1,11 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use common::Position;
-use tokenizer::{FromTokens, Token, TokenStream};
+use tokenizer::{FromTokens, Token, TokenKind, TokenStream};
 
-use crate::{Ident, Stmt, Typ};
-use crate::error::ErrorKind::ExpectedIdent;
+use crate::{BinOp, Expr, Ident, Stmt, Typ, Value};
+use crate::error::ErrorKind::{ExpectedIdent, VariadicNotLast};
 use crate::error::ParserError;
 
 use serde::{Serialize, Deserialize};
@@ -16,13 +19,43 @@ pub struct Function {
     pub block: Block,
     #[serde(skip)]
     pub position: Position,
+    /// Identifies the compiled form of the function, for function values created by the VM
+    #[serde(skip)]
+    pub id: Option<usize>,
 }
 
+///
+/// A function value together with the bindings it captured from the
+/// scope it was defined in, which are bound alongside the arguments on
+/// each call. The bindings are shared rather than copied, so changes made
+/// to them by the closure, any copy of it, or the scope it was defined in
+/// are seen by all of them.
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Closure {
+    pub func: Function,
+    /// Closures are only created when a script runs, so are never part of its AST
+    #[serde(skip)]
+    pub captures: Vec<(String, Capture)>,
+}
+
+///
+/// The binding of a name captured by a closure, shared with the scope it was defined in
+///
+pub type Capture = Rc<RefCell<Value>>;
+
 #[derive(Clone, Debug)]
 #[derive(Serialize, Deserialize)]
 pub struct ArgSpec {
     pub name: Ident,
     pub typ: Option<Typ>,
+    /// Evaluated when the call does not give this argument, with the
+    /// preceding arguments in scope
+    pub default: Option<Expr>,
+    /// Whether this collects any extra positional arguments into a list `...rest`,
+    /// in which case any annotation applies to each of them
+    pub variadic: bool,
     #[serde(skip)]
     pub position: Position,
 }
@@ -35,58 +68,148 @@ pub struct Block {
     pub position: Position,
 }
 
+impl Function {
+    /// The name given to anonymous functions
+    pub const LAMBDA_NAME: &'static str = "<lambda>";
+}
+
+impl Typ {
+    ///
+    /// Parses an optional return type annotation `-> <type>`
+    ///
+    pub(crate) fn parse_return(tokens: &TokenStream) -> Result<Option<Self>, ParserError> {
+        match tokens.expect(TokenKind::Arrow) {
+            Some(_) => Ok(Some(Typ::from_tokens(tokens)?)),
+            None => Ok(None),
+        }
+    }
+}
+
 impl FromTokens for Function {
     type Error = ParserError;
 
     fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
         use tokenizer::TokenKind::*;
-        let mut args: Vec<ArgSpec> = Vec::new();
         // fn_tok is used purely for its position in the source. it's used for the overall function location
         let fn_tok = tokens.expect_ident("fn").ok_or(ParserError::new(ExpectedIdent("fn"), tokens.position()))?;
         let name = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
         tokens.expect(LeftParen).ok_or(ParserError::new(RightParen, tokens.position()))?;
-        while let None = tokens.expect(RightParen) {
-            args.push(ArgSpec::from_tokens(tokens)?);
-            if let Some(Token { kind: Comma, .. }) = tokens.peek() {
-                tokens.consume();
-            }
-        }
-        // TODO: add return types
+        let args = ArgSpec::parse_list(tokens, RightParen)?;
+        let ret_typ = Typ::parse_return(tokens)?;
         let block = Block::from_tokens(tokens)?;
         Ok(Self {
             ident: Ident {
                 name: name.literal.clone(),
-                position: name.position.clone(),
+                position: name.position,
             },
-            args: args.clone(),
-            ret_typ: None,
+            args,
+            ret_typ,
             block,
-            position: fn_tok.position.clone(),
+            position: fn_tok.position,
+            id: None,
         })
     }
 }
 
+///
+/// The arguments of a call, matched up with the parameters of the function being called
+///
+#[derive(Debug, PartialEq)]
+pub struct Bindings<T> {
+    /// The argument given for each parameter other than a variadic one, if any.
+    /// Those without an argument take their default value.
+    pub args: Vec<Option<T>>,
+    /// The extra positional arguments, collected by a variadic parameter
+    pub rest: Vec<T>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BindError {
+    /// More positional arguments than parameters, with the expected and actual counts
+    TooMany(usize, usize),
+    /// A named argument that is not a parameter
+    Unknown(String),
+    /// A parameter given more than one argument
+    Duplicate(String),
+    /// A parameter without a default that was not given an argument
+    Missing(String),
+}
+
+impl ArgSpec {
+    ///
+    /// Parses a comma separated list of arguments, up to and including the
+    /// closing token, i.e. `)` for functions or `|` for lambdas. Defaults in
+    /// lambda arguments may not contain `|` unless parenthesised.
+    ///
+    pub(crate) fn parse_list(tokens: &TokenStream, close: TokenKind) -> Result<Vec<Self>, ParserError> {
+        let max_precedence = match close {
+            TokenKind::RightParen => BinOp::Range.precedence(),
+            _ => BinOp::Xor.precedence(),
+        };
+        let mut args: Vec<ArgSpec> = Vec::new();
+        while tokens.expect(close).is_none() {
+            if args.last().is_some_and(|arg| arg.variadic) {
+                return Err(ParserError::new(VariadicNotLast, tokens.position()));
+            }
+            let mut arg = ArgSpec::from_tokens(tokens)?;
+            if !arg.variadic && tokens.expect(TokenKind::Eq).is_some() {
+                arg.default = Some(Expr::parse_binary(tokens, max_precedence)?);
+            }
+            args.push(arg);
+            if let Some(Token { kind: TokenKind::Comma, .. }) = tokens.peek() {
+                tokens.consume();
+            }
+        }
+        Ok(args)
+    }
+
+    ///
+    /// Matches the positional and named arguments of a call up with params. Positional
+    /// arguments are taken in order, with any extras going to a variadic parameter, and
+    /// named arguments may be given for any parameter that is not variadic.
+    ///
+    pub fn bind<T>(params: &[ArgSpec], positional: Vec<T>, named: Vec<(String, T)>) -> Result<Bindings<T>, BindError> {
+        let fixed = params.iter().take_while(|param| !param.variadic).count();
+        if fixed == params.len() && positional.len() > fixed {
+            return Err(BindError::TooMany(fixed, positional.len()));
+        }
+
+        let mut positional = positional.into_iter();
+        let mut args: Vec<Option<T>> = positional.by_ref().take(fixed).map(Some).collect();
+        args.resize_with(fixed, || None);
+        let rest = positional.collect();
+
+        for (name, value) in named {
+            let idx = params[..fixed].iter().position(|param| param.name.name == name)
+                .ok_or_else(|| BindError::Unknown(name.clone()))?;
+            if args[idx].is_some() {
+                return Err(BindError::Duplicate(name));
+            }
+            args[idx] = Some(value);
+        }
+
+        let missing = params.iter().zip(&args).find(|(param, arg)| arg.is_none() && param.default.is_none());
+        if let Some((param, _)) = missing {
+            return Err(BindError::Missing(param.name.name.clone()));
+        }
+        Ok(Bindings { args, rest })
+    }
+}
+
 impl FromTokens for ArgSpec {
     type Error = ParserError;
 
     fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
         use tokenizer::TokenKind::*;
 
+        let variadic = tokens.expect(Ellipsis).is_some();
         let name = tokens.expect(Identifier).ok_or(
             ParserError::new(Identifier, tokens.position())
         )?;
 
         let typ = if let Some(Token { kind: Colon, .. }) = tokens.peek() {
             tokens.consume();
-            let typ = tokens.expect(Identifier).ok_or(
-                ParserError::new(Identifier, tokens.position())
-            )?;
-            Some(Typ {
-                ident: Ident {
-                    name: typ.literal.clone(),
-                    position: typ.position.clone(),
-                }
-            })
+            Some(Typ::from_tokens(tokens)?)
         } else {
             None
         };
@@ -94,10 +217,12 @@ impl FromTokens for ArgSpec {
         Ok(Self {
             name: Ident {
                 name: name.literal.clone(),
-                position: name.position.clone(),
+                position: name.position,
             },
             typ,
-            position: name.position.clone(),
+            default: None,
+            variadic,
+            position: name.position,
         })
     }
 }
@@ -112,13 +237,13 @@ impl FromTokens for Block {
         )?;
 
         let mut stmts = Vec::new();
-        while let None = tokens.expect(RightBrace) {
-            stmts.push(Stmt::from_tokens(tokens)?);
+        while tokens.expect(RightBrace).is_none() {
+            stmts.push(Stmt::parse(tokens)?);
         }
 
         Ok(Self {
             stmts,
-            position: start.position.clone(),
+            position: start.position,
         })
     }
 }
\ No newline at end of file
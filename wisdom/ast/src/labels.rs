@@ -0,0 +1,138 @@
+use crate::{Block, Expr, ExprKind, Fragment, Function, Ident, Stmt, StmtKind};
+use crate::error::ErrorKind::UnknownLabel;
+use crate::error::ParserError;
+
+///
+/// Checks that every labeled break and continue within a statement refers to
+/// an enclosing loop. Loops outside of a function can't be broken out of from
+/// within it, so each function body is checked on its own.
+///
+pub(crate) fn check(stmt: &Stmt) -> Result<(), ParserError> {
+    visit_stmt(stmt, &mut Vec::new())
+}
+
+fn visit_stmt<'a>(stmt: &'a Stmt, labels: &mut Vec<&'a str>) -> Result<(), ParserError> {
+    match &stmt.kind {
+        StmtKind::Expr(expr) => visit_expr(expr, labels),
+        StmtKind::Fn(func) => visit_function(func),
+        StmtKind::Struct(_) | StmtKind::Enum(_) => Ok(()),
+        StmtKind::Impl(imp) => imp.methods.iter().try_for_each(visit_function),
+    }
+}
+
+fn visit_function(func: &Function) -> Result<(), ParserError> {
+    let mut labels = Vec::new();
+    for default in func.args.iter().filter_map(|arg| arg.default.as_ref()) {
+        visit_expr(default, &mut labels)?;
+    }
+    visit_block(&func.block, &mut labels)
+}
+
+fn visit_block<'a>(block: &'a Block, labels: &mut Vec<&'a str>) -> Result<(), ParserError> {
+    for stmt in &block.stmts {
+        visit_stmt(stmt, labels)?;
+    }
+    Ok(())
+}
+
+///
+/// Visits the body of a loop, with its label (if any) in scope.
+///
+fn visit_loop<'a>(label: &'a Option<Ident>, block: &'a Block, labels: &mut Vec<&'a str>) -> Result<(), ParserError> {
+    match label {
+        Some(label) => {
+            labels.push(&label.name);
+            let result = visit_block(block, labels);
+            labels.pop();
+            result
+        }
+        None => visit_block(block, labels),
+    }
+}
+
+fn check_label(label: &Option<Ident>, labels: &[&str]) -> Result<(), ParserError> {
+    match label {
+        Some(label) if !labels.contains(&label.name.as_str()) => {
+            Err(ParserError::new(UnknownLabel, Some(label.position)))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn visit_expr<'a>(expr: &'a Expr, labels: &mut Vec<&'a str>) -> Result<(), ParserError> {
+    use ExprKind::*;
+    match &expr.kind {
+        Let(_, rhs) => {
+            if let Some(rhs) = rhs {
+                visit_expr(rhs, labels)?;
+            }
+        }
+        Assign(lhs, rhs) | AssignOp(lhs, _, rhs) | BinOp(lhs, _, rhs) | Index(lhs, rhs) => {
+            visit_expr(lhs, labels)?;
+            visit_expr(rhs, labels)?;
+        }
+        Unary(_, operand) | Field(operand, _) | Ret(operand) | Throw(operand) | Propagate(operand) => visit_expr(operand, labels)?,
+        For(label, _, _, iterable, block) | While(label, iterable, block) => {
+            visit_expr(iterable, labels)?;
+            visit_loop(label, block, labels)?;
+        }
+        If(cond, block, maybe_else) => {
+            visit_expr(cond, labels)?;
+            visit_block(block, labels)?;
+            if let Some(expr) = maybe_else {
+                visit_expr(expr, labels)?;
+            }
+        }
+        Match(subject, arms) => {
+            visit_expr(subject, labels)?;
+            for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    visit_expr(guard, labels)?;
+                }
+                visit_expr(&arm.body, labels)?;
+            }
+        }
+        Block(block) => visit_block(block, labels)?,
+        Try(block, _, catch) => {
+            visit_block(block, labels)?;
+            visit_block(catch, labels)?;
+        }
+        Lambda(func) => visit_function(func)?,
+        Call(callee, args, named) => {
+            visit_expr(callee, labels)?;
+            for arg in args {
+                visit_expr(arg, labels)?;
+            }
+            for (_, arg) in named {
+                visit_expr(arg, labels)?;
+            }
+        }
+        Interpolate(fragments) => {
+            for fragment in fragments {
+                if let Fragment::Expr(expr) = fragment {
+                    visit_expr(expr, labels)?;
+                }
+            }
+        }
+        List(items) => {
+            for item in items {
+                visit_expr(item, labels)?;
+            }
+        }
+        Map(entries) => {
+            for (key, value) in entries {
+                visit_expr(key, labels)?;
+                visit_expr(value, labels)?;
+            }
+        }
+        Break(label, value) => {
+            check_label(label, labels)?;
+            if let Some(value) = value {
+                visit_expr(value, labels)?;
+            }
+        }
+        Continue(label) => check_label(label, labels)?,
+        Literal(_) | Ident(_) => {}
+    }
+    Ok(())
+}
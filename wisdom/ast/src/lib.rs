@@ -5,16 +5,23 @@ use common::{Position};
 pub use expr::*;
 pub use func::*;
 pub use operation::*;
+pub use pattern::*;
 pub use stmt::*;
-use tokenizer::Token;
+pub use structs::*;
+use tokenizer::{FromTokens, Token, TokenKind, TokenStream};
+
+use crate::error::ParserError;
 pub use value::*;
 
 mod stmt;
 mod expr;
 mod func;
+mod structs;
 mod operation;
+mod pattern;
+mod labels;
 pub mod error;
-mod ext;
+pub mod checker;
 mod value;
 
 extern crate serde;
@@ -32,7 +39,7 @@ pub struct Ident {
 impl From<&Token> for Ident {
     fn from(t: &Token) -> Self {
         Self {
-            position: t.position.clone(),
+            position: t.position,
             name: t.literal.clone(),
         }
     }
@@ -42,4 +49,17 @@ impl From<&Token> for Ident {
 #[derive(Serialize, Deserialize)]
 pub struct Typ {
     pub ident: Ident
+}
+
+impl FromTokens for Typ {
+    type Error = ParserError;
+
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        let typ = tokens.expect(TokenKind::Identifier).ok_or(
+            ParserError::new(TokenKind::Identifier, tokens.position())
+        )?;
+        Ok(Typ {
+            ident: (&typ).into(),
+        })
+    }
 }
\ No newline at end of file
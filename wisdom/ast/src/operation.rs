@@ -1,7 +1,7 @@
 use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-use tokenizer::{FromTokens, TokenStream};
+use tokenizer::{BinOpKind, FromTokens, TokenKind, TokenStream};
 
 use crate::error::ErrorKind::ExpectedOperator;
 use crate::error::ParserError;
@@ -29,9 +29,28 @@ pub enum BinOp {
     BinOr,
     Mod,
     TildeEq,
+    ShiftLeft,
+    ShiftRight,
+    /// Exponentiation `a ** b`, which is right-associative
+    Pow,
+    /// `a..b`
+    Range,
+    /// `a..=b`
+    RangeInclusive,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Associativity {
+    Left,
+    Right,
 }
 
 impl BinOp {
+    ///
+    /// The loosest precedence of any operator, used to parse a whole expression.
+    ///
+    pub const LOWEST_PRECEDENCE: usize = 14;
+
     ///
     /// Defines the operator precedence. Lower value is higher-precedence.
     /// Lifted from C's precedence rules (for consistence with other C-style languages)
@@ -39,8 +58,10 @@ impl BinOp {
     pub fn precedence(self) -> usize {
         use BinOp::*;
         match self {
+            Pow => 2,
             Mul | Div | Mod => 3,
             Add | Sub => 4,
+            ShiftLeft | ShiftRight => 5,
             Lt | LtEq | Gt | GtEq => 6,
             EqEq | NotEq | TildeEq => 7,
             BinAnd => 8,
@@ -48,9 +69,77 @@ impl BinOp {
             BinOr => 10,
             And => 11,
             Or => 12,
+            Range | RangeInclusive => 13,
             Eq => 14,
         }
     }
+
+    ///
+    /// Returns the operator applied by a compound assignment token e.g. `+=`
+    ///
+    pub fn from_compound_assignment(kind: TokenKind) -> Option<Self> {
+        Some(match kind {
+            TokenKind::AddEq => BinOp::Add,
+            TokenKind::SubEq => BinOp::Sub,
+            TokenKind::MulEq => BinOp::Mul,
+            TokenKind::DivEq => BinOp::Div,
+            TokenKind::ModEq => BinOp::Mod,
+            TokenKind::OrEq => BinOp::BinOr,
+            TokenKind::AndEq => BinOp::BinAnd,
+            TokenKind::XorEq => BinOp::Xor,
+            _ => return None
+        })
+    }
+
+    ///
+    /// Defines how a chain of operators of the same precedence is grouped.
+    /// Assignment and exponentiation group from the right, so that
+    /// `a = b = 1` is `a = (b = 1)` and `a ** b ** c` is `a ** (b ** c)`
+    ///
+    pub fn associativity(self) -> Associativity {
+        match self {
+            BinOp::Eq | BinOp::Pow => Associativity::Right,
+            _ => Associativity::Left,
+        }
+    }
+}
+
+///
+/// Prefix operators, which bind more tightly than any binary operator
+///
+#[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+pub enum UnaryOp {
+    /// `-a`
+    Neg,
+    /// `!a`
+    Not,
+    /// `~a`
+    BitNot,
+}
+
+impl UnaryOp {
+    ///
+    /// Returns the prefix operator for the given token, if it is one.
+    ///
+    pub fn from_token(kind: TokenKind) -> Option<Self> {
+        match kind {
+            TokenKind::Sub => Some(UnaryOp::Neg),
+            TokenKind::BinOp(BinOpKind::Not) => Some(UnaryOp::Not),
+            TokenKind::Tilde => Some(UnaryOp::BitNot),
+            _ => None
+        }
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::BitNot => write!(f, "~"),
+        }
+    }
 }
 
 impl Display for BinOp {
@@ -73,7 +162,12 @@ impl Display for BinOp {
             BinOp::BinOr => write!(f, "|"),
             BinOp::Eq => write!(f, "="),
             BinOp::Mod => write!(f, "%"),
-            BinOp::TildeEq => write!(f, "~=")
+            BinOp::ShiftLeft => write!(f, "<<"),
+            BinOp::ShiftRight => write!(f, ">>"),
+            BinOp::Pow => write!(f, "**"),
+            BinOp::TildeEq => write!(f, "~="),
+            BinOp::Range => write!(f, ".."),
+            BinOp::RangeInclusive => write!(f, "..=")
         }
     }
 }
@@ -100,7 +194,12 @@ impl FromStr for BinOp {
             "&" => Self::BinAnd,
             "|" => Self::BinOr,
             "=" => Self::Eq,
+            "<<" => Self::ShiftLeft,
+            ">>" => Self::ShiftRight,
+            "**" => Self::Pow,
             "~=" => Self::TildeEq,
+            ".." => Self::Range,
+            "..=" => Self::RangeInclusive,
             _ => return Err(())
         })
     }
@@ -127,6 +226,17 @@ mod test {
         assert_eq!(BinOp::from_str("-").unwrap(), BinOp::Sub);
         assert_eq!(BinOp::from_str("*").unwrap(), BinOp::Mul);
         assert_eq!(BinOp::from_str("/").unwrap(), BinOp::Div);
+        assert_eq!(BinOp::from_str("<<").unwrap(), BinOp::ShiftLeft);
+        assert_eq!(BinOp::from_str(">>").unwrap(), BinOp::ShiftRight);
+        assert_eq!(BinOp::from_str("**").unwrap(), BinOp::Pow);
+    }
+
+    #[test]
+    fn test_unary_op() {
+        assert_eq!(UnaryOp::from_token(TokenKind::Sub), Some(UnaryOp::Neg));
+        assert_eq!(UnaryOp::from_token(TokenKind::BinOp(BinOpKind::Not)), Some(UnaryOp::Not));
+        assert_eq!(UnaryOp::from_token(TokenKind::Tilde), Some(UnaryOp::BitNot));
+        assert_eq!(UnaryOp::from_token(TokenKind::Add), None);
     }
 
     #[test]
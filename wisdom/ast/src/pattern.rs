@@ -0,0 +1,206 @@
+use tokenizer::{FromTokens, Token, TokenStream};
+use tokenizer::TokenKind::*;
+
+use crate::{Ident, Value};
+use crate::error::ErrorKind::{InvalidLit, InvalidToken, UnexpectedEOL};
+use crate::error::ParserError;
+
+use serde::{Serialize, Deserialize};
+
+///
+/// A pattern that a match arm compares a value against. Patterns may bind
+/// names to parts of the value, which are in scope in the arm's guard and body.
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub enum Pattern {
+    /// `_`, which matches anything
+    Wildcard,
+    /// A name, which matches anything and is bound to it
+    Bind(Ident),
+    /// A literal, which matches equal values. A regex instead matches any string
+    /// it is found in, binding its capture groups as `~=` does
+    Literal(Value),
+    /// A range of integers `1..10` or `1..=10`, as its half-open bounds
+    Range(i64, i64),
+    /// A list `[first, second, ...rest]`. Without a rest pattern the list must have
+    /// exactly as many items as patterns, otherwise the rest is matched against
+    /// a list of any remaining items
+    List(Vec<Pattern>, Option<Box<Pattern>>),
+    /// A struct `Point { x, y: 0 }`, where a field without a pattern is bound to its own name
+    Struct(Ident, Vec<(Ident, Pattern)>),
+    /// An enum variant `Shape.Circle(r)`, with a pattern for each of its fields in
+    /// order. Without them, `Shape.Circle` matches any value of the variant
+    Variant(Ident, Ident, Option<Vec<Pattern>>),
+}
+
+impl Pattern {
+    ///
+    /// The names bound by this pattern, in the order they are bound when it matches.
+    ///
+    pub fn bindings(&self) -> Vec<&Ident> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a Ident>) {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(_, _) => {}
+            Pattern::Bind(ident) => names.push(ident),
+            Pattern::List(items, rest) => {
+                items.iter().for_each(|item| item.collect_bindings(names));
+                if let Some(rest) = rest {
+                    rest.collect_bindings(names);
+                }
+            }
+            Pattern::Struct(_, fields) => fields.iter().for_each(|(_, field)| field.collect_bindings(names)),
+            Pattern::Variant(_, _, fields) => fields.iter().flatten().for_each(|field| field.collect_bindings(names)),
+        }
+    }
+
+    ///
+    /// Whether this pattern matches any value at all
+    ///
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Bind(_))
+    }
+
+    ///
+    /// Parses a literal, or a range if it is followed by `..` or `..=`
+    ///
+    fn parse_literal(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let position = tokens.position();
+        let value = Pattern::parse_value(tokens)?;
+        let inclusive = match tokens.peek() {
+            Some(Token { kind: DotDot, .. }) => false,
+            Some(Token { kind: DotDotEq, .. }) => true,
+            _ => return Ok(Pattern::Literal(value)),
+        };
+        tokens.consume();
+        match (value, Pattern::parse_value(tokens)?) {
+            (Value::Int(start), Value::Int(end)) if inclusive => Ok(Pattern::Range(start, end + 1)),
+            (Value::Int(start), Value::Int(end)) => Ok(Pattern::Range(start, end)),
+            _ => Err(ParserError::new(InvalidLit, position)),
+        }
+    }
+
+    ///
+    /// Parses a literal value, which may be a negated number `-1`
+    ///
+    fn parse_value(tokens: &TokenStream) -> Result<Value, ParserError> {
+        let negate = tokens.expect(Sub).is_some();
+        let tok = tokens.peek().ok_or(ParserError::new(UnexpectedEOL, tokens.position()))?;
+        if !matches!(tok.kind, Literal { .. } | Identifier) {
+            return Err(ParserError::new(InvalidToken(tok.kind), Some(tok.position)));
+        }
+        match Value::from_tokens(tokens)? {
+            Value::Int(n) if negate => Ok(Value::Int(-n)),
+            Value::Float(n) if negate => Ok(Value::Float(-n)),
+            Value::Named(_) => Err(ParserError::new(InvalidLit, Some(tok.position))),
+            _ if negate => Err(ParserError::new(InvalidLit, Some(tok.position))),
+            value => Ok(value),
+        }
+    }
+
+    ///
+    /// Parses a list pattern. Expects that the stream is on the opening bracket.
+    /// The rest pattern `...rest` (or just `...`) may only come last.
+    ///
+    fn parse_list(tokens: &TokenStream) -> Result<Self, ParserError> {
+        tokens.consume();
+        let mut items = Vec::new();
+        let mut rest = None;
+        while tokens.expect(RightBracket).is_none() {
+            if tokens.expect(Ellipsis).is_some() {
+                rest = Some(match tokens.peek() {
+                    Some(Token { kind: Identifier, .. }) => Pattern::from_tokens(tokens)?.into(),
+                    _ => Pattern::Wildcard.into(),
+                });
+                let _ = tokens.expect(Comma);
+                tokens.expect(RightBracket).ok_or(ParserError::new(RightBracket, tokens.position()))?;
+                break;
+            }
+            items.push(Pattern::from_tokens(tokens)?);
+            if tokens.expect(Comma).is_none() {
+                tokens.expect(RightBracket).ok_or(ParserError::new(RightBracket, tokens.position()))?;
+                break;
+            }
+        }
+        Ok(Pattern::List(items, rest))
+    }
+
+    ///
+    /// Parses a struct pattern, with the struct name as the next token.
+    ///
+    fn parse_struct(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let name = tokens.consume().expect("expected a struct name");
+        tokens.consume();
+        let mut fields = Vec::new();
+        while tokens.expect(RightBrace).is_none() {
+            let field = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+            let pattern = match tokens.expect(Colon) {
+                Some(_) => Pattern::from_tokens(tokens)?,
+                None => Pattern::Bind((&field).into()),
+            };
+            fields.push(((&field).into(), pattern));
+            if tokens.expect(Comma).is_none() {
+                tokens.expect(RightBrace).ok_or(ParserError::new(RightBrace, tokens.position()))?;
+                break;
+            }
+        }
+        Ok(Pattern::Struct((&name).into(), fields))
+    }
+
+    ///
+    /// Parses an enum variant pattern, with the enum name as the next token.
+    ///
+    fn parse_variant(tokens: &TokenStream) -> Result<Self, ParserError> {
+        let name = tokens.consume().expect("expected an enum name");
+        tokens.consume();
+        let variant = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+        let fields = match tokens.expect(LeftParen) {
+            Some(_) => {
+                let mut fields = Vec::new();
+                while tokens.expect(RightParen).is_none() {
+                    fields.push(Pattern::from_tokens(tokens)?);
+                    if tokens.expect(Comma).is_none() {
+                        tokens.expect(RightParen).ok_or(ParserError::new(RightParen, tokens.position()))?;
+                        break;
+                    }
+                }
+                Some(fields)
+            }
+            None => None,
+        };
+        Ok(Pattern::Variant((&name).into(), (&variant).into(), fields))
+    }
+}
+
+impl FromTokens for Pattern {
+    type Error = ParserError;
+
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        let tok = tokens.peek().ok_or(ParserError::new(UnexpectedEOL, tokens.position()))?;
+        match tok.kind {
+            LeftBracket => Pattern::parse_list(tokens),
+            Literal { .. } | Sub => Pattern::parse_literal(tokens),
+            Identifier => match tok.literal.as_str() {
+                "_" => {
+                    tokens.consume();
+                    Ok(Pattern::Wildcard)
+                }
+                "true" | "false" | "none" => Pattern::parse_literal(tokens),
+                _ => match tokens.second() {
+                    Some(Token { kind: LeftBrace, .. }) => Pattern::parse_struct(tokens),
+                    Some(Token { kind: Dot, .. }) => Pattern::parse_variant(tokens),
+                    _ => {
+                        tokens.consume();
+                        Ok(Pattern::Bind((&tok).into()))
+                    }
+                },
+            },
+            kind => Err(ParserError::new(InvalidToken(kind), Some(tok.position))),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use common::{Position};
-use tokenizer::{FromTokens, TokenStream};
+use tokenizer::{FromTokens, Token, TokenStream};
 
-use crate::{Expr, Function};
+use crate::{labels, Enum, Expr, Function, Impl, Struct};
 use crate::error::ErrorKind::UnexpectedEOL;
 use crate::error::ParserError;
 
@@ -20,27 +20,57 @@ pub struct Stmt {
 pub enum StmtKind {
     Expr(Expr),
     Fn(Function),
+    Struct(Struct),
+    Enum(Enum),
+    Impl(Impl),
 }
 
 impl FromTokens for Stmt {
     type Error = ParserError;
 
+    ///
+    /// Parses a top-level statement, checking that any labeled break
+    /// or continue within it refers to an enclosing loop.
+    ///
     fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        let stmt = Stmt::parse(tokens)?;
+        labels::check(&stmt)?;
+        Ok(stmt)
+    }
+}
+
+impl Stmt {
+    ///
+    /// Parses a statement, which may be nested within a block.
+    ///
+    pub(crate) fn parse(tokens: &TokenStream) -> Result<Self, ParserError> {
         if let Some(tok) = &tokens.peek() {
             use tokenizer::TokenKind::*;
             let stmt_kind = match tok.kind {
                 Identifier => {
                     match tok.literal.as_str() {
-                        "fn" => StmtKind::Fn(Function::from_tokens(tokens)?),
-                        _ => StmtKind::Expr(Expr::from_tokens(tokens)?),
+                        // `fn(...)` is an anonymous function expression, rather than a definition
+                        "fn" if matches!(tokens.second(), Some(Token { kind: Identifier, .. })) => {
+                            StmtKind::Fn(Function::from_tokens(tokens)?)
+                        }
+                        "struct" if matches!(tokens.second(), Some(Token { kind: Identifier, .. })) => {
+                            StmtKind::Struct(Struct::from_tokens(tokens)?)
+                        }
+                        "enum" if matches!(tokens.second(), Some(Token { kind: Identifier, .. })) => {
+                            StmtKind::Enum(Enum::from_tokens(tokens)?)
+                        }
+                        "impl" if matches!(tokens.second(), Some(Token { kind: Identifier, .. })) => {
+                            StmtKind::Impl(Impl::from_tokens(tokens)?)
+                        }
+                        _ => StmtKind::Expr(Expr::parse_statement(tokens)?),
                     }
                 }
-                _ => StmtKind::Expr(Expr::from_tokens(tokens)?),
+                _ => StmtKind::Expr(Expr::parse_statement(tokens)?),
             };
             // TODO: look into semi-colon processing - when do we need them?
             let _ = tokens.expect(SemiColon);
             Ok(Stmt {
-                position: tok.position.clone(),
+                position: tok.position,
                 kind: stmt_kind,
             })
         } else {
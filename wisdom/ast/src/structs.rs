@@ -0,0 +1,204 @@
+use common::Position;
+use indexmap::IndexMap;
+use tokenizer::{FromTokens, TokenKind, TokenStream};
+
+use crate::{ArgSpec, Function, Ident, Typ, Value};
+use crate::error::ErrorKind::ExpectedIdent;
+use crate::error::ParserError;
+
+use serde::{Serialize, Deserialize};
+
+///
+/// A struct declaration `struct Point { x: int, y: int }`. The struct
+/// is called to construct an instance, with a value for each field given
+/// in order or by name, i.e. `Point(1, y = 2)`.
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Struct {
+    pub ident: Ident,
+    /// The fields, which are bound like the arguments of a function
+    /// without defaults
+    pub fields: Vec<ArgSpec>,
+    #[serde(skip)]
+    pub position: Position,
+}
+
+///
+/// A block of methods for a struct `impl Point { fn len(self) { ... } }`.
+/// Methods whose first argument is `self` are bound to the instance they
+/// are accessed through, and are given a copy of it when called.
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Impl {
+    pub ident: Ident,
+    pub methods: Vec<Function>,
+    #[serde(skip)]
+    pub position: Position,
+}
+
+///
+/// An enum declaration `enum Shape { Circle(r: float), Rect(w, h), Empty }`.
+/// A variant with fields is called to construct a value of it, i.e.
+/// `Shape.Circle(1.5)`, whereas a variant without fields is a value itself.
+/// Methods are added to an enum with `impl`, as with structs.
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Enum {
+    pub ident: Ident,
+    pub variants: Vec<Variant>,
+    #[serde(skip)]
+    pub position: Position,
+}
+
+///
+/// A single variant of an enum, with its fields (if any)
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Variant {
+    pub ident: Ident,
+    pub fields: Vec<ArgSpec>,
+    #[serde(skip)]
+    pub position: Position,
+}
+
+///
+/// A value of a struct or enum, with its fields in the order they were declared
+///
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Instance {
+    /// The name of the struct or enum
+    pub name: String,
+    /// The variant, for values of an enum
+    pub variant: Option<String>,
+    pub fields: IndexMap<String, Value>,
+}
+
+///
+/// A method bound to the instance it was accessed through, which is
+/// passed as `self` when it is called
+///
+#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize)]
+pub struct Method {
+    pub receiver: Value,
+    pub func: Function,
+}
+
+impl Function {
+    ///
+    /// Whether this is a method that takes the instance it is called on
+    ///
+    pub fn takes_self(&self) -> bool {
+        self.args.first().is_some_and(|arg| arg.name.name == "self")
+    }
+}
+
+impl FromTokens for Struct {
+    type Error = ParserError;
+
+    ///
+    /// Parses a struct declaration. A trailing comma is permitted after the last field.
+    ///
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        use tokenizer::TokenKind::*;
+        let struct_tok = tokens.expect_ident("struct").ok_or(ParserError::new(ExpectedIdent("struct"), tokens.position()))?;
+        let name = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+        tokens.expect(LeftBrace).ok_or(ParserError::new(LeftBrace, tokens.position()))?;
+        Ok(Self {
+            ident: (&name).into(),
+            fields: parse_fields(tokens, RightBrace)?,
+            position: struct_tok.position,
+        })
+    }
+}
+
+impl FromTokens for Enum {
+    type Error = ParserError;
+
+    ///
+    /// Parses an enum declaration. The fields of each variant are declared in
+    /// parentheses, as with the fields of a struct.
+    ///
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        use tokenizer::TokenKind::*;
+        let enum_tok = tokens.expect_ident("enum").ok_or(ParserError::new(ExpectedIdent("enum"), tokens.position()))?;
+        let name = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+        tokens.expect(LeftBrace).ok_or(ParserError::new(LeftBrace, tokens.position()))?;
+        let mut variants = Vec::new();
+        while tokens.expect(RightBrace).is_none() {
+            let variant = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+            let fields = match tokens.expect(LeftParen) {
+                Some(_) => parse_fields(tokens, RightParen)?,
+                None => Vec::new(),
+            };
+            variants.push(Variant {
+                ident: (&variant).into(),
+                fields,
+                position: variant.position,
+            });
+            if tokens.expect(Comma).is_none() {
+                tokens.expect(RightBrace).ok_or(ParserError::new(RightBrace, tokens.position()))?;
+                break;
+            }
+        }
+        Ok(Self {
+            ident: (&name).into(),
+            variants,
+            position: enum_tok.position,
+        })
+    }
+}
+
+///
+/// Parses comma separated fields `name[: type]` up to and including the closing
+/// token. A trailing comma is permitted after the last field.
+///
+fn parse_fields(tokens: &TokenStream, close: TokenKind) -> Result<Vec<ArgSpec>, ParserError> {
+    use tokenizer::TokenKind::*;
+    let mut fields = Vec::new();
+    while tokens.expect(close).is_none() {
+        let field = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+        let typ = match tokens.expect(Colon) {
+            Some(_) => Some(Typ::from_tokens(tokens)?),
+            None => None,
+        };
+        fields.push(ArgSpec {
+            name: (&field).into(),
+            typ,
+            default: None,
+            variadic: false,
+            position: field.position,
+        });
+        if tokens.expect(Comma).is_none() {
+            tokens.expect(close).ok_or(ParserError::new(close, tokens.position()))?;
+            break;
+        }
+    }
+    Ok(fields)
+}
+
+impl FromTokens for Impl {
+    type Error = ParserError;
+
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+        use tokenizer::TokenKind::*;
+        let impl_tok = tokens.expect_ident("impl").ok_or(ParserError::new(ExpectedIdent("impl"), tokens.position()))?;
+        let name = tokens.expect(Identifier).ok_or(ParserError::new(Identifier, tokens.position()))?;
+        tokens.expect(LeftBrace).ok_or(ParserError::new(LeftBrace, tokens.position()))?;
+        let mut methods = Vec::new();
+        while tokens.expect(RightBrace).is_none() {
+            methods.push(Function::from_tokens(tokens)?);
+            let _ = tokens.expect(SemiColon);
+        }
+        Ok(Self {
+            ident: (&name).into(),
+            methods,
+            position: impl_tok.position,
+        })
+    }
+}
@@ -3,11 +3,15 @@ use std::fmt::{Display, Formatter};
 use std::fmt;
 use std::str::FromStr;
 
+use common::Position;
+use indexmap::IndexMap;
+use regex::Regex;
 use tokenizer::{FromTokens, TokenStream};
 
-use crate::error::ParserError;
-use crate::error::ErrorKind::{InvalidLit, UnexpectedEOL};
-use crate::func::Function;
+use crate::error::{ErrorKind, ParserError};
+use crate::error::ErrorKind::{InvalidEscape, InvalidLit, InvalidRegex, UnexpectedEOL, UnterminatedString};
+use crate::func::{Closure, Function};
+use crate::structs::{Enum, Instance, Method, Struct, Variant};
 
 use serde::{Serialize, Deserialize};
 
@@ -19,8 +23,30 @@ pub enum Value {
     Float(f64),
     Bool(bool),
     Func(Function),
+    /// A function along with the values it captured where it was defined
+    Closure(Closure),
+    /// A builtin function, by name
+    Builtin(String),
+    /// A struct, which is called to construct an instance of it
+    Struct(Struct),
+    /// An enum, through which its variants are accessed
+    Enum(Enum),
+    /// A variant of an enum with fields, which is called to construct a value of it
+    Variant(String, Variant),
+    /// An instance of a struct, or a value of an enum
+    Instance(Instance),
+    /// A method bound to an instance
+    Method(Box<Method>),
     String(String),
-    Regex(String),
+    #[serde(with = "regex_serde")]
+    Regex(Regex),
+    List(Vec<Value>),
+    /// A map from string keys to values, which preserves insertion order
+    Map(IndexMap<String, Value>),
+    /// A half-open range of integers `start..end`
+    Range(i64, i64),
+    /// An error, as thrown by a script or raised by the interpreter
+    Error(Box<ErrorValue>),
     Named(String),
     None,
 }
@@ -32,9 +58,18 @@ impl PartialEq for Value {
             (Int(n), Int(m)) => n == m,
             (Int(n), Float(m)) => *n as f64 == *m,
             (Float(n), Int(m)) => *n == *m as f64,
+            (Float(n), Float(m)) => n == m,
             (Bool(n), Bool(m)) => n == m,
             (String(n), String(m)) => n == m,
             (Named(n), Named(m)) => n == m,
+            (Regex(n), Regex(m)) => n.as_str() == m.as_str(),
+            (List(n), List(m)) => n == m,
+            (Map(n), Map(m)) => n == m,
+            (Range(a, b), Range(c, d)) => a == c && b == d,
+            (Builtin(n), Builtin(m)) => n == m,
+            (Instance(n), Instance(m)) => n == m,
+            (Error(n), Error(m)) => n == m,
+            (None, None) => true,
             _ => false
         }
     }
@@ -64,16 +99,74 @@ impl Display for Value {
             Value::String(n) => write!(f, "{}", n),
             Value::Named(n) => write!(f, "{}", n),
             Value::Func(func) => write!(f, "{}", func.ident.name),
+            Value::Closure(closure) => write!(f, "{}", closure.func.ident.name),
+            Value::Builtin(name) => write!(f, "{}", name),
+            Value::Struct(def) => write!(f, "{}", def.ident.name),
+            Value::Enum(def) => write!(f, "{}", def.ident.name),
+            Value::Variant(name, variant) => write!(f, "{}.{}", name, variant.ident.name),
+            Value::Method(method) => write!(f, "{}", method.func.ident.name),
+            Value::Instance(instance) => {
+                write!(f, "{}", instance.name)?;
+                if let Some(variant) = &instance.variant {
+                    write!(f, ".{}", variant)?;
+                    if instance.fields.is_empty() {
+                        return Ok(());
+                    }
+                }
+                write!(f, " {{")?;
+                for (i, (name, value)) in instance.fields.iter().enumerate() {
+                    write!(f, "{}{}: {}", if i > 0 { ", " } else { " " }, name, value)?;
+                }
+                if !instance.fields.is_empty() {
+                    write!(f, " ")?;
+                }
+                write!(f, "}}")
+            }
             Value::None => write!(f, "none"),
-            Value::Regex(s) => write!(f, "{}", s)
+            Value::Regex(re) => write!(f, "{}", re.as_str()),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::Error(err) => write!(f, "{}: {}", err.kind, err.message),
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
         }
     }
 }
 
+///
+/// The value of an error, which is caught by a `try` block
+///
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ErrorValue {
+    /// The kind of error i.e. `IndexOutOfBounds`, or `Error` for those thrown by a script
+    pub kind: String,
+    pub message: String,
+    /// Where the error was raised, if known
+    pub position: Option<Position>,
+}
+
 impl FromTokens for Value {
     type Error = ParserError;
 
-    fn from_tokens(tokens: &TokenStream) -> Result<Self, Self::Error> {
+    fn from_tokens(tokens: &TokenStream) -> Result<Self, ParserError> {
         let tok = tokens.peek();
         if let Some(tok) = tok {
             use tokenizer::TokenKind::*;
@@ -90,16 +183,27 @@ impl FromTokens for Value {
                         Int { base } => {
                             Ok(match base {
                                 Hex => Self::Int(i64::from_str_radix(tok.literal.as_str(), 16).map_err(|_| err)?),
-                                Dec => Self::Int(i64::from_str_radix(tok.literal.as_str(), 10).map_err(|_| err)?),
+                                Dec => Self::Int(tok.literal.as_str().parse::<i64>().map_err(|_| err)?),
                                 Oct => Self::Int(i64::from_str_radix(tok.literal.as_str(), 8).map_err(|_| err)?),
                                 Bin => Self::Int(i64::from_str_radix(tok.literal.as_str(), 2).map_err(|_| err)?),
                             })
                         }
                         Float => Ok(Self::Float(f64::from_str(tok.literal.as_str()).map_err(|_| err)?)),
-                        String => Ok({
-                            let unescaped: std::string::String = tok.literal.chars().filter(|c| *c != '\\').collect();
-                            Self::String(unescaped[1..unescaped.len() - 1].to_owned())
-                        })
+                        String => {
+                            let mut position = tok.position;
+                            // skip the opening quote
+                            position.column += 1;
+                            let contents = tok.literal.get(1..tok.literal.len() - 1).ok_or(err)?;
+                            Ok(Self::String(unescape(contents, position)?))
+                        }
+                        RawString { hashes } => {
+                            let contents = tok.literal.get(hashes + 2..tok.literal.len() - hashes - 1).ok_or(err)?;
+                            Ok(Self::String(contents.to_owned()))
+                        }
+                        Regex => Ok(Self::Regex(parse_regex(&tok.literal).map_err(|kind| ParserError::new(kind, pos))?)),
+                        // interpolated strings are expressions, rather than values
+                        InterpolatedString => Err(err),
+                        Unterminated => Err(ParserError::new(UnterminatedString, pos)),
                     }
                 }
                 Identifier => {
@@ -119,6 +223,123 @@ impl FromTokens for Value {
     }
 }
 
+///
+/// Processes the escape sequences in the (unquoted) contents of a
+/// string literal, which begin at the given position in the source.
+///
+/// Supported escapes are `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\$`,
+/// unicode escapes `\u{XXXX}`, and ASCII escapes `\xNN` (up to `\x7F`).
+///
+pub fn unescape(raw: &str, position: Position) -> Result<String, ParserError> {
+    let mut result = String::with_capacity(raw.len());
+    let mut position = position;
+    let mut chars = raw.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            position.column += 1;
+            if c == '\n' {
+                position.line += 1;
+                position.column = 1;
+            }
+            continue;
+        }
+
+        let start = position;
+        let escape = chars.next().unwrap_or('\\');
+        let err = ParserError::new(InvalidEscape(escape), Some(start));
+        let mut len = 2;
+        result.push(match escape {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '$' => '$',
+            'x' => {
+                let digits: std::string::String = chars.by_ref().take(2).collect();
+                len += digits.len();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(n) if digits.len() == 2 && n <= 0x7f => n as char,
+                    _ => return Err(err),
+                }
+            }
+            'u' => {
+                if chars.next() != Some('{') {
+                    return Err(err);
+                }
+                let mut digits = std::string::String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                        _ => return Err(err),
+                    }
+                }
+                len += digits.len() + 2;
+                u32::from_str_radix(&digits, 16).ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or(err)?
+            }
+            _ => return Err(err),
+        });
+        position.column += len;
+    }
+
+    Ok(result)
+}
+
+///
+/// Compiles a regex literal of the form `/pattern/flags`. Flags are
+/// translated into the equivalent inline flags, so that the compiled
+/// regex can be recreated from its pattern alone.
+///
+/// Supported flags are `i` (case-insensitive), `m` (multi-line), `s` (`.` matches
+/// new lines), `x` (ignore whitespace) and `U` (swap greediness).
+///
+pub fn parse_regex(literal: &str) -> Result<Regex, ErrorKind> {
+    let end = literal.rfind('/').filter(|end| *end > 0).ok_or(InvalidLit)?;
+    let flags = &literal[end + 1..];
+    if !flags.chars().all(|c| "imsxU".contains(c)) {
+        return Err(InvalidRegex);
+    }
+
+    let mut pattern = std::string::String::new();
+    if !flags.is_empty() {
+        pattern.push_str(&format!("(?{})", flags));
+    }
+    let mut chars = literal[1..end].chars().peekable();
+    while let Some(c) = chars.next() {
+        // escaped slashes only exist to avoid terminating the literal
+        if c == '\\' && chars.peek() == Some(&'/') {
+            continue;
+        }
+        pattern.push(c);
+    }
+
+    Regex::new(&pattern).map_err(|_| InvalidRegex)
+}
+
+///
+/// Regexes are (de)serialized as their pattern, which includes any flags.
+///
+mod regex_serde {
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+
+    pub fn serialize<S: Serializer>(re: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(re.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
         Value::Bool(b)
@@ -128,4 +349,44 @@ impl From<bool> for Value {
 #[cfg(test)]
 mod test {
     // TODO: add some tests for all operations
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        let unescaped = unescape(r#"a\tb\n\\\"\$\0\x41\u{1F600}"#, Position::default()).unwrap();
+        assert_eq!(unescaped, "a\tb\n\\\"$\0A\u{1F600}");
+    }
+
+    #[test]
+    fn test_parse_regex() {
+        assert_eq!(parse_regex("/a\\/b/").unwrap().as_str(), "a/b");
+        assert_eq!(parse_regex("/abc/im").unwrap().as_str(), "(?im)abc");
+        assert!(parse_regex("/abc/i").unwrap().is_match("ABC"));
+        assert_eq!(parse_regex("/abc/g").unwrap_err(), ErrorKind::InvalidRegex);
+        assert_eq!(parse_regex("/(abc/").unwrap_err(), ErrorKind::InvalidRegex);
+    }
+
+    #[test]
+    fn test_invalid_escape() {
+        let err = unescape(r#"ab\q"#, Position::default()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidEscape('q'));
+        assert_eq!(err.position, Some(Position { line: 1, column: 3 }));
+
+        let err = unescape(r#"\u{110000}"#, Position::default()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidEscape('u'));
+
+        let err = unescape(r#"\xff"#, Position::default()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidEscape('x'));
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let err = Value::from_tokens(&TokenStream::new(r#"r"abc"#)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnterminatedString);
+        assert_eq!(err.position, Some(Position { line: 1, column: 1 }));
+
+        let err = Value::from_tokens(&TokenStream::new(r#""abc"#)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::UnterminatedString);
+        assert_eq!(Value::from_tokens(&TokenStream::new(r#"r"abc""#)).unwrap(), Value::String("abc".to_string()));
+    }
 }
\ No newline at end of file
@@ -1,4 +1,3 @@
-use ron;
 use pretty_assertions::assert_eq;
 
 use paste::item;
@@ -34,3 +33,21 @@ test_ast_creation!(while, "tests/data/while.wis");
 test_ast_creation!(if, "tests/data/if.wis");
 test_ast_creation!(func, "tests/data/func.wis");
 test_ast_creation!(multi_op_expr, "tests/data/multi-op-expr.wis");
+test_ast_creation!(comments, "tests/data/comments.wis");
+test_ast_creation!(interpolation, "tests/data/interpolation.wis");
+test_ast_creation!(strings, "tests/data/strings.wis");
+test_ast_creation!(regex, "tests/data/regex.wis");
+test_ast_creation!(lists, "tests/data/lists.wis");
+test_ast_creation!(maps, "tests/data/maps.wis");
+test_ast_creation!(for_loop, "tests/data/for.wis");
+test_ast_creation!(unary, "tests/data/unary.wis");
+test_ast_creation!(associativity, "tests/data/associativity.wis");
+test_ast_creation!(compound_assignment, "tests/data/compound_assignment.wis");
+test_ast_creation!(lambda, "tests/data/lambda.wis");
+test_ast_creation!(calls, "tests/data/calls.wis");
+test_ast_creation!(types, "tests/data/types.wis");
+test_ast_creation!(args, "tests/data/args.wis");
+test_ast_creation!(labels, "tests/data/labels.wis");
+test_ast_creation!(structs, "tests/data/structs.wis");
+test_ast_creation!(matches, "tests/data/matches.wis");
+test_ast_creation!(errors, "tests/data/errors.wis");
@@ -1,33 +1,57 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use interpreter::{SlowInterpreter, Interpreter};
+use interpreter::{SlowInterpreter, VmInterpreter, Interpreter};
 
 fn bench_simple_expression(c: &mut Criterion) {
     let mut intp = SlowInterpreter::new();
     c.bench_function("simple expr", |b| b.iter(|| intp.eval_script(black_box("1 + 5"))));
+    let mut intp = VmInterpreter::new();
+    c.bench_function("vm simple expr", |b| b.iter(|| intp.eval_script(black_box("1 + 5"))));
 }
 
 fn bench_complex_expression(c: &mut Criterion) {
-    let mut intp = SlowInterpreter::new();
     let script = r#"
 let a = 10;
 let b = 20;
 a * b - 200 + 1345
 "#;
+    let mut intp = SlowInterpreter::new();
     c.bench_function("complex expr", |b| b.iter(|| intp.eval_script(black_box(script))));
+    let mut intp = VmInterpreter::new();
+    c.bench_function("vm complex expr", |b| b.iter(|| intp.eval_script(black_box(script))));
 }
 
 fn bench_function_call(c: &mut Criterion) {
-    let mut intp = SlowInterpreter::new();
     let func = "fn foo() {}";
+    let mut intp = SlowInterpreter::new();
     intp.eval_script(func).unwrap();
     c.bench_function("function call", |b| b.iter(|| intp.eval_script(black_box("foo()"))));
+    let mut intp = VmInterpreter::new();
+    intp.eval_script(func).unwrap();
+    c.bench_function("vm function call", |b| b.iter(|| intp.eval_script(black_box("foo()"))));
+}
+
+fn bench_loop(c: &mut Criterion) {
+    let script = r#"
+let n = 0;
+let total = 0;
+while n < 1000 {
+    total = total + n % 7;
+    n = n + 1;
+}
+total
+"#;
+    let mut intp = SlowInterpreter::new();
+    c.bench_function("loop", |b| b.iter(|| intp.eval_script(black_box(script))));
+    let mut intp = VmInterpreter::new();
+    c.bench_function("vm loop", |b| b.iter(|| intp.eval_script(black_box(script))));
 }
 
 criterion_group!(
     benches,
     bench_simple_expression,
     bench_complex_expression,
-    bench_function_call
+    bench_function_call,
+    bench_loop
 );
 
-criterion_main!(benches);
\ No newline at end of file
+criterion_main!(benches);
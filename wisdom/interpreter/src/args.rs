@@ -0,0 +1,64 @@
+use ast::{ArgSpec, Function, Value};
+
+use crate::error::Error;
+
+///
+/// Binds the arguments of a call to func's parameters, giving the value of
+/// each parameter in order, or None for those left to their default. Extra
+/// positional arguments are collected into a list for a variadic parameter.
+///
+pub fn bind(func: &Function, args: Vec<Value>, named: Vec<(String, Value)>) -> Result<Vec<Option<Value>>, Error> {
+    let bindings = ArgSpec::bind(&func.args, args, named)?;
+    let mut values = bindings.args;
+    if func.args.last().is_some_and(|arg| arg.variadic) {
+        values.push(Some(Value::List(bindings.rest)));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod test {
+    use ast::{Function, Stmt, StmtKind, Value};
+    use tokenizer::{FromTokens, TokenStream};
+
+    use crate::args::bind;
+    use crate::error::Error;
+    use crate::error::ErrorKind::{DuplicateArg, MissingArg, UnexpectedArgs, UnknownArg};
+
+    fn function(script: &str) -> Function {
+        let tokens = TokenStream::new(script);
+        match Stmt::from_tokens(&tokens).unwrap().kind {
+            StmtKind::Fn(func) => func,
+            _ => panic!("expected a function definition"),
+        }
+    }
+
+    fn named(name: &str, value: i64) -> (String, Value) {
+        (name.to_owned(), Value::Int(value))
+    }
+
+    #[test]
+    fn test_bind() {
+        let func = function("fn f(a, b = 2, c = 3) {}");
+        assert_eq!(bind(&func, vec![Value::Int(1)], vec![]), Ok(vec![Some(Value::Int(1)), None, None]));
+        assert_eq!(
+            bind(&func, vec![Value::Int(1)], vec![named("c", 4)]),
+            Ok(vec![Some(Value::Int(1)), None, Some(Value::Int(4))])
+        );
+        assert_eq!(bind(&func, vec![], vec![named("b", 4)]), Err(Error::new(MissingArg("a".to_owned()))));
+        assert_eq!(bind(&func, vec![Value::Int(1)], vec![named("d", 4)]), Err(Error::new(UnknownArg("d".to_owned()))));
+        assert_eq!(bind(&func, vec![Value::Int(1)], vec![named("a", 4)]), Err(Error::new(DuplicateArg("a".to_owned()))));
+        assert_eq!(bind(&func, vec![Value::Int(1); 4], vec![]), Err(Error::new(UnexpectedArgs(3, 4))));
+    }
+
+    #[test]
+    fn test_bind_variadic() {
+        let func = function("fn f(a, ...rest) {}");
+        assert_eq!(bind(&func, vec![Value::Int(1)], vec![]), Ok(vec![Some(Value::Int(1)), Some(Value::List(vec![]))]));
+        assert_eq!(
+            bind(&func, vec![Value::Int(1), Value::Int(2), Value::Int(3)], vec![]),
+            Ok(vec![Some(Value::Int(1)), Some(Value::List(vec![Value::Int(2), Value::Int(3)]))])
+        );
+        assert_eq!(bind(&func, vec![], vec![named("rest", 1)]), Err(Error::new(UnknownArg("rest".to_owned()))));
+    }
+}
@@ -1,20 +1,55 @@
 use ast::{Value, Stmt};
 
 use crate::error::{Error, ErrorKind};
+use crate::iter::ValueIter;
+use crate::regex_cache::RegexCache;
+use crate::value::Operations;
 use tokenizer::{TokenStream, FromTokens};
 
-pub fn exists(name: &String) -> bool {
-    match name.as_str() {
-        "print" => true,
-        "ast" => true,
-        _ => false
-    }
+///
+/// The names of all of the builtin functions
+///
+pub const BUILTINS: &[&str] = &[
+    "print", "ast", "len", "lines", "keys", "values", "items", "contains", "replace", "replace_all", "split",
+    "find_all", "trim", "map", "filter", "read", "parse_int", "parse_float", "regex", "is_error",
+];
+
+pub fn exists(name: &str) -> bool {
+    BUILTINS.contains(&name)
 }
 
-pub fn run(name: &String, args: Vec<Value>) -> Result<Value, Error> {
-    match name.as_str() {
+///
+/// Calls a function value with the given arguments, on behalf of a builtin.
+///
+pub type Callback<'a> = dyn FnMut(&Value, Vec<Value>) -> Result<Value, Error> + 'a;
+
+///
+/// Runs the named builtin. Any string patterns given to the regex
+/// builtins are compiled through the interpreter's cache, and builtins
+/// that take a function call it through the interpreter's callback.
+///
+pub fn run(name: &str, args: Vec<Value>, regexes: &RegexCache, call: &mut Callback) -> Result<Value, Error> {
+    match name {
         "print" => print(args),
         "ast" => ast(args),
+        "len" => len(args),
+        "lines" => lines(args),
+        "keys" => keys(args),
+        "values" => values(args),
+        "items" => items(args),
+        "contains" => contains(args),
+        "replace" => replace(args, regexes, 1),
+        "replace_all" => replace(args, regexes, 0),
+        "split" => split(args, regexes),
+        "find_all" => find_all(args, regexes),
+        "trim" => trim(args),
+        "map" => map(args, call),
+        "filter" => filter(args, call),
+        "read" => read(args),
+        "parse_int" => parse_int(args),
+        "parse_float" => parse_float(args),
+        "regex" => regex(args, regexes),
+        "is_error" => is_error(args),
         _ => panic!("no such builtin function: {}", name)
     }
 }
@@ -23,17 +58,17 @@ pub fn print(args: Vec<Value>) -> Result<Value, Error> {
     for arg in args {
         print!("{}", arg);
     }
-    print!("\n");
+    println!();
     Ok(Value::None)
 }
 
 pub fn ast(args: Vec<Value>) -> Result<Value, Error> {
     use ron::ser::PrettyConfig;
-    let arg = args.iter().next().ok_or(
+    let arg = args.first().ok_or(
         Error::new(ErrorKind::UnexpectedArgs(0, 1))
     )?;
     if let Value::String(s) = arg {
-        let tokens = TokenStream::new(&s);
+        let tokens = TokenStream::new(s);
         let stmt = Stmt::from_tokens(&tokens)?;
         Ok(Value::String(ron::ser::to_string_pretty(&stmt, PrettyConfig::new()).unwrap_or(
             String::from("invalid statement"))))
@@ -41,3 +76,232 @@ pub fn ast(args: Vec<Value>) -> Result<Value, Error> {
         Err(Error::new(ErrorKind::InvalidType))
     }
 }
+
+///
+/// len(value) returns the number of items in a list or map, or characters in a string
+///
+pub fn len(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::List(items) => Ok(Value::Int(items.len() as i64)),
+        Value::Map(entries) => Ok(Value::Int(entries.len() as i64)),
+        Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// lines(str) splits str into a list of its lines, without line endings
+///
+pub fn lines(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(Value::List(s.lines().map(|l| Value::String(l.to_owned())).collect())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// keys(map) returns a list of the keys of map, in insertion order
+///
+pub fn keys(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::Map(entries) => Ok(Value::List(entries.keys().map(|k| Value::String(k.clone())).collect())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// values(map) returns a list of the values of map, in insertion order
+///
+pub fn values(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::Map(entries) => Ok(Value::List(entries.values().cloned().collect())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// items(map) returns a list of [key, value] pairs of map, in insertion order
+///
+pub fn items(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::Map(entries) => Ok(Value::List(entries.iter().map(|(k, v)| {
+            Value::List(vec![Value::String(k.clone()), v.clone()])
+        }).collect())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// contains(map, key) checks whether key is present in map
+///
+pub fn contains(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 2)?;
+    match (&args[0], &args[1]) {
+        (Value::Map(entries), Value::String(key)) => Ok(Value::Bool(entries.contains_key(key))),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// trim(str) removes leading and trailing whitespace from str
+///
+pub fn trim(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(Value::String(s.trim().to_owned())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// map(items, func) returns a list of the results of calling func on each item,
+/// where items is anything that can be iterated over by a for loop
+///
+pub fn map(args: Vec<Value>, call: &mut Callback) -> Result<Value, Error> {
+    expect_args(&args, 2)?;
+    let mut args = args.into_iter();
+    let mut iter = ValueIter::new(args.next().unwrap())?;
+    let func = args.next().unwrap();
+    let mut result = Vec::new();
+    while let Some(item) = iter.next_single() {
+        result.push(call(&func, vec![item])?);
+    }
+    Ok(Value::List(result))
+}
+
+///
+/// filter(items, func) returns a list of the items for which func returns a truthy value
+///
+pub fn filter(args: Vec<Value>, call: &mut Callback) -> Result<Value, Error> {
+    expect_args(&args, 2)?;
+    let mut args = args.into_iter();
+    let mut iter = ValueIter::new(args.next().unwrap())?;
+    let func = args.next().unwrap();
+    let mut result = Vec::new();
+    while let Some(item) = iter.next_single() {
+        if call(&func, vec![item.clone()])?.into_bool() {
+            result.push(item);
+        }
+    }
+    Ok(Value::List(result))
+}
+
+///
+/// read(path) returns the contents of the file at path, or an error value if it can't be read
+///
+pub fn read(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(path) => Ok(or_error_value(std::fs::read_to_string(path).map(Value::String).map_err(Error::from))),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// parse_int(str) returns the integer that str holds, or an error value if it doesn't hold one
+///
+pub fn parse_int(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(or_error_value(s.trim().parse().map(Value::Int).map_err(|_| {
+            Error::new(ErrorKind::InvalidNumber(s.clone()))
+        }))),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// parse_float(str) returns the number that str holds, or an error value if it doesn't hold one
+///
+pub fn parse_float(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(or_error_value(s.trim().parse().map(Value::Float).map_err(|_| {
+            Error::new(ErrorKind::InvalidNumber(s.clone()))
+        }))),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// regex(str) compiles str into a regex, or returns an error value if it isn't a valid one
+///
+pub fn regex(args: Vec<Value>, regexes: &RegexCache) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    match &args[0] {
+        Value::String(s) => Ok(or_error_value(regexes.compile(s).map(Value::Regex))),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// is_error(value) checks whether value is an error, such as those returned by fallible builtins
+///
+pub fn is_error(args: Vec<Value>) -> Result<Value, Error> {
+    expect_args(&args, 1)?;
+    Ok(Value::Bool(matches!(args[0], Value::Error(_))))
+}
+
+///
+/// The result of a fallible builtin, which returns its error as a value
+/// rather than raising it.
+///
+fn or_error_value(result: Result<Value, Error>) -> Value {
+    result.unwrap_or_else(Error::into_value)
+}
+
+///
+/// Checks that exactly n arguments were provided.
+///
+fn expect_args(args: &[Value], n: usize) -> Result<(), Error> {
+    if args.len() != n {
+        return Err(Error::new(ErrorKind::UnexpectedArgs(n, args.len())));
+    }
+    Ok(())
+}
+
+///
+/// Unpacks the leading string and pattern arguments of the regex builtins.
+///
+fn text_and_pattern(args: &[Value], regexes: &RegexCache) -> Result<(String, regex::Regex), Error> {
+    match (&args[0], regexes.coerce(args[1].clone())?) {
+        (Value::String(text), Value::Regex(re)) => Ok((text.clone(), re)),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// replace(str, re, repl) replaces the first limit matches (or all if limit
+/// is 0) of re in str. The replacement may refer to capture groups, e.g. $1
+///
+pub fn replace(args: Vec<Value>, regexes: &RegexCache, limit: usize) -> Result<Value, Error> {
+    expect_args(&args, 3)?;
+    let (text, re) = text_and_pattern(&args, regexes)?;
+    match &args[2] {
+        Value::String(repl) => Ok(Value::String(re.replacen(&text, limit, repl.as_str()).into_owned())),
+        _ => Err(Error::new(ErrorKind::InvalidType))
+    }
+}
+
+///
+/// split(str, re) splits str around each match of re
+///
+pub fn split(args: Vec<Value>, regexes: &RegexCache) -> Result<Value, Error> {
+    expect_args(&args, 2)?;
+    let (text, re) = text_and_pattern(&args, regexes)?;
+    Ok(Value::List(re.split(&text).map(|s| Value::String(s.to_owned())).collect()))
+}
+
+///
+/// find_all(str, re) returns every non-overlapping match of re in str
+///
+pub fn find_all(args: Vec<Value>, regexes: &RegexCache) -> Result<Value, Error> {
+    expect_args(&args, 2)?;
+    let (text, re) = text_and_pattern(&args, regexes)?;
+    Ok(Value::List(re.find_iter(&text).map(|m| Value::String(m.as_str().to_owned())).collect()))
+}
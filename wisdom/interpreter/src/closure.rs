@@ -0,0 +1,205 @@
+use ast::{Block, Expr, ExprKind, Fragment, Function, Stmt, StmtKind};
+
+///
+/// Finds the names referred to within a function's body and argument defaults,
+/// other than its own arguments, in order of first use. These are the candidates for capture when
+/// the function is defined within another function or block.
+///
+/// Names bound within the body (by `let` or `for`) are included too, which is
+/// harmless as capturing a name that is later shadowed has no effect.
+///
+pub fn free_names(func: &Function) -> Vec<String> {
+    let mut names = visit_function_body(func);
+    names.free.retain(|name| !func.args.iter().any(|arg| &arg.name.name == name));
+    names.free
+}
+
+///
+/// Finds the names that are free in the functions nested within a function, which
+/// includes any of its own arguments and locals that they capture. These are the
+/// names whose bindings are shared with the closures that capture them.
+///
+pub fn captured_names(func: &Function) -> Vec<String> {
+    visit_function_body(func).captured
+}
+
+///
+/// Finds the names that are free in the functions nested within a script.
+///
+pub fn script_captured_names(stmts: &[Stmt]) -> Vec<String> {
+    let mut names = Names::default();
+    for stmt in stmts {
+        visit_stmt(stmt, &mut names);
+    }
+    names.captured
+}
+
+#[derive(Default)]
+struct Names {
+    /// Every name referred to, in order of first use
+    free: Vec<String>,
+    /// The names referred to by nested functions
+    captured: Vec<String>,
+}
+
+fn add_name(name: &str, names: &mut Vec<String>) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_owned());
+    }
+}
+
+fn visit_function_body(func: &Function) -> Names {
+    let mut names = Names::default();
+    for default in func.args.iter().filter_map(|arg| arg.default.as_ref()) {
+        visit_expr(default, &mut names);
+    }
+    visit_block(&func.block, &mut names);
+    names
+}
+
+fn visit_block(block: &Block, names: &mut Names) {
+    for stmt in &block.stmts {
+        visit_stmt(stmt, names);
+    }
+}
+
+fn visit_stmt(stmt: &Stmt, names: &mut Names) {
+    match &stmt.kind {
+        StmtKind::Expr(expr) => visit_expr(expr, names),
+        StmtKind::Fn(func) => visit_function(func, names),
+        // methods are looked up through their struct when called, so they never capture
+        StmtKind::Struct(_) | StmtKind::Enum(_) | StmtKind::Impl(_) => {}
+    }
+}
+
+///
+/// A nested function's free names must be captured by this function
+/// in turn, so that they are available when the nested one is created.
+///
+fn visit_function(func: &Function, names: &mut Names) {
+    for name in free_names(func) {
+        add_name(&name, &mut names.free);
+        add_name(&name, &mut names.captured);
+    }
+}
+
+fn visit_expr(expr: &Expr, names: &mut Names) {
+    use ExprKind::*;
+    match &expr.kind {
+        Let(_, rhs) => {
+            if let Some(rhs) = rhs {
+                visit_expr(rhs, names);
+            }
+        }
+        Assign(lhs, rhs) | AssignOp(lhs, _, rhs) | BinOp(lhs, _, rhs) | Index(lhs, rhs) => {
+            visit_expr(lhs, names);
+            visit_expr(rhs, names);
+        }
+        Unary(_, operand) | Field(operand, _) | Throw(operand) | Propagate(operand) => visit_expr(operand, names),
+        For(_, _, _, iterable, block) => {
+            visit_expr(iterable, names);
+            visit_block(block, names);
+        }
+        While(_, cond, block) => {
+            visit_expr(cond, names);
+            visit_block(block, names);
+        }
+        If(cond, block, maybe_else) => {
+            visit_expr(cond, names);
+            visit_block(block, names);
+            if let Some(expr) = maybe_else {
+                visit_expr(expr, names);
+            }
+        }
+        Match(subject, arms) => {
+            visit_expr(subject, names);
+            for arm in arms {
+                if let Some(guard) = &arm.guard {
+                    visit_expr(guard, names);
+                }
+                visit_expr(&arm.body, names);
+            }
+        }
+        Try(block, _, catch) => {
+            visit_block(block, names);
+            visit_block(catch, names);
+        }
+        Block(block) => visit_block(block, names),
+        Lambda(func) => visit_function(func, names),
+        Call(callee, args, named) => {
+            visit_expr(callee, names);
+            for arg in args {
+                visit_expr(arg, names);
+            }
+            for (_, arg) in named {
+                visit_expr(arg, names);
+            }
+        }
+        Interpolate(fragments) => {
+            for fragment in fragments {
+                if let Fragment::Expr(expr) = fragment {
+                    visit_expr(expr, names);
+                }
+            }
+        }
+        Ident(ident) => add_name(&ident.name, &mut names.free),
+        List(items) => {
+            for item in items {
+                visit_expr(item, names);
+            }
+        }
+        Map(entries) => {
+            for (key, value) in entries {
+                visit_expr(key, names);
+                visit_expr(value, names);
+            }
+        }
+        Ret(expr) => visit_expr(expr, names),
+        Break(_, value) => {
+            if let Some(value) = value {
+                visit_expr(value, names);
+            }
+        }
+        Literal(_) | Continue(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ast::{Stmt, StmtKind};
+    use tokenizer::{FromTokens, TokenStream};
+
+    use crate::closure::{captured_names, free_names};
+
+    fn names_of(script: &str) -> Vec<String> {
+        let tokens = TokenStream::new(script);
+        match Stmt::from_tokens(&tokens).unwrap().kind {
+            StmtKind::Fn(func) => free_names(&func),
+            _ => panic!("expected a function definition"),
+        }
+    }
+
+    #[test]
+    fn test_free_names() {
+        assert_eq!(names_of("fn f(a) { a + b }"), vec!["b"]);
+        assert_eq!(names_of("fn f(a) { let c = a; c = b * \"${d}\"; foo(c) }"), vec!["c", "b", "d", "foo"]);
+        assert!(names_of("fn f(a, b) { a[b] }").is_empty());
+        assert_eq!(names_of("fn f(a, b = a + c) { g(a, sep = d) }"), vec!["c", "g", "d"]);
+    }
+
+    #[test]
+    fn test_nested_free_names() {
+        assert_eq!(names_of("fn f(a) { |x| x + a + b }"), vec!["b"]);
+        assert_eq!(names_of("fn f() { fn g(y) { y + z } }"), vec!["z"]);
+    }
+
+    #[test]
+    fn test_captured_names() {
+        let tokens = TokenStream::new("fn f(a, b) { let c = a; fn g() { g(c) } |x| x + b }");
+        let func = match Stmt::from_tokens(&tokens).unwrap().kind {
+            StmtKind::Fn(func) => func,
+            _ => panic!("expected a function definition"),
+        };
+        assert_eq!(captured_names(&func), vec!["g", "c", "b"]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::fmt;
 
+use ast::{BindError, ErrorValue, Value};
 use ast::error::ParserError;
 use common::{Position, WisdomError};
 use tokenizer::Token;
@@ -8,7 +9,8 @@ use tokenizer::Token;
 #[derive(PartialEq, Debug, Clone)]
 pub struct Error {
     pub kind: ErrorKind,
-    pub position: Position,
+    /// Where the error was raised, once known
+    pub position: Option<Position>,
 }
 
 impl Display for Error {
@@ -23,26 +25,90 @@ impl Error {
             ErrorKind::Parser(p) => format!("{}", p),
             ErrorKind::UndefinedVar(name) => format!("Undefined variable '{}'", name),
             ErrorKind::Unexpected(tok) => format!("Unexpected token '{:?}'", tok.kind),
-            ErrorKind::InvalidType => format!("Invalid type in expression"),
+            ErrorKind::InvalidType => "Invalid type in expression".to_string(),
             ErrorKind::InvalidRegex(e) => format!("Failed to compile regex: {}", e),
             ErrorKind::IOError(io) => format!("IO Error: {}", io),
             ErrorKind::UnexpectedArgs(exp, act) => format!("Expected {} args, got {}", exp, act),
-            ErrorKind::InvalidAssignment => format!("Invalid assignment"),
-            ErrorKind::NotCallable => format!("not callable"),
-            ErrorKind::BreakInWrongContext => format!("unable to use 'break' in this context"),
-            ErrorKind::ContinueInWrongContext => format!("unable to use 'continue' in this context")
+            ErrorKind::UnknownArg(name) => format!("Unknown argument '{}'", name),
+            ErrorKind::DuplicateArg(name) => format!("Argument '{}' given more than once", name),
+            ErrorKind::MissingArg(name) => format!("Missing argument '{}'", name),
+            ErrorKind::InvalidAssignment => "Invalid assignment".to_string(),
+            ErrorKind::IndexOutOfBounds(idx, len) => format!("Index {} out of bounds for length {}", idx, len),
+            ErrorKind::KeyNotFound(key) => format!("Key '{}' not found", key),
+            ErrorKind::UnknownField(name) => format!("Unknown field or method '{}'", name),
+            ErrorKind::Overflow => "Integer overflow".to_string(),
+            ErrorKind::NotCallable => "not callable".to_string(),
+            ErrorKind::BreakInWrongContext => "unable to use 'break' in this context".to_string(),
+            ErrorKind::ContinueInWrongContext => "unable to use 'continue' in this context".to_string(),
+            ErrorKind::TypeMismatch(exp, act) => format!("Expected a value of type '{}', got '{}'", exp, act),
+            ErrorKind::UnknownType(name) => format!("Unknown type '{}'", name),
+            ErrorKind::InvalidNumber(text) => format!("Invalid number '{}'", text),
+            ErrorKind::Thrown(err) => format!("{}: {}", err.kind, err.message),
+        }
+    }
+
+    ///
+    /// The error raised by throwing a value. Error values are rethrown as they are,
+    /// whereas anything else becomes the message of an error of kind `Error`.
+    ///
+    pub fn thrown(value: Value, position: Position) -> Self {
+        let err = match value {
+            Value::Error(err) => err,
+            value => Box::new(ErrorValue { kind: "Error".to_owned(), message: value.to_string(), position: Some(position) }),
+        };
+        Self {
+            position: err.position,
+            kind: ErrorKind::Thrown(err),
+        }
+    }
+
+    ///
+    /// The value that a catch block is given for this error. Errors thrown by
+    /// a script are caught as the value they were thrown as. The position is
+    /// none for errors that weren't raised by an expression, such as those
+    /// returned as values by fallible builtins.
+    ///
+    pub fn into_value(self) -> Value {
+        let message = self.description();
+        match self.kind {
+            ErrorKind::Thrown(err) => Value::Error(err),
+            kind => Value::Error(Box::new(ErrorValue { kind: kind.name().to_owned(), message, position: self.position })),
         }
     }
 }
 
 impl WisdomError for Error {
     fn position(&self) -> Position {
-        self.position
+        self.position.unwrap_or_default()
     }
 }
 
 impl std::error::Error for Error {}
 
+///
+/// A problem found while running a script, which doesn't stop it from running.
+/// Warnings are collected by the interpreter for its caller to report.
+///
+#[derive(PartialEq, Debug, Clone)]
+pub struct Warning {
+    pub message: String,
+    pub position: Position,
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl WisdomError for Warning {
+    fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl std::error::Error for Warning {}
+
 #[derive(PartialEq, Debug, Clone)]
 pub enum ErrorKind {
     Parser(ParserError),
@@ -51,38 +117,121 @@ pub enum ErrorKind {
     InvalidType,
     InvalidRegex(regex::Error),
     InvalidAssignment,
+    IndexOutOfBounds(i64, usize),
+    KeyNotFound(String),
+    /// A field or method that a struct does not have
+    UnknownField(String),
+    Overflow,
     NotCallable,
     IOError(String),
     UnexpectedArgs(usize, usize),
+    /// A named argument that the function does not have
+    UnknownArg(String),
+    DuplicateArg(String),
+    /// A parameter without a default, that was not given an argument
+    MissingArg(String),
     BreakInWrongContext,
     ContinueInWrongContext,
+    /// A value did not match its type annotation, with the annotated and actual type names
+    TypeMismatch(String, String),
+    UnknownType(String),
+    InvalidNumber(String),
+    /// An error thrown by a script, and not caught
+    Thrown(Box<ErrorValue>),
+}
+
+impl ErrorKind {
+    ///
+    /// The name of the kind of error, which is the `kind` of the value it is caught as
+    ///
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Parser(_) => "Parser",
+            ErrorKind::UndefinedVar(_) => "UndefinedVar",
+            ErrorKind::Unexpected(_) => "Unexpected",
+            ErrorKind::InvalidType => "InvalidType",
+            ErrorKind::InvalidRegex(_) => "InvalidRegex",
+            ErrorKind::InvalidAssignment => "InvalidAssignment",
+            ErrorKind::IndexOutOfBounds(_, _) => "IndexOutOfBounds",
+            ErrorKind::KeyNotFound(_) => "KeyNotFound",
+            ErrorKind::UnknownField(_) => "UnknownField",
+            ErrorKind::Overflow => "Overflow",
+            ErrorKind::NotCallable => "NotCallable",
+            ErrorKind::IOError(_) => "IOError",
+            ErrorKind::UnexpectedArgs(_, _) => "UnexpectedArgs",
+            ErrorKind::UnknownArg(_) => "UnknownArg",
+            ErrorKind::DuplicateArg(_) => "DuplicateArg",
+            ErrorKind::MissingArg(_) => "MissingArg",
+            ErrorKind::BreakInWrongContext => "BreakInWrongContext",
+            ErrorKind::ContinueInWrongContext => "ContinueInWrongContext",
+            ErrorKind::TypeMismatch(_, _) => "TypeMismatch",
+            ErrorKind::UnknownType(_) => "UnknownType",
+            ErrorKind::InvalidNumber(_) => "InvalidNumber",
+            ErrorKind::Thrown(_) => "Error",
+        }
+    }
 }
 
 impl From<ParserError> for Error {
     fn from(p: ParserError) -> Self {
         Self {
             kind: ErrorKind::Parser(p),
-            position: p.position(),
+            position: p.position,
         }
     }
 }
 
+impl From<BindError> for Error {
+    fn from(err: BindError) -> Self {
+        Error::new(match err {
+            BindError::TooMany(exp, act) => ErrorKind::UnexpectedArgs(exp, act),
+            BindError::Unknown(name) => ErrorKind::UnknownArg(name),
+            BindError::Duplicate(name) => ErrorKind::DuplicateArg(name),
+            BindError::Missing(name) => ErrorKind::MissingArg(name),
+        })
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(io: std::io::Error) -> Self {
         Self {
             kind: ErrorKind::IOError(io.to_string()),
-            position: Default::default(),
+            position: None,
         }
     }
 }
 
 impl Error {
+    ///
+    /// An error without a position, which is given the position of the expression
+    /// that raised it as it propagates.
+    ///
     pub fn new(kind: ErrorKind) -> Self {
         Self {
             kind,
-            // TODO: update to use an actual position
-            position: Default::default(),
+            position: None,
+        }
+    }
+
+    pub fn new_at(kind: ErrorKind, position: Position) -> Self {
+        Self {
+            kind,
+            position: Some(position),
+        }
+    }
+
+    ///
+    /// Gives the error a position, if it doesn't already have one.
+    ///
+    pub fn or_position(mut self, position: Position) -> Self {
+        if self.position.is_none() {
+            self.position = Some(position);
+            // rethrowing an error value without a position gives it this one
+            if let ErrorKind::Thrown(err) = &mut self.kind {
+                err.position = Some(position);
+            }
         }
+        self
     }
 }
 
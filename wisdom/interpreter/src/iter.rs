@@ -0,0 +1,93 @@
+use std::ops::Range;
+use std::vec;
+
+use ast::Value;
+
+use crate::error::Error;
+use crate::error::ErrorKind::InvalidType;
+
+enum Elements {
+    Range(Range<i64>),
+    Items(vec::IntoIter<Value>),
+    Entries(vec::IntoIter<(String, Value)>),
+}
+
+///
+/// Iterates over the elements of a value, as used by for loops.
+///
+/// Every element has a key and a value. For sequences (ranges, lists
+/// and the characters of strings) the key is the index of the element,
+/// and for maps it is the element's key. Loops with a single variable
+/// are given the value of each element of a sequence, but the key of
+/// each element of a map.
+///
+pub struct ValueIter {
+    elements: Elements,
+    index: i64,
+}
+
+impl ValueIter {
+    pub fn new(value: Value) -> Result<Self, Error> {
+        let elements = match value {
+            Value::Range(start, end) => Elements::Range(start..end),
+            Value::List(items) => Elements::Items(items.into_iter()),
+            Value::String(s) => {
+                let chars: Vec<Value> = s.chars().map(|c| Value::String(c.to_string())).collect();
+                Elements::Items(chars.into_iter())
+            }
+            Value::Map(entries) => {
+                let entries: Vec<(String, Value)> = entries.into_iter().collect();
+                Elements::Entries(entries.into_iter())
+            }
+            _ => return Err(Error::new(InvalidType))
+        };
+        Ok(Self { elements, index: 0 })
+    }
+
+    ///
+    /// Advances the iterator, returning the key and value of the next element.
+    ///
+    pub fn next_pair(&mut self) -> Option<(Value, Value)> {
+        let pair = match &mut self.elements {
+            Elements::Range(range) => (Value::Int(self.index), Value::Int(range.next()?)),
+            Elements::Items(items) => (Value::Int(self.index), items.next()?),
+            Elements::Entries(entries) => {
+                let (key, value) = entries.next()?;
+                (Value::String(key), value)
+            }
+        };
+        self.index += 1;
+        Some(pair)
+    }
+
+    ///
+    /// Advances the iterator, returning the value bound by a single variable loop.
+    ///
+    pub fn next_single(&mut self) -> Option<Value> {
+        let keyed = matches!(self.elements, Elements::Entries(_));
+        let (key, value) = self.next_pair()?;
+        Some(if keyed { key } else { value })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_iterate_range() {
+        let mut iter = ValueIter::new(Value::Range(3, 5)).unwrap();
+        assert_eq!(iter.next_pair(), Some((Value::Int(0), Value::Int(3))));
+        assert_eq!(iter.next_single(), Some(Value::Int(4)));
+        assert_eq!(iter.next_single(), None);
+    }
+
+    #[test]
+    fn test_iterate_map() {
+        let map = Value::Map(vec![("b".to_string(), Value::Int(1)), ("a".to_string(), Value::Int(2))].into_iter().collect());
+        let mut iter = ValueIter::new(map).unwrap();
+        assert_eq!(iter.next_single(), Some(Value::String("b".to_string())));
+        assert_eq!(iter.next_pair(), Some((Value::String("a".to_string()), Value::Int(2))));
+        assert!(ValueIter::new(Value::Int(1)).is_err());
+    }
+}
@@ -4,12 +4,23 @@ extern crate tokenizer;
 use std::path::PathBuf;
 
 use common::WisdomError;
+use error::Warning;
+pub use builtin::BUILTINS;
 pub use slow::*;
+pub use vm::VmInterpreter;
 
 mod scope;
 mod slow;
 mod builtin;
 mod value;
+mod regex_cache;
+mod iter;
+mod closure;
+mod args;
+mod structs;
+mod pattern;
+mod types;
+mod vm;
 pub mod error;
 
 pub trait Interpreter<T, W: WisdomError> {
@@ -29,4 +40,10 @@ pub trait Interpreter<T, W: WisdomError> {
     /// Evaluate a multi-line script
     ///
     fn eval_script(&mut self, script: &str) -> Result<T, W>;
+
+    ///
+    /// Takes the warnings raised since they were last taken, so that
+    /// the caller can decide whether and how to report them.
+    ///
+    fn take_warnings(&mut self) -> Vec<Warning>;
 }
@@ -0,0 +1,159 @@
+use ast::{Pattern, Value};
+use common::Position;
+
+use crate::error::Warning;
+use crate::regex_cache;
+
+///
+/// The names bound by a pattern that matched
+///
+#[derive(Default)]
+pub struct Matched {
+    /// The values of the names bound by the pattern, in the order of `Pattern::bindings`
+    pub bindings: Vec<(String, Value)>,
+    /// The capture groups of any regexes within the pattern
+    pub captures: Vec<(String, Value)>,
+}
+
+///
+/// Matches a value against a pattern, returning the names that it binds if it matches.
+///
+pub fn matches(pattern: &Pattern, value: &Value) -> Option<Matched> {
+    let mut matched = Matched::default();
+    visit(pattern, value, &mut matched).then_some(matched)
+}
+
+///
+/// The names of the capture groups of any regexes within the pattern,
+/// in the order of `Matched::captures` when it matches.
+///
+pub fn captures(pattern: &Pattern) -> Vec<String> {
+    match pattern {
+        Pattern::Literal(Value::Regex(re)) => regex_cache::group_names(re),
+        Pattern::List(items, rest) => items.iter().chain(rest.as_deref()).flat_map(captures).collect(),
+        Pattern::Struct(_, fields) => fields.iter().flat_map(|(_, field)| captures(field)).collect(),
+        Pattern::Variant(_, _, fields) => fields.iter().flatten().flat_map(captures).collect(),
+        _ => Vec::new(),
+    }
+}
+
+///
+/// Records a warning that a match on a value of an enum had no arm for its variant, as
+/// the match then evaluates to none, which is unlikely to be what was intended. Values of
+/// other types aren't expected to be matched exhaustively. A match that is run repeatedly
+/// is only warned about once for each variant.
+///
+pub fn warn_unmatched(value: &Value, position: Position, warnings: &mut Vec<Warning>) {
+    if let Value::Instance(instance) = value {
+        if let Some(variant) = &instance.variant {
+            let warning = Warning {
+                message: format!("match on '{}' has no arm for variant '{}'", instance.name, variant),
+                position,
+            };
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+}
+
+fn visit(pattern: &Pattern, value: &Value, matched: &mut Matched) -> bool {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Bind(ident), value) => {
+            matched.bindings.push((ident.name.clone(), value.clone()));
+            true
+        }
+        (Pattern::Literal(Value::Regex(re)), Value::String(text)) => match regex_cache::captures(re, text) {
+            Some(groups) => {
+                matched.captures.extend(groups);
+                true
+            }
+            None => false,
+        },
+        (Pattern::Literal(Value::Regex(_)), _) => false,
+        (Pattern::Literal(literal), value) => literal == value,
+        (Pattern::Range(start, end), Value::Int(n)) => (*start..*end).contains(n),
+        (Pattern::List(items, rest), Value::List(values)) => {
+            let fits = match rest {
+                Some(_) => values.len() >= items.len(),
+                None => values.len() == items.len(),
+            };
+            fits && items.iter().zip(values).all(|(item, value)| visit(item, value, matched))
+                && match rest {
+                    Some(rest) => visit(rest, &Value::List(values[items.len()..].to_vec()), matched),
+                    None => true,
+                }
+        }
+        (Pattern::Struct(name, fields), Value::Instance(instance)) => {
+            instance.name == name.name && instance.variant.is_none() && fields.iter().all(|(field, pattern)| {
+                instance.fields.get(&field.name).is_some_and(|value| visit(pattern, value, matched))
+            })
+        }
+        (Pattern::Variant(name, variant, fields), Value::Instance(instance)) => {
+            instance.name == name.name && instance.variant.as_ref() == Some(&variant.name) && match fields {
+                Some(fields) => {
+                    fields.len() == instance.fields.len()
+                        && fields.iter().zip(instance.fields.values()).all(|(field, value)| visit(field, value, matched))
+                }
+                None => true,
+            }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ast::{Expr, ExprKind, Value};
+    use tokenizer::{FromTokens, TokenStream};
+
+    use crate::pattern::{captures, matches};
+
+    fn pattern_of(pattern: &str) -> ast::Pattern {
+        let tokens = TokenStream::new(&format!("match x {{ {} => 1 }}", pattern));
+        match Expr::from_tokens(&tokens).unwrap().kind {
+            ExprKind::Match(_, mut arms) => arms.remove(0).pattern,
+            _ => panic!("expected a match expression"),
+        }
+    }
+
+    fn bindings(pattern: &str, value: Value) -> Option<Vec<(String, Value)>> {
+        matches(&pattern_of(pattern), &value).map(|matched| matched.bindings)
+    }
+
+    #[test]
+    fn test_literal_patterns() {
+        assert_eq!(bindings("1", Value::Int(1)), Some(vec![]));
+        assert_eq!(bindings("1", Value::Float(1.0)), Some(vec![]));
+        assert_eq!(bindings("-1", Value::Int(1)), None);
+        assert_eq!(bindings("\"a\"", Value::String("a".to_string())), Some(vec![]));
+        assert_eq!(bindings("none", Value::None), Some(vec![]));
+        assert_eq!(bindings("1..3", Value::Int(3)), None);
+        assert_eq!(bindings("1..=3", Value::Int(3)), Some(vec![]));
+        assert_eq!(bindings("_", Value::Bool(true)), Some(vec![]));
+        assert_eq!(bindings("x", Value::Bool(true)), Some(vec![("x".to_string(), Value::Bool(true))]));
+    }
+
+    #[test]
+    fn test_regex_patterns() {
+        let matched = matches(&pattern_of("/(\\d+)-(?P<end>\\d+)/"), &Value::String("1-2".to_string())).unwrap();
+        assert!(matched.bindings.is_empty());
+        assert!(matched.captures.contains(&("$1".to_string(), Value::String("1".to_string()))));
+        assert!(matched.captures.contains(&("$end".to_string(), Value::String("2".to_string()))));
+        assert!(matches(&pattern_of("/a/"), &Value::Int(1)).is_none());
+        assert_eq!(captures(&pattern_of("[/(a)/, /(?P<b>b)/]")), vec!["$0", "$1", "$0", "$1", "$b"]);
+    }
+
+    #[test]
+    fn test_list_patterns() {
+        let list = Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(3)]);
+        assert_eq!(bindings("[a, b]", list.clone()), None);
+        assert_eq!(bindings("[1, _, c]", list.clone()), Some(vec![("c".to_string(), Value::Int(3))]));
+        assert_eq!(
+            bindings("[a, ...rest]", list.clone()),
+            Some(vec![("a".to_string(), Value::Int(1)), ("rest".to_string(), Value::List(vec![Value::Int(2), Value::Int(3)]))])
+        );
+        assert_eq!(bindings("[2, ...]", list), None);
+    }
+}
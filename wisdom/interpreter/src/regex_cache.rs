@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ast::Value;
+use regex::Regex;
+
+use crate::error::Error;
+use crate::error::ErrorKind::InvalidRegex;
+
+///
+/// Upper bound on the number of cached patterns, so that scripts which
+/// build many distinct patterns dynamically don't grow the cache forever.
+///
+const MAX_CACHED: usize = 256;
+
+///
+/// Compiles regexes from string patterns, compiling each distinct
+/// pattern only once.
+///
+#[derive(Default)]
+pub struct RegexCache {
+    cache: RefCell<HashMap<String, Regex>>,
+}
+
+impl RegexCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///
+    /// Returns the compiled regex for the pattern, compiling and caching
+    /// it if it has not been seen before.
+    ///
+    pub fn compile(&self, pattern: &str) -> Result<Regex, Error> {
+        if let Some(re) = self.cache.borrow().get(pattern) {
+            return Ok(re.clone());
+        }
+
+        let re = Regex::new(pattern).map_err(|e| Error::new(InvalidRegex(e)))?;
+        let mut cache = self.cache.borrow_mut();
+        if cache.len() >= MAX_CACHED {
+            cache.clear();
+        }
+        cache.insert(pattern.to_owned(), re.clone());
+        Ok(re)
+    }
+
+    ///
+    /// Converts string values into regex values, so that they can be used
+    /// as patterns. Any other value is returned unchanged.
+    ///
+    pub fn coerce(&self, value: Value) -> Result<Value, Error> {
+        match value {
+            Value::String(s) => Ok(Value::Regex(self.compile(&s)?)),
+            value => Ok(value),
+        }
+    }
+}
+
+///
+/// Matches the regex against the text, returning the capture groups
+/// as named values if it matched. Numbered groups are named `$0`, `$1`
+/// and so on, and named groups are additionally bound as `$name`.
+/// Groups that did not participate in the match are `none`.
+///
+pub fn captures(re: &Regex, text: &str) -> Option<Vec<(String, Value)>> {
+    let caps = re.captures(text)?;
+    let group = |m: Option<regex::Match>| match m {
+        Some(m) => Value::String(m.as_str().to_owned()),
+        None => Value::None,
+    };
+
+    let mut groups = Vec::new();
+    for (i, name) in re.capture_names().enumerate() {
+        groups.push((format!("${}", i), group(caps.get(i))));
+        if let Some(name) = name {
+            groups.push((format!("${}", name), group(caps.name(name))));
+        }
+    }
+    Some(groups)
+}
+
+///
+/// The names that a successful match binds the regex's capture groups
+/// to, in the order that `captures` returns them.
+///
+pub fn group_names(re: &Regex) -> Vec<String> {
+    let mut names = Vec::new();
+    for (i, name) in re.capture_names().enumerate() {
+        names.push(format!("${}", i));
+        if let Some(name) = name {
+            names.push(format!("${}", name));
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compile_once() {
+        let cache = RegexCache::new();
+        cache.compile("[0-9]+").unwrap();
+        cache.compile("[0-9]+").unwrap();
+        assert_eq!(cache.cache.borrow().len(), 1);
+        assert!(cache.compile("(").is_err());
+    }
+
+    #[test]
+    fn test_captures() {
+        let re = Regex::new(r"(?P<key>\w+)=(\d+)?").unwrap();
+        let groups = captures(&re, "a=").unwrap();
+        let names: Vec<&str> = groups.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["$0", "$1", "$key", "$2"]);
+        assert_eq!(groups[0].1, Value::String("a=".to_string()));
+        assert_eq!(groups[2].1, Value::String("a".to_string()));
+        assert!(matches!(groups[3].1, Value::None));
+        assert!(captures(&re, "nope").is_none());
+    }
+}
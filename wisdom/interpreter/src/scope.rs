@@ -1,9 +1,14 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
-use ast::Value;
+use ast::{Capture, Value};
 
-pub type Scope = HashMap<String, Value>;
+///
+/// The bindings of a scope. Each is held in a cell of its own, which a closure
+/// defined within the scope shares rather than copying.
+///
+pub type Scope = HashMap<String, Capture>;
 
 pub struct Context {
     /// This is treated as a stack, and is used
@@ -30,6 +35,34 @@ impl Context {
         result
     }
 
+    ///
+    /// Runs a function within a new scope, which starts out holding the given
+    /// bindings i.e. the names bound by a match pattern.
+    ///
+    pub fn scoped_with<R, E>(&self, bindings: Vec<(String, Value)>, func: impl Fn() -> Result<R, E>) -> Result<R, E> {
+        self.push();
+        self.scopes.borrow_mut().last_mut().expect("there should always be at least one scope")
+            .extend(bindings.into_iter().map(|(name, value)| (name, Rc::new(RefCell::new(value)))));
+        let result = func();
+        self.pop();
+        result
+    }
+
+    ///
+    /// Runs a function's body within a new scope, which starts out holding the given
+    /// shared bindings i.e. the captures of a closure. Only the global scope is visible
+    /// from within it, and not those of the caller, so that names are resolved lexically.
+    ///
+    pub fn scoped_call<R, E>(&self, bindings: &[(String, Capture)], func: impl Fn() -> Result<R, E>) -> Result<R, E> {
+        let hidden = self.scopes.borrow_mut().split_off(1);
+        self.scopes.borrow_mut().push(bindings.iter().cloned().collect());
+        let result = func();
+        let mut scopes = self.scopes.borrow_mut();
+        scopes.truncate(1);
+        scopes.extend(hidden);
+        result
+    }
+
     ///
     /// Push a new scope to the context, so that subsequent
     /// variable storage will be placed in this new scope.
@@ -57,10 +90,24 @@ impl Context {
     /// backwards up the Scope stack to find the first occurrence of
     /// the name.
     ///
-    pub fn lookup(&self, name: &String) -> Option<Value> {
+    pub fn lookup(&self, name: &str) -> Option<Value> {
         for scope in self.scopes.borrow().iter().rev() {
             if let Some(v) = scope.get(name) {
-                return Some(v.to_owned());
+                return Some(v.borrow().to_owned());
+            }
+        }
+        None
+    }
+
+    ///
+    /// Lookup the binding of a name within any scope except the outermost, global
+    /// one. These are the bindings that a closure captures, whereas globals
+    /// are always looked up when used.
+    ///
+    pub fn lookup_local(&self, name: &str) -> Option<Capture> {
+        for scope in self.scopes.borrow().iter().skip(1).rev() {
+            if let Some(binding) = scope.get(name) {
+                return Some(binding.clone());
             }
         }
         None
@@ -69,30 +116,41 @@ impl Context {
     ///
     /// Checks whether a given name exists in the context
     ///
-    pub fn exists(&self, name: &String) -> bool {
+    pub fn exists(&self, name: &str) -> bool {
         self.lookup(name).is_some()
     }
 
     ///
     /// Stores a variable and its value into the scope. If it exists
-    /// in the context, it is overwritten with the new value.
+    /// in the context, it is overwritten with the new value, which any
+    /// closures sharing the binding see too.
     /// If it doesn't exist, then it is added to the top scope.
     ///
     pub fn store(&self, name: String, value: Value) {
-        if let Some(_) = self.lookup(&name) {
-            for scope in self.scopes.borrow_mut().iter_mut().rev() {
-                if let Some(_) = scope.get_mut(&name) {
-                    scope.insert(name.to_owned(), value.to_owned());
-                }
-            }
-        } else {
-            self.store_top(name, value)
+        let existing = self.scopes.borrow().iter().rev().find_map(|scope| scope.get(&name).cloned());
+        match existing {
+            // only the innermost binding is updated, any it shadows are left alone
+            Some(existing) => *existing.borrow_mut() = value,
+            None => self.store_top(name, value),
         }
     }
 
+    ///
+    /// Binds the capture groups of a successful regex match into the
+    /// top scope, replacing any groups left over from a previous match
+    /// in the same scope.
+    ///
+    pub fn store_captures(&self, groups: Vec<(String, Value)>) {
+        let mut scopes = self.scopes.borrow_mut();
+        let scope = scopes.last_mut().expect("there should always be at least one scope");
+        scope.retain(|name, _| !name.starts_with('$'));
+        scope.extend(groups.into_iter().map(|(name, value)| (name, Rc::new(RefCell::new(value)))));
+    }
+
     ///
     /// Inserts a new variable in the top scope, most useful
-    /// for pushing function arguments.
+    /// for pushing function arguments. It has a binding of its own,
+    /// even if it shadows one of the same name.
     ///
     pub fn store_top(&self, name: String, value: Value) {
         let mut scopes = self.scopes.borrow_mut();
@@ -100,7 +158,7 @@ impl Context {
         let scope = scopes.get_mut(end - 1);
         match scope {
             Some(scope) => {
-                scope.insert(name, value);
+                scope.insert(name, Rc::new(RefCell::new(value)));
             }
             None => panic!("there should always be at least one scope")
         }
@@ -1,26 +1,33 @@
+use std::cell::RefCell;
 use std::path::PathBuf;
 
-use ast::{BinOp, Block, Value, Function};
+use ast::{Arm, BinOp, Block, Value, Function, Fragment, Ident, Capture, Closure};
 use ast::{Expr, ExprKind, Stmt, StmtKind};
+use common::Position;
 use tokenizer::{FromTokens, TokenStream};
 
-use crate::{builtin, Interpreter};
-use crate::error::Error;
-use crate::error::ErrorKind::{InvalidAssignment, NotCallable, UndefinedVar, UnexpectedArgs, BreakInWrongContext, ContinueInWrongContext};
+use crate::{args, builtin, closure, pattern, types, Interpreter};
+use crate::error::{Error, Warning};
+use crate::error::ErrorKind::{InvalidAssignment, InvalidType, NotCallable, UndefinedVar, UnknownArg, BreakInWrongContext, ContinueInWrongContext};
+use crate::iter::ValueIter;
+use crate::regex_cache::{self, RegexCache};
 use crate::scope::Context;
+use crate::structs::Structs;
 use crate::value::Operations;
 
 #[derive(Clone)]
 enum VarContext<T: Clone> {
     Ret(T),
     Norm(T),
-    Break,
-    Continue,
+    /// Leaving the loop with the given label (or the innermost), which evaluates to the value
+    Break(Option<String>, T),
+    /// Skipping to the next iteration of the loop with the given label (or the innermost)
+    Continue(Option<String>),
 }
 
-impl Into<VarContext<Value>> for Value {
-    fn into(self) -> VarContext<Value> {
-        VarContext::Norm(self)
+impl From<Value> for VarContext<Value> {
+    fn from(val: Value) -> Self {
+        VarContext::Norm(val)
     }
 }
 
@@ -29,7 +36,7 @@ macro_rules! vctx {
         {
             let v = $value;
             match &v {
-                VarContext::Ret(_) | VarContext::Break | VarContext::Continue => return Ok(v),
+                VarContext::Ret(_) | VarContext::Break(..) | VarContext::Continue(_) => return Ok(v),
                 VarContext::Norm(n) => n.clone(),
             }
         }
@@ -38,14 +45,43 @@ macro_rules! vctx {
 
 type Result = std::result::Result<VarContext<Value>, Error>;
 
+///
+/// A place that an assignment stores into: a variable, and the steps from it to the
+/// part of it being assigned
+///
+#[derive(Default)]
+struct Place {
+    root: String,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    /// An index, which has already been evaluated
+    Index(Value),
+    Field(String),
+}
+
 pub struct SlowInterpreter {
     globals: Context,
+    regexes: RegexCache,
+    structs: Structs,
+    /// Warnings raised while running, until they are taken
+    warnings: RefCell<Vec<Warning>>,
+}
+
+impl Default for SlowInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SlowInterpreter {
     pub fn new() -> Self {
         Self {
             globals: Context::new(),
+            regexes: RegexCache::new(),
+            structs: Structs::new(),
+            warnings: RefCell::new(Vec::new()),
         }
     }
 
@@ -55,18 +91,42 @@ impl SlowInterpreter {
                 self.visit_expr(expr)
             }
             StmtKind::Fn(func) => {
-                self.globals.store(func.ident.name.to_owned(), Value::Func(func.clone()));
+                // the name is bound first, so that a nested function can capture it to call itself
+                self.globals.store_top(func.ident.name.to_owned(), Value::None);
+                self.globals.store(func.ident.name.to_owned(), self.visit_function_def(func));
+                Ok(VarContext::Norm(Value::None))
+            }
+            StmtKind::Struct(def) => {
+                self.structs.declare_struct(def);
+                self.globals.store(def.ident.name.to_owned(), Value::Struct(def.clone()));
+                Ok(VarContext::Norm(Value::None))
+            }
+            StmtKind::Enum(def) => {
+                self.structs.declare_enum(def);
+                self.globals.store(def.ident.name.to_owned(), Value::Enum(def.clone()));
+                Ok(VarContext::Norm(Value::None))
+            }
+            StmtKind::Impl(imp) => {
+                self.structs.implement(imp)?;
                 Ok(VarContext::Norm(Value::None))
             }
         }
     }
 
+    ///
+    /// Errors raised without a position are given that of the innermost
+    /// expression that raised them.
+    ///
     fn visit_expr(&self, expr: &Expr) -> Result {
+        self.visit_expr_kind(expr).map_err(|err| err.or_position(expr.position))
+    }
+
+    fn visit_expr_kind(&self, expr: &Expr) -> Result {
         use ExprKind::*;
         match &expr.kind {
             Let(ident, rhs) => {
                 let value = if let Some(expr) = rhs {
-                    vctx!(self.visit_expr(&*expr)?)
+                    vctx!(self.visit_expr(expr)?)
                 } else {
                     Value::None
                 };
@@ -75,59 +135,204 @@ impl SlowInterpreter {
             }
             Assign(lhs, rhs) => {
                 match &lhs.kind {
-                    Ident(ident) => {
-                        if self.globals.exists(&ident.name) {
-                            let value = self.visit_expr(rhs)?;
-                            self.globals.store(ident.name.clone(), vctx!(value));
-                            Ok(VarContext::Norm(Value::None))
-                        } else {
-                            Err(Error::new(UndefinedVar(ident.name.clone())))
-                        }
+                    Ident(ident) if !self.globals.exists(&ident.name) => {
+                        Err(Error::new(UndefinedVar(ident.name.clone())))
+                    }
+                    Ident(_) | Index(_, _) | Field(_, _) => {
+                        // assignment evaluates to the assigned value, so that it can be chained
+                        let value = vctx!(self.visit_expr(rhs)?);
+                        let mut place = Place::default();
+                        vctx!(self.visit_place(lhs, &mut place)?);
+                        self.store(&place, value.clone())?;
+                        Ok(VarContext::Norm(value))
+                    }
+                    _ => {
+                        Err(Error::new(InvalidAssignment))
+                    }
+                }
+            }
+            AssignOp(lhs, op, rhs) => {
+                match &lhs.kind {
+                    Ident(_) | Index(_, _) | Field(_, _) => {
+                        // the place is evaluated once, for both loading its value and storing the result
+                        let mut place = Place::default();
+                        vctx!(self.visit_place(lhs, &mut place)?);
+                        let current = self.load(&place, place.steps.len())?;
+                        let rhs = vctx!(self.visit_expr(rhs)?);
+                        let value = current.try_binop(*op, &rhs)?;
+                        self.store(&place, value.clone())?;
+                        Ok(VarContext::Norm(value))
                     }
                     _ => {
                         Err(Error::new(InvalidAssignment))
                     }
                 }
             }
+            BinOp(lhs, op @ (ast::BinOp::And | ast::BinOp::Or), rhs) => {
+                // short-circuit, evaluating to the operand that decided the result
+                let lhs = vctx!(self.visit_expr(lhs)?);
+                if lhs.into_bool() == (*op == ast::BinOp::Or) {
+                    return Ok(VarContext::Norm(lhs));
+                }
+                self.visit_expr(rhs)
+            }
             BinOp(lhs, op, rhs) => {
-                self.visit_op(vctx!(self.visit_expr(&*lhs)?), *op, vctx!(self.visit_expr(&*rhs)?))
+                self.visit_op(vctx!(self.visit_expr(lhs)?), *op, vctx!(self.visit_expr(rhs)?))
             }
-            Call(name, args) => {
-                let name = match &name.kind {
-                    Ident(ident) => {
-                        &ident.name
-                    }
-                    _ => unimplemented!("meta-programmed function names??")
-                };
-                self.visit_call(name, args)
+            Unary(op, operand) => {
+                Ok(VarContext::Norm(vctx!(self.visit_expr(operand)?).try_unop(*op)?))
+            }
+            Call(callee, args, named) => {
+                let callee = vctx!(self.visit_expr(callee)?);
+                let mut evaled_args = Vec::new();
+                for arg in args {
+                    evaled_args.push(vctx!(self.visit_expr(arg)?));
+                }
+                let mut evaled_named = Vec::new();
+                for (name, arg) in named {
+                    evaled_named.push((name.name.clone(), vctx!(self.visit_expr(arg)?)));
+                }
+                Ok(VarContext::Norm(self.call_value(&callee, evaled_args, evaled_named)?))
+            }
+            Lambda(func) => {
+                Ok(VarContext::Norm(self.visit_function_def(func)))
             }
             Literal(lit) => {
                 Ok(VarContext::Norm(lit.clone()))
             }
+            Interpolate(fragments) => {
+                let mut result = String::new();
+                for fragment in fragments {
+                    match fragment {
+                        Fragment::Text(text) => result.push_str(text),
+                        Fragment::Expr(expr) => result.push_str(&vctx!(self.visit_expr(expr)?).to_string()),
+                    }
+                }
+                Ok(VarContext::Norm(Value::String(result)))
+            }
             Ident(ident) => {
-                let value = self.globals.lookup(&ident.name).ok_or(Error::new(UndefinedVar(ident.name.clone())))?;
+                let value = match self.globals.lookup(&ident.name) {
+                    Some(value) => value,
+                    // builtins can be shadowed by variables of the same name
+                    None if builtin::exists(&ident.name) => Value::Builtin(ident.name.clone()),
+                    None => return Err(Error::new(UndefinedVar(ident.name.clone()))),
+                };
                 Ok(VarContext::Norm(value))
             }
-            While(cond, block) => {
-                self.visit_while(cond, block)
+            List(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(vctx!(self.visit_expr(item)?));
+                }
+                Ok(VarContext::Norm(Value::List(values)))
+            }
+            Map(entries) => {
+                let mut values = Vec::new();
+                for (key, value) in entries {
+                    let key = match vctx!(self.visit_expr(key)?) {
+                        Value::String(key) => key,
+                        _ => return Err(Error::new(InvalidType)),
+                    };
+                    values.push((key, vctx!(self.visit_expr(value)?)));
+                }
+                Ok(VarContext::Norm(Value::Map(values.into_iter().collect())))
+            }
+            Index(target, index) => {
+                let target = vctx!(self.visit_expr(target)?);
+                let index = vctx!(self.visit_expr(index)?);
+                Ok(VarContext::Norm(target.try_index(&index)?))
+            }
+            Field(target, field) => {
+                let target = vctx!(self.visit_expr(target)?);
+                Ok(VarContext::Norm(self.structs.field(target, &field.name)?))
+            }
+            While(label, cond, block) => {
+                self.visit_while(label, cond, block)
+            }
+            For(label, first, second, iterable, block) => {
+                self.visit_for(label, first, second, iterable, block)
             }
             If(cond, block, maybe_else) => {
                 self.visit_if(cond, block, maybe_else)
             }
+            Match(subject, arms) => self.visit_arms(subject, arms, expr.position),
+            Throw(value) => {
+                let value = vctx!(self.visit_expr(value)?);
+                Err(Error::thrown(value, expr.position))
+            }
+            Try(block, ident, catch) => self.visit_try(block, ident, catch),
+            Propagate(value) => match vctx!(self.visit_expr(value)?) {
+                err @ Value::Error(_) => Ok(VarContext::Ret(err)),
+                value => Ok(VarContext::Norm(value)),
+            },
             Block(block) => self.visit_block(block),
             Ret(expr) => {
                 let ret = VarContext::Ret(vctx!(self.visit_expr(expr)?));
                 Ok(ret)
             }
-            // TODO: add labels to break statements
-            Break(_) => {
-                Ok(VarContext::Break)
+            Break(label, value) => {
+                let value = match value {
+                    Some(value) => vctx!(self.visit_expr(value)?),
+                    None => Value::None,
+                };
+                Ok(VarContext::Break(label.as_ref().map(|label| label.name.clone()), value))
+            }
+            Continue(label) => {
+                Ok(VarContext::Continue(label.as_ref().map(|label| label.name.clone())))
+            }
+        }
+    }
+
+    ///
+    /// Evaluates the place described by target, which is either an existing variable
+    /// or an index or field of one. Indexes are evaluated here, so that the place can
+    /// be both loaded and stored into without evaluating them again.
+    ///
+    fn visit_place(&self, target: &Expr, place: &mut Place) -> Result {
+        match &target.kind {
+            ExprKind::Ident(ident) => place.root = ident.name.clone(),
+            ExprKind::Index(container, index) => {
+                vctx!(self.visit_place(container, place)?);
+                place.steps.push(Step::Index(vctx!(self.visit_expr(index)?)));
+            }
+            ExprKind::Field(container, field) => {
+                vctx!(self.visit_place(container, place)?);
+                place.steps.push(Step::Field(field.name.clone()));
             }
-            // TODO: add labels to continue statements
-            Continue(_) => {
-                Ok(VarContext::Continue)
+            _ => return Err(Error::new(InvalidAssignment))
+        }
+        Ok(VarContext::Norm(Value::None))
+    }
+
+    ///
+    /// Loads the value found by following the first depth steps of the place's path.
+    ///
+    fn load(&self, place: &Place, depth: usize) -> std::result::Result<Value, Error> {
+        let mut value = self.globals.lookup(&place.root).ok_or_else(|| Error::new(UndefinedVar(place.root.clone())))?;
+        for step in &place.steps[..depth] {
+            value = match step {
+                Step::Index(index) => value.try_index(index)?,
+                Step::Field(field) => self.structs.field(value, field)?,
+            };
+        }
+        Ok(value)
+    }
+
+    ///
+    /// Stores value into the place. Indexing stores into a copy of the container,
+    /// which is then stored back into its own place in turn.
+    ///
+    fn store(&self, place: &Place, mut value: Value) -> std::result::Result<(), Error> {
+        for (depth, step) in place.steps.iter().enumerate().rev() {
+            let mut updated = self.load(place, depth)?;
+            match step {
+                Step::Index(index) => updated.try_set_index(index, value)?,
+                Step::Field(field) => self.structs.set_field(&mut updated, field, value, &self.regexes)?,
             }
+            value = updated;
         }
+        self.globals.store(place.root.clone(), value);
+        Ok(())
     }
 
     fn visit_if(&self, cond: &Expr, block: &Block, maybe_else: &Option<Box<Expr>>) -> Result {
@@ -136,24 +341,105 @@ impl SlowInterpreter {
             result = vctx!(self.visit_block(block)?);
         } else {
             if let Some(expr) = maybe_else {
-                result = vctx!(self.visit_expr(&*expr)?);
+                result = vctx!(self.visit_expr(expr)?);
             }
         }
         Ok(VarContext::Norm(result))
     }
 
-    fn visit_while(&self, cond: &Expr, block: &Block) -> Result {
+    ///
+    /// A while loop evaluates to the value it was broken out of with, or none
+    /// if its condition became false.
+    ///
+    fn visit_while(&self, label: &Option<Ident>, cond: &Expr, block: &Block) -> Result {
         while vctx!(self.visit_expr(cond)?).into_bool() {
-            let n = self.visit_block(block)?;
+            match self.visit_block(block)? {
+                VarContext::Break(target, value) if is_target(label, &target) => return Ok(VarContext::Norm(value)),
+                VarContext::Continue(target) if is_target(label, &target) => continue,
+                VarContext::Norm(_) => {}
+                // leaving an outer loop or function
+                n => return Ok(n),
+            }
+        }
+        Ok(VarContext::Norm(Value::None))
+    }
+
+    fn visit_for(&self, label: &Option<Ident>, first: &Ident, second: &Option<Ident>, iterable: &Expr, block: &Block) -> Result {
+        let mut iter = ValueIter::new(vctx!(self.visit_expr(iterable)?))?;
+        loop {
+            let bindings = match second {
+                Some(second) => match iter.next_pair() {
+                    Some((key, value)) => vec![(first, key), (second, value)],
+                    None => break,
+                },
+                None => match iter.next_single() {
+                    Some(value) => vec![(first, value)],
+                    None => break,
+                },
+            };
+            // the loop variables live in their own scope, around the block's
+            let n = self.globals.scoped(|| {
+                for (ident, value) in &bindings {
+                    self.globals.store_top(ident.name.clone(), value.clone());
+                }
+                self.visit_block(block)
+            })?;
             match n {
-                VarContext::Break => break,
-                VarContext::Continue => continue,
-                _ => {}
+                VarContext::Break(target, value) if is_target(label, &target) => return Ok(VarContext::Norm(value)),
+                VarContext::Continue(target) if is_target(label, &target) => continue,
+                VarContext::Norm(_) => {}
+                n => return Ok(n),
             }
         }
         Ok(VarContext::Norm(Value::None))
     }
 
+    ///
+    /// Evaluates the first arm whose pattern matches the subject, and whose guard (if any)
+    /// holds. If none do, the match evaluates to none, with a warning for values of an enum.
+    ///
+    fn visit_arms(&self, subject: &Expr, arms: &[Arm], position: Position) -> Result {
+        let subject = vctx!(self.visit_expr(subject)?);
+        for arm in arms {
+            let matched = match pattern::matches(&arm.pattern, &subject) {
+                Some(matched) => matched,
+                None => continue,
+            };
+            let mut bindings = matched.bindings;
+            bindings.extend(matched.captures);
+            // the names bound by the pattern live in their own scope, around the guard and body
+            let result = self.globals.scoped_with(bindings, || {
+                if let Some(guard) = &arm.guard {
+                    match self.visit_expr(guard)? {
+                        VarContext::Norm(value) if !value.into_bool() => return Ok(None),
+                        VarContext::Norm(_) => {}
+                        vc => return Ok(Some(vc)),
+                    }
+                }
+                self.visit_expr(&arm.body).map(Some)
+            })?;
+            if let Some(result) = result {
+                return Ok(result);
+            }
+        }
+        pattern::warn_unmatched(&subject, position, &mut self.warnings.borrow_mut());
+        Ok(VarContext::Norm(Value::None))
+    }
+
+    ///
+    /// Any error raised by the try block, whether thrown by the script or not,
+    /// is caught and bound to the catch block's name as an error value.
+    ///
+    fn visit_try(&self, block: &Block, ident: &Option<Ident>, catch: &Block) -> Result {
+        match self.visit_block(block) {
+            Err(err) => {
+                let bindings = ident.iter().map(|ident| (ident.name.clone(), err.clone().into_value())).collect();
+                self.globals.scoped_with(bindings, || self.visit_block(catch))
+            }
+            result => result,
+        }
+    }
+
     fn visit_block(&self, block: &Block) -> Result {
         self.globals.scoped(|| {
             let mut result = Value::None;
@@ -168,85 +454,137 @@ impl SlowInterpreter {
         })
     }
 
-    fn visit_function(&self, func: &Function, args: &Vec<Value>) -> Result {
-        self.globals.scoped(|| {
-            let mut result = Value::None;
-            for (i, arg) in args.iter().enumerate() {
-                self.globals.store_top(
-                    func.args.get(i).unwrap().name.name.to_owned(),
-                    arg.clone(),
-                );
-            }
-            for stmt in &func.block.stmts {
-                result = match self.visit_stmt(stmt)? {
-                    VarContext::Norm(v) => v,
-                    VarContext::Break => {
-                        // if this is handled at this level, then it's definitely wrong
-                        return Err(Error::new(BreakInWrongContext));
-                    }
-                    VarContext::Continue => {
-                        return Err(Error::new(ContinueInWrongContext));
-                    }
-                    VarContext::Ret(v) => {
-                        result = v;
-                        break;
-                    }
-                }
-            }
-            Ok(VarContext::Norm(result))
-        })
+    ///
+    /// Creates the value of a function definition. A function defined within another
+    /// function (or block) closes over the local variables that it refers to, sharing
+    /// their bindings with the scope they were defined in. Globals are not captured, and
+    /// are looked up when the function is called as usual, as are regex capture groups,
+    /// which belong to the call that matched them.
+    ///
+    fn visit_function_def(&self, func: &Function) -> Value {
+        let captures: Vec<(String, Capture)> = closure::free_names(func).into_iter()
+            .filter(|name| !name.starts_with('$'))
+            .filter_map(|name| self.globals.lookup_local(&name).map(|binding| (name, binding)))
+            .collect();
+        if captures.is_empty() {
+            Value::Func(func.clone())
+        } else {
+            Value::Closure(Closure { func: func.clone(), captures })
+        }
     }
 
-    fn visit_call(&self, name: &String, args: &Vec<Box<Expr>>) -> Result {
-        if let Some(func) = self.globals.lookup(name) {
-            if let Value::Func(func) = func {
-                if func.args.len() != args.len() {
-                    return Err(Error::new(UnexpectedArgs(func.args.len(), args.len())));
-                }
+    ///
+    /// Runs a function with its bound arguments, and any bindings captured by its closure.
+    /// Only these and the globals are in scope, not the caller's locals.
+    ///
+    fn visit_function(&self, func: &Function, captures: &[(String, Capture)], args: Vec<Option<Value>>) -> std::result::Result<Value, Error> {
+        self.globals.scoped_call(captures, || self.visit_function_body(func, &args))
+    }
 
-                let mut evaled_args = Vec::new();
-                for arg in args {
-                    evaled_args.push(vctx!(self.visit_expr(&arg)?));
+    ///
+    /// Binds a function's arguments and runs its body. Arguments left as None take
+    /// their default, which is evaluated after the preceding arguments are bound.
+    ///
+    fn visit_function_body(&self, func: &Function, args: &[Option<Value>]) -> std::result::Result<Value, Error> {
+        let mut result = Value::None;
+        for (spec, arg) in func.args.iter().zip(args) {
+            let value = match (arg, &spec.default) {
+                (Some(value), _) => value.clone(),
+                (None, Some(default)) => {
+                    let value = match self.visit_expr(default)? {
+                        VarContext::Norm(v) => v,
+                        VarContext::Ret(v) => return Ok(v),
+                        VarContext::Break(..) => return Err(Error::new(BreakInWrongContext)),
+                        VarContext::Continue(_) => return Err(Error::new(ContinueInWrongContext)),
+                    };
+                    types::check_arg(spec, value, &self.regexes, &self.structs)?
                 }
+                (None, None) => unreachable!("arguments without defaults are always bound"),
+            };
+            self.globals.store_top(spec.name.name.to_owned(), value);
+        }
+        for stmt in &func.block.stmts {
+            result = match self.visit_stmt(stmt)? {
+                VarContext::Norm(v) => v,
+                VarContext::Break(..) => {
+                    // if this is handled at this level, then it's definitely wrong
+                    return Err(Error::new(BreakInWrongContext));
+                }
+                VarContext::Continue(_) => {
+                    return Err(Error::new(ContinueInWrongContext));
+                }
+                VarContext::Ret(v) => {
+                    result = v;
+                    break;
+                }
+            }
+        }
+        Ok(result)
+    }
 
-                self.visit_function(&func, &evaled_args)
-            } else {
-                Err(Error::new(NotCallable))
+    ///
+    /// Calls a function, closure, method or builtin with already evaluated arguments,
+    /// which are checked against any type annotations, as is the result. Builtins only
+    /// take positional arguments. Calling a struct (or enum variant) constructs an instance of it.
+    ///
+    fn call_value(&self, callee: &Value, mut args: Vec<Value>, named: Vec<(String, Value)>) -> std::result::Result<Value, Error> {
+        let (func, captures) = match callee {
+            Value::Func(func) => (func, &[][..]),
+            Value::Closure(closure) => (&closure.func, closure.captures.as_slice()),
+            Value::Method(method) => {
+                args.insert(0, method.receiver.clone());
+                (&method.func, &[][..])
             }
-        } else if builtin::exists(name) {
-            let mut evaled_args = Vec::new();
-            for arg in args {
-                evaled_args.push(vctx!(self.visit_expr(&arg)?));
+            Value::Struct(_) | Value::Variant(_, _) => return self.structs.construct(callee, args, named, &self.regexes),
+            Value::Builtin(name) => {
+                if let Some((name, _)) = named.into_iter().next() {
+                    return Err(Error::new(UnknownArg(name)));
+                }
+                return builtin::run(name, args, &self.regexes, &mut |func, args| self.call_value(func, args, Vec::new()));
             }
-            Ok(VarContext::Norm(builtin::run(&name, evaled_args)?))
-        } else {
-            Err(Error::new(UndefinedVar(name.clone())))
-        }
+            _ => return Err(Error::new(NotCallable)),
+        };
+        let args = args::bind(func, args, named)?;
+        let args = types::check_args(func, args, &self.regexes, &self.structs)?;
+        let result = self.visit_function(func, captures, args)?;
+        types::check_return(func, result, &self.regexes, &self.structs)
     }
 
     fn visit_op(&self, lhs: Value, op: BinOp, rhs: Value) -> Result {
-        use BinOp::*;
-        let result = match op {
-            Mod => lhs.try_mod(&rhs)?,
-            Add => lhs.try_add(&rhs)?,
-            Sub => lhs.try_sub(&rhs)?,
-            Mul => lhs.try_mul(&rhs)?,
-            Div => lhs.try_div(&rhs)?,
-            EqEq => lhs.is_equal(&rhs).into(),
-            NotEq => Value::Bool(!lhs.is_equal(&rhs)),
-            LtEq => (lhs.is_lt(&rhs) || lhs.is_equal(&rhs)).into(),
-            GtEq => (lhs.is_gt(&rhs) || lhs.is_equal(&rhs)).into(),
-            Lt => lhs.is_lt(&rhs).into(),
-            Gt => lhs.is_gt(&rhs).into(),
-            And => lhs.and(&rhs).into(),
-            Or => lhs.or(&rhs).into(),
-            Xor => lhs.try_xor(&rhs)?,
-            BinAnd => lhs.try_bin_and(&rhs)?,
-            BinOr => lhs.try_bin_or(&rhs)?,
-            TildeEq => lhs.try_regex_match(&rhs)?,
-            Eq => panic!("invalid assignment in binop")
-        };
-        Ok(VarContext::Norm(result))
+        if op == BinOp::TildeEq {
+            return self.visit_match(lhs, rhs);
+        }
+        Ok(VarContext::Norm(lhs.try_binop(op, &rhs)?))
+    }
+
+    ///
+    /// Matches a string against a regex (or a string pattern) and on success,
+    /// binds the capture groups into the current scope.
+    ///
+    fn visit_match(&self, lhs: Value, rhs: Value) -> Result {
+        let rhs = self.regexes.coerce(rhs)?;
+        if let (Value::String(text), Value::Regex(re)) = (&lhs, &rhs) {
+            return match regex_cache::captures(re, text) {
+                Some(groups) => {
+                    self.globals.store_captures(groups);
+                    Ok(VarContext::Norm(Value::Bool(true)))
+                }
+                None => Ok(VarContext::Norm(Value::Bool(false))),
+            };
+        }
+        Ok(VarContext::Norm(lhs.try_binop(BinOp::TildeEq, &rhs)?))
+    }
+}
+
+///
+/// Whether a break or continue with the given target label applies to a loop
+/// with the given label. Those without a label apply to the innermost loop.
+///
+fn is_target(label: &Option<Ident>, target: &Option<String>) -> bool {
+    match (label, target) {
+        (_, None) => true,
+        (Some(label), Some(target)) => &label.name == target,
+        (None, Some(_)) => false,
     }
 }
 
@@ -263,8 +601,8 @@ impl Interpreter<Value, Error> for SlowInterpreter {
             let stmt = Stmt::from_tokens(&tokens)?;
             result = match self.visit_stmt(&stmt)? {
                 VarContext::Norm(n) => n,
-                VarContext::Break => return Err(Error::new(BreakInWrongContext)),
-                VarContext::Continue => return Err(Error::new(ContinueInWrongContext)),
+                VarContext::Break(..) => return Err(Error::new(BreakInWrongContext)),
+                VarContext::Continue(_) => return Err(Error::new(ContinueInWrongContext)),
                 VarContext::Ret(n) => {
                     result = n;
                     break;
@@ -273,4 +611,8 @@ impl Interpreter<Value, Error> for SlowInterpreter {
         }
         Ok(result)
     }
+
+    fn take_warnings(&mut self) -> Vec<Warning> {
+        self.warnings.take()
+    }
 }
@@ -0,0 +1,148 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use ast::{ArgSpec, Enum, Function, Impl, Instance, Method, Struct, Value};
+
+use crate::error::Error;
+use crate::error::ErrorKind::{NotCallable, UnknownField, UnknownType};
+use crate::regex_cache::RegexCache;
+use crate::types;
+use crate::value::Operations;
+
+/// A struct, or an enum and one of its variants
+type TypeKey = (String, Option<String>);
+
+///
+/// The structs and enums that have been declared, along with the methods
+/// implemented for each. Methods are shared by every instance of a struct,
+/// so they are kept here rather than in the instances themselves.
+///
+#[derive(Default)]
+pub struct Structs {
+    methods: RefCell<HashMap<String, HashMap<String, Function>>>,
+    /// The fields of each struct, and of each variant of each enum, whose
+    /// annotations are checked when they are assigned to
+    fields: RefCell<HashMap<TypeKey, Vec<ArgSpec>>>,
+}
+
+impl Structs {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    ///
+    /// Declares a struct or enum. Redeclaring one keeps any methods it already has.
+    ///
+    fn declare(&self, name: &str) {
+        self.methods.borrow_mut().entry(name.to_owned()).or_default();
+    }
+
+    pub fn declare_struct(&self, def: &Struct) {
+        self.declare(&def.ident.name);
+        self.fields.borrow_mut().insert((def.ident.name.clone(), None), def.fields.clone());
+    }
+
+    pub fn declare_enum(&self, def: &Enum) {
+        self.declare(&def.ident.name);
+        let mut fields = self.fields.borrow_mut();
+        for variant in &def.variants {
+            fields.insert((def.ident.name.clone(), Some(variant.ident.name.clone())), variant.fields.clone());
+        }
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.methods.borrow().contains_key(name)
+    }
+
+    ///
+    /// Adds the methods of an impl block to its struct (or enum), which must already be declared.
+    /// Each method is named after the struct i.e. `Point.len`, so that it does not
+    /// clash with functions (or the methods of other structs) of the same name.
+    ///
+    pub fn implement(&self, imp: &Impl) -> Result<(), Error> {
+        let mut methods = self.methods.borrow_mut();
+        let methods = methods.get_mut(&imp.ident.name)
+            .ok_or_else(|| Error::new_at(UnknownType(imp.ident.name.clone()), imp.ident.position))?;
+        for method in &imp.methods {
+            let mut func = method.clone();
+            func.ident.name = format!("{}.{}", imp.ident.name, method.ident.name);
+            methods.insert(method.ident.name.clone(), func);
+        }
+        Ok(())
+    }
+
+    fn method(&self, typ: &str, name: &str) -> Option<Function> {
+        self.methods.borrow().get(typ).and_then(|methods| methods.get(name)).cloned()
+    }
+
+    ///
+    /// Gets a named field of a value. Anything other than a field of an instance is
+    /// looked up as a method of its struct, which is bound to the instance if it takes
+    /// `self`. Methods can also be accessed through the struct itself i.e. `Point.new`.
+    ///
+    /// The variants of an enum are accessed through it too. A variant without fields is
+    /// a value of the enum, whereas one with fields is called to construct a value.
+    ///
+    pub fn field(&self, target: Value, name: &str) -> Result<Value, Error> {
+        match &target {
+            Value::Instance(instance) if !instance.fields.contains_key(name) => {
+                match self.method(&instance.name, name) {
+                    Some(func) if func.takes_self() => Ok(Value::Method(Method { receiver: target, func }.into())),
+                    Some(func) => Ok(Value::Func(func)),
+                    None => Err(Error::new(UnknownField(name.to_owned()))),
+                }
+            }
+            Value::Struct(def) => {
+                self.method(&def.ident.name, name).map(Value::Func).ok_or_else(|| Error::new(UnknownField(name.to_owned())))
+            }
+            Value::Enum(def) => match def.variants.iter().find(|variant| variant.ident.name == name) {
+                Some(variant) if variant.fields.is_empty() => Ok(Value::Instance(Instance {
+                    name: def.ident.name.clone(),
+                    variant: Some(name.to_owned()),
+                    fields: Default::default(),
+                })),
+                Some(variant) => Ok(Value::Variant(def.ident.name.clone(), variant.clone())),
+                None => self.method(&def.ident.name, name).map(Value::Func).ok_or_else(|| Error::new(UnknownField(name.to_owned()))),
+            },
+            _ => target.try_field(name),
+        }
+    }
+
+    ///
+    /// Sets a named field of a value. The fields of an instance are checked against
+    /// their annotations, as they are when it is constructed.
+    ///
+    pub fn set_field(&self, target: &mut Value, name: &str, value: Value, regexes: &RegexCache) -> Result<(), Error> {
+        let value = match &*target {
+            Value::Instance(instance) => {
+                let fields = self.fields.borrow();
+                let key = (instance.name.clone(), instance.variant.clone());
+                match fields.get(&key).and_then(|specs| specs.iter().find(|spec| spec.name.name == name)) {
+                    Some(spec) => types::check_arg(spec, value, regexes, self)?,
+                    None => value,
+                }
+            }
+            _ => value,
+        };
+        target.try_set_field(name, value)
+    }
+
+    ///
+    /// Constructs an instance of a struct (or a value of an enum variant) from a call to it,
+    /// binding the arguments of the call to its fields and checking them against any annotations.
+    ///
+    pub fn construct(&self, callee: &Value, args: Vec<Value>, named: Vec<(String, Value)>, regexes: &RegexCache) -> Result<Value, Error> {
+        let (name, variant, specs) = match callee {
+            Value::Struct(def) => (&def.ident.name, None, &def.fields),
+            Value::Variant(name, variant) => (name, Some(&variant.ident.name), &variant.fields),
+            _ => return Err(Error::new(NotCallable)),
+        };
+        let bindings = ArgSpec::bind(specs, args, named)?;
+        let mut fields = Vec::new();
+        for (spec, value) in specs.iter().zip(bindings.args) {
+            let value = value.expect("fields without defaults are always bound");
+            fields.push((spec.name.name.clone(), types::check_arg(spec, value, regexes, self)?));
+        }
+        Ok(Value::Instance(Instance { name: name.clone(), variant: variant.cloned(), fields: fields.into_iter().collect() }))
+    }
+}
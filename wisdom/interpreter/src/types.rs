@@ -0,0 +1,121 @@
+use ast::{ArgSpec, Function, Typ, Value};
+
+use crate::error::Error;
+use crate::error::ErrorKind::{TypeMismatch, UnknownType};
+use crate::regex_cache::RegexCache;
+use crate::structs::Structs;
+
+///
+/// The names that may be used in type annotations, other than `any` and the names of structs
+///
+const TYPE_NAMES: &[&str] = &["int", "float", "bool", "str", "regex", "list", "map", "range", "error", "fn", "none"];
+
+///
+/// The name of a value's type, as used in type annotations. Instances are
+/// named after their struct or enum.
+///
+pub fn type_name(value: &Value) -> &str {
+    match value {
+        Value::Int(_) => "int",
+        Value::Float(_) => "float",
+        Value::Bool(_) => "bool",
+        Value::String(_) => "str",
+        Value::Regex(_) => "regex",
+        Value::List(_) => "list",
+        Value::Map(_) => "map",
+        Value::Range(_, _) => "range",
+        Value::Error(_) => "error",
+        Value::Func(_) | Value::Closure(_) | Value::Builtin(_) | Value::Struct(_) | Value::Variant(_, _) | Value::Method(_) => "fn",
+        Value::Enum(_) => "enum",
+        Value::Instance(instance) => &instance.name,
+        Value::Named(_) => "named",
+        Value::None => "none",
+    }
+}
+
+///
+/// Checks a value against its type annotation, returning the value to use in its place.
+/// Strings given where a regex is expected are compiled, otherwise the value is returned
+/// as is. The error is positioned at the annotation.
+///
+pub fn check(typ: &Typ, value: Value, regexes: &RegexCache, structs: &Structs) -> Result<Value, Error> {
+    let expected = typ.ident.name.as_str();
+    let actual = type_name(&value);
+    if expected == "any" || expected == actual {
+        return Ok(value);
+    }
+    let actual = actual.to_owned();
+    if !TYPE_NAMES.contains(&expected) && !structs.exists(expected) {
+        return Err(Error::new_at(UnknownType(expected.to_owned()), typ.ident.position));
+    }
+    match (expected, value) {
+        ("regex", value @ Value::String(_)) => regexes.coerce(value).map_err(|e| e.or_position(typ.ident.position)),
+        _ => Err(Error::new_at(TypeMismatch(expected.to_owned(), actual), typ.ident.position)),
+    }
+}
+
+///
+/// Checks an argument against its annotation, if it has one. The annotation
+/// of a variadic argument applies to each of the values it collected.
+///
+pub fn check_arg(spec: &ArgSpec, value: Value, regexes: &RegexCache, structs: &Structs) -> Result<Value, Error> {
+    match (&spec.typ, value) {
+        (None, value) => Ok(value),
+        (Some(typ), Value::List(items)) if spec.variadic => {
+            items.into_iter().map(|item| check(typ, item, regexes, structs)).collect::<Result<_, _>>().map(Value::List)
+        }
+        (Some(typ), value) => check(typ, value, regexes, structs),
+    }
+}
+
+///
+/// Checks the arguments of a call against any annotations on func's arguments.
+/// Arguments that were not given are left for their defaults.
+///
+pub fn check_args(func: &Function, args: Vec<Option<Value>>, regexes: &RegexCache, structs: &Structs) -> Result<Vec<Option<Value>>, Error> {
+    args.into_iter().zip(&func.args).map(|(value, spec)| {
+        value.map(|value| check_arg(spec, value, regexes, structs)).transpose()
+    }).collect()
+}
+
+///
+/// Checks the result of a call against func's return type, if it has one.
+/// Error values may be returned from any function, so that they can be propagated with `?`.
+///
+pub fn check_return(func: &Function, value: Value, regexes: &RegexCache, structs: &Structs) -> Result<Value, Error> {
+    match (&func.ret_typ, value) {
+        (Some(_), value @ Value::Error(_)) => Ok(value),
+        (Some(typ), value) => check(typ, value, regexes, structs),
+        (None, value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ast::{Ident, Typ, Value};
+    use common::Position;
+
+    use crate::error::Error;
+    use crate::error::ErrorKind::{TypeMismatch, UnknownType};
+    use crate::regex_cache::RegexCache;
+    use crate::structs::Structs;
+    use crate::types::check;
+
+    fn typ(name: &str) -> Typ {
+        Typ { ident: Ident { name: name.to_owned(), position: Position { line: 2, column: 3 } } }
+    }
+
+    #[test]
+    fn test_check() {
+        let regexes = RegexCache::new();
+        let structs = Structs::new();
+        assert_eq!(check(&typ("int"), Value::Int(1), &regexes, &structs), Ok(Value::Int(1)));
+        assert_eq!(check(&typ("any"), Value::None, &regexes, &structs), Ok(Value::None));
+        assert!(matches!(check(&typ("regex"), Value::String("a+".to_owned()), &regexes, &structs), Ok(Value::Regex(_))));
+
+        let mismatch = Error::new_at(TypeMismatch("int".to_owned(), "str".to_owned()), Position { line: 2, column: 3 });
+        assert_eq!(check(&typ("int"), Value::String("1".to_owned()), &regexes, &structs), Err(mismatch));
+        let unknown = Error::new_at(UnknownType("integer".to_owned()), Position { line: 2, column: 3 });
+        assert_eq!(check(&typ("integer"), Value::Int(1), &regexes, &structs), Err(unknown));
+    }
+}
@@ -1,9 +1,15 @@
-use ast::Value;
+use std::convert::TryFrom;
+
+use ast::{BinOp, UnaryOp, Value};
 
 use crate::error::Error;
-use crate::error::ErrorKind::{InvalidType, InvalidRegex};
+use crate::error::ErrorKind::{IndexOutOfBounds, InvalidAssignment, InvalidType, KeyNotFound, Overflow, UnknownField};
 
 pub trait Operations {
+    fn try_binop(&self, op: BinOp, rhs: &Value) -> Result<Value, Error>;
+    fn try_unop(&self, op: UnaryOp) -> Result<Value, Error>;
+    fn try_neg(&self) -> Result<Value, Error>;
+    fn try_bit_not(&self) -> Result<Value, Error>;
     fn try_mod(&self, rhs: &Value) -> Result<Value, Error>;
     fn try_add(&self, rhs: &Value) -> Result<Value, Error>;
     fn try_sub(&self, rhs: &Value) -> Result<Value, Error>;
@@ -12,16 +18,84 @@ pub trait Operations {
     fn is_equal(&self, rhs: &Value) -> bool;
     fn is_lt(&self, rhs: &Value) -> bool;
     fn is_gt(&self, rhs: &Value) -> bool;
-    fn and(&self, rhs: &Value) -> bool;
-    fn or(&self, rhs: &Value) -> bool;
+    fn and(&self, rhs: &Value) -> Value;
+    fn or(&self, rhs: &Value) -> Value;
     fn try_xor(&self, rhs: &Value) -> Result<Value, Error>;
     fn try_bin_and(&self, rhs: &Value) -> Result<Value, Error>;
     fn try_bin_or(&self, rhs: &Value) -> Result<Value, Error>;
+    fn try_shl(&self, rhs: &Value) -> Result<Value, Error>;
+    fn try_shr(&self, rhs: &Value) -> Result<Value, Error>;
+    fn try_pow(&self, rhs: &Value) -> Result<Value, Error>;
     fn try_regex_match(&self, rhs: &Value) -> Result<Value, Error>;
+    fn try_range(&self, rhs: &Value, inclusive: bool) -> Result<Value, Error>;
+    fn try_index(&self, index: &Value) -> Result<Value, Error>;
+    fn try_set_index(&mut self, index: &Value, value: Value) -> Result<(), Error>;
+    fn try_field(&self, name: &str) -> Result<Value, Error>;
+    fn try_set_field(&mut self, name: &str, value: Value) -> Result<(), Error>;
+    #[allow(clippy::wrong_self_convention)]
     fn into_bool(&self) -> bool;
 }
 
 impl Operations for Value {
+    ///
+    /// Applies the binary operator to this value and rhs, dispatching
+    /// to the relevant operation below.
+    ///
+    fn try_binop(&self, op: BinOp, rhs: &Value) -> Result<Value, Error> {
+        use BinOp::*;
+        Ok(match op {
+            Mod => self.try_mod(rhs)?,
+            Add => self.try_add(rhs)?,
+            Sub => self.try_sub(rhs)?,
+            Mul => self.try_mul(rhs)?,
+            Div => self.try_div(rhs)?,
+            EqEq => self.is_equal(rhs).into(),
+            NotEq => Value::Bool(!self.is_equal(rhs)),
+            LtEq => (self.is_lt(rhs) || self.is_equal(rhs)).into(),
+            GtEq => (self.is_gt(rhs) || self.is_equal(rhs)).into(),
+            Lt => self.is_lt(rhs).into(),
+            Gt => self.is_gt(rhs).into(),
+            And => self.and(rhs),
+            Or => self.or(rhs),
+            Xor => self.try_xor(rhs)?,
+            BinAnd => self.try_bin_and(rhs)?,
+            BinOr => self.try_bin_or(rhs)?,
+            ShiftLeft => self.try_shl(rhs)?,
+            ShiftRight => self.try_shr(rhs)?,
+            Pow => self.try_pow(rhs)?,
+            TildeEq => self.try_regex_match(rhs)?,
+            Range => self.try_range(rhs, false)?,
+            RangeInclusive => self.try_range(rhs, true)?,
+            Eq => return Err(Error::new(InvalidAssignment)),
+        })
+    }
+
+    ///
+    /// Applies the prefix operator to this value.
+    ///
+    fn try_unop(&self, op: UnaryOp) -> Result<Value, Error> {
+        match op {
+            UnaryOp::Neg => self.try_neg(),
+            UnaryOp::Not => Ok(Value::Bool(!self.into_bool())),
+            UnaryOp::BitNot => self.try_bit_not(),
+        }
+    }
+
+    fn try_neg(&self) -> Result<Value, Error> {
+        match self {
+            Value::Int(n) => n.checked_neg().map(Value::Int).ok_or_else(|| Error::new(Overflow)),
+            Value::Float(n) => Ok(Value::Float(-n)),
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    fn try_bit_not(&self) -> Result<Value, Error> {
+        match self {
+            Value::Int(n) => Ok(Value::Int(!n)),
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
     fn try_mod(&self, rhs: &Value) -> Result<Value, Error> {
         match self {
             Value::Int(n) => {
@@ -66,6 +140,12 @@ impl Operations for Value {
                     _ => Err(Error::new(InvalidType))
                 }
             }
+            Value::List(n) => {
+                match rhs {
+                    Value::List(m) => Ok(Value::List(n.iter().chain(m).cloned().collect())),
+                    _ => Err(Error::new(InvalidType))
+                }
+            }
             _ => Err(Error::new(InvalidType))
         }
     }
@@ -142,12 +222,19 @@ impl Operations for Value {
         *self > *rhs
     }
 
-    fn and(&self, rhs: &Value) -> bool {
-        self.into_bool() && rhs.into_bool()
+    ///
+    /// Returns the deciding operand: self if it is falsey, otherwise rhs.
+    /// The interpreters short-circuit, so that rhs is only evaluated when needed.
+    ///
+    fn and(&self, rhs: &Value) -> Value {
+        if self.into_bool() { rhs.clone() } else { self.clone() }
     }
 
-    fn or(&self, rhs: &Value) -> bool {
-        self.into_bool() || rhs.into_bool()
+    ///
+    /// Returns the deciding operand: self if it is truthy, otherwise rhs.
+    ///
+    fn or(&self, rhs: &Value) -> Value {
+        if self.into_bool() { self.clone() } else { rhs.clone() }
     }
 
     fn try_xor(&self, rhs: &Value) -> Result<Value, Error> {
@@ -186,14 +273,103 @@ impl Operations for Value {
         }
     }
 
+    ///
+    /// Shifts left, failing if the shift is negative, or if any bits
+    /// (including the sign) would be lost.
+    ///
+    fn try_shl(&self, rhs: &Value) -> Result<Value, Error> {
+        match (self, rhs) {
+            (Value::Int(n), Value::Int(m)) => {
+                let shift = shift_amount(*m)?;
+                let result = n.checked_shl(shift).ok_or_else(|| Error::new(Overflow))?;
+                if result >> shift != *n {
+                    return Err(Error::new(Overflow));
+                }
+                Ok(Value::Int(result))
+            }
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    ///
+    /// Arithmetic (sign-preserving) right shift, failing if the shift is negative
+    /// or at least the width of an integer.
+    ///
+    fn try_shr(&self, rhs: &Value) -> Result<Value, Error> {
+        match (self, rhs) {
+            (Value::Int(n), Value::Int(m)) => {
+                n.checked_shr(shift_amount(*m)?).map(Value::Int).ok_or_else(|| Error::new(Overflow))
+            }
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    ///
+    /// Raises to a power. Integers raised to non-negative integer powers stay
+    /// integers (failing on overflow), anything else results in a float.
+    ///
+    fn try_pow(&self, rhs: &Value) -> Result<Value, Error> {
+        match (self, rhs) {
+            (Value::Int(n), Value::Int(m)) if *m >= 0 => {
+                let exp = u32::try_from(*m).map_err(|_| Error::new(Overflow))?;
+                n.checked_pow(exp).map(Value::Int).ok_or_else(|| Error::new(Overflow))
+            }
+            (Value::Int(n), Value::Int(m)) => Ok(Value::Float((*n as f64).powf(*m as f64))),
+            (Value::Int(n), Value::Float(m)) => Ok(Value::Float((*n as f64).powf(*m))),
+            (Value::Float(n), Value::Int(m)) => Ok(Value::Float(n.powf(*m as f64))),
+            (Value::Float(n), Value::Float(m)) => Ok(Value::Float(n.powf(*m))),
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
     fn try_regex_match(&self, rhs: &Value) -> Result<Value, Error> {
         match self {
             Value::String(this) => {
                 match rhs {
-                    Value::Regex(s) | Value::String(s) => {
-                        let re = regex::Regex::new(&s).map_err(|e| Error::new(InvalidRegex(e)))?;
-                        Ok(re.is_match(&this).into())
-                    },
+                    // string patterns are compiled by the interpreter's RegexCache
+                    Value::Regex(re) => Ok(re.is_match(this).into()),
+                    _ => Err(Error::new(InvalidType))
+                }
+            }
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    fn try_range(&self, rhs: &Value, inclusive: bool) -> Result<Value, Error> {
+        match (self, rhs) {
+            (Value::Int(start), Value::Int(end)) if inclusive => Ok(Value::Range(*start, end.saturating_add(1))),
+            (Value::Int(start), Value::Int(end)) => Ok(Value::Range(*start, *end)),
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    fn try_index(&self, index: &Value) -> Result<Value, Error> {
+        match self {
+            Value::List(items) => Ok(items[list_offset(index, items.len())?].clone()),
+            Value::Map(entries) => {
+                match index {
+                    Value::String(key) => entries.get(key).cloned().ok_or_else(|| Error::new(KeyNotFound(key.clone()))),
+                    _ => Err(Error::new(InvalidType))
+                }
+            }
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    fn try_set_index(&mut self, index: &Value, value: Value) -> Result<(), Error> {
+        match self {
+            Value::List(items) => {
+                let offset = list_offset(index, items.len())?;
+                items[offset] = value;
+                Ok(())
+            }
+            Value::Map(entries) => {
+                match index {
+                    // new keys are added at the end, existing keys keep their place
+                    Value::String(key) => {
+                        entries.insert(key.clone(), value);
+                        Ok(())
+                    }
                     _ => Err(Error::new(InvalidType))
                 }
             }
@@ -201,16 +377,80 @@ impl Operations for Value {
         }
     }
 
+    ///
+    /// Fields of a map are its string keys, so `a.b` is equivalent to `a["b"]`
+    ///
+    fn try_field(&self, name: &str) -> Result<Value, Error> {
+        match self {
+            Value::Map(_) => self.try_index(&Value::String(name.to_owned())),
+            Value::Instance(instance) => {
+                instance.fields.get(name).cloned().ok_or_else(|| Error::new(UnknownField(name.to_owned())))
+            }
+            Value::Error(err) => match name {
+                "kind" => Ok(Value::String(err.kind.clone())),
+                "message" => Ok(Value::String(err.message.clone())),
+                "position" => Ok(match err.position {
+                    Some(position) => Value::Map(vec![
+                        ("line".to_owned(), Value::Int(position.line as i64)),
+                        ("column".to_owned(), Value::Int(position.column as i64)),
+                    ].into_iter().collect()),
+                    None => Value::None,
+                }),
+                _ => Err(Error::new(UnknownField(name.to_owned()))),
+            },
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
+    ///
+    /// Only the declared fields of an instance can be set. This does not check the
+    /// value against the field's annotation, which `Structs::set_field` does.
+    ///
+    fn try_set_field(&mut self, name: &str, value: Value) -> Result<(), Error> {
+        match self {
+            Value::Map(_) => self.try_set_index(&Value::String(name.to_owned()), value),
+            Value::Instance(instance) => match instance.fields.get_mut(name) {
+                Some(field) => {
+                    *field = value;
+                    Ok(())
+                }
+                None => Err(Error::new(UnknownField(name.to_owned()))),
+            },
+            _ => Err(Error::new(InvalidType))
+        }
+    }
+
     fn into_bool(&self) -> bool {
         match self {
             Value::Int(n) => *n != 0,
             Value::Float(n) => *n != 0f64,
             Value::String(s) => !s.is_empty(),
             Value::Bool(b) => *b,
+            Value::List(items) => !items.is_empty(),
+            Value::Map(entries) => !entries.is_empty(),
+            Value::Instance(_) => true,
             _ => false
         }
     }
 }
 
+///
+/// Converts the rhs of a shift into a shift amount, which must not be negative.
+///
+fn shift_amount(shift: i64) -> Result<u32, Error> {
+    u32::try_from(shift).map_err(|_| Error::new(Overflow))
+}
+
+///
+/// Converts an index value into an offset into a list of the given length.
+///
+fn list_offset(index: &Value, len: usize) -> Result<usize, Error> {
+    match index {
+        Value::Int(n) if *n >= 0 && (*n as usize) < len => Ok(*n as usize),
+        Value::Int(n) => Err(Error::new(IndexOutOfBounds(*n, len))),
+        _ => Err(Error::new(InvalidType))
+    }
+}
+
 #[cfg(test)]
 mod test {}
\ No newline at end of file
@@ -0,0 +1,189 @@
+use std::rc::Rc;
+
+use ast::{BinOp, Enum, Function, Impl, Pattern, Struct, UnaryOp, Value};
+use common::Position;
+
+///
+/// A single bytecode instruction. Every expression compiles down
+/// to a sequence of these which, when executed, leaves exactly one
+/// value on the stack.
+///
+#[derive(Debug, Copy, Clone)]
+pub enum Op {
+    /// Push a constant from the prototype's constant table
+    Const(usize),
+    /// Push `none`
+    None,
+    /// Push a copy of the top of the stack
+    Dup,
+    /// Discard the top of the stack
+    Pop,
+    /// Discard the top n values of the stack
+    PopN(usize),
+    /// Push a copy of a local slot
+    LoadLocal(usize),
+    /// Pop the top of the stack into a local slot
+    StoreLocal(usize),
+    /// Pop the top of the stack into a new cell, held by a boxed local slot
+    MakeCell(usize),
+    /// Push a copy of the value in a boxed local slot's cell
+    LoadCell(usize),
+    /// Pop the top of the stack into a boxed local slot's cell
+    StoreCell(usize),
+    /// Push a copy of a named global
+    LoadGlobal(usize),
+    /// Push a copy of a named regex capture group, such as $1
+    LoadGroup(usize),
+    /// Pop the top of the stack into an existing named global
+    StoreGlobal(usize),
+    /// Pop the top of the stack into a named global, creating it if needed
+    DefineGlobal(usize),
+    /// Register a nested function prototype and push its function value
+    DefineFn(usize),
+    /// Register a nested function prototype and push a closure sharing
+    /// the cells of the local slots it captures
+    Closure(usize),
+    /// Declare a struct from the struct table and push its value
+    Struct(usize),
+    /// Declare an enum from the enum table and push its value
+    Enum(usize),
+    /// Add the methods of an impl block from the impl table to its struct, and push `none`
+    Impl(usize),
+    /// Pop rhs and lhs, and push the result of the operation
+    BinOp(BinOp),
+    /// Pop the operand, and push the result of the operation
+    Unary(UnaryOp),
+    /// Pop n values and push the concatenation of their displayed forms
+    Concat(usize),
+    /// Pop n values and push a list containing them
+    List(usize),
+    /// Pop n key value pairs and push a map containing them
+    Map(usize),
+    /// Pop an index and a container, and push the indexed value
+    Index,
+    /// Pop an index, a container and a value, and push the container
+    /// with the value stored at the index
+    SetIndex,
+    /// Pop a value, and push its named field
+    Field(usize),
+    /// Pop a container and a value, and push the container with the
+    /// value stored in its named field
+    SetField(usize),
+    /// Unconditionally jump to the given instruction
+    Jump(usize),
+    /// Pop the top of the stack, jumping to the given instruction if it is falsey
+    JumpIfFalse(usize),
+    /// Pop a value and start iterating over it
+    IterStart,
+    /// Push the next element of the innermost iterator, either its key and
+    /// value (if true) or the single value bound by one variable loops.
+    /// Jumps to the given instruction if the iterator is exhausted.
+    IterNext(usize, bool),
+    /// Discard the innermost iterator
+    IterEnd,
+    /// Jump to the given instruction if the top of the stack is falsey, leaving
+    /// it in place. Otherwise pop it.
+    JumpIfFalseOrPop(usize),
+    /// Jump to the given instruction if the top of the stack is truthy, leaving
+    /// it in place. Otherwise pop it.
+    JumpIfTrueOrPop(usize),
+    /// Call the value below the given number of arguments
+    Call(usize),
+    /// Call a named global (or builtin) with the given number of arguments
+    CallGlobal(usize, usize),
+    /// Call the value below the given number of positional arguments, followed
+    /// by the named arguments listed in the given entry of the keyword table
+    CallNamed(usize, usize),
+    /// Jump to the given instruction if the given argument slot was given an
+    /// argument by the call, rather than being left to its default
+    JumpIfBound(usize, usize),
+    /// Pop a default value into an argument slot, checking it against the
+    /// argument's annotation
+    StoreArg(usize),
+    /// Match a local slot against the given entry of the pattern table, storing the names
+    /// it binds, then any regex capture groups, in the local slots that follow the entry's
+    /// first slot. Jumps to the given instruction if it does not match.
+    Match(usize, usize, usize),
+    /// Push none for a match in which no arm matched the local slot, warning if it holds
+    /// a value of an enum. Carries the line and column of the match
+    Unmatched(usize, usize, usize),
+    /// Install a handler for errors raised before the matching EndTry, which
+    /// unwinds to the current stack depth, pushes the error as a value and jumps to
+    /// the given instruction
+    Try(usize),
+    /// Remove the innermost handler
+    EndTry,
+    /// Pop a value and raise it as an error, thrown at the given line and column
+    Throw(usize, usize),
+    /// Jump to the given instruction if the top of the stack is not an error value,
+    /// leaving it in place
+    JumpIfNotError(usize),
+    /// Pop the return value and unwind the current frame
+    Return,
+}
+
+///
+/// A Proto is the compiled form of a function (or a top-level script).
+/// It owns its bytecode and all of the constants and names that the
+/// bytecode refers to by index.
+///
+#[derive(Debug, Default)]
+pub struct Proto {
+    /// Number of arguments expected by this function
+    pub arity: usize,
+    /// Whether any arguments have defaults or are variadic, in which case the
+    /// arguments of a call can't simply be taken in order
+    pub flexible_args: bool,
+    /// Total number of local slots, including arguments and captures
+    pub num_locals: usize,
+    /// Names of the values captured by a closure, whose cells occupy the
+    /// local slots following the arguments
+    pub captures: Vec<String>,
+    /// The local slots of the enclosing function whose cells a closure
+    /// captures, in the same order as the names
+    pub capture_slots: Vec<usize>,
+    pub code: Vec<Op>,
+    /// The position of the expression that each instruction was compiled from
+    pub positions: Vec<Position>,
+    pub constants: Vec<Value>,
+    /// Global names referred to by LoadGlobal / StoreGlobal etc
+    pub names: Vec<String>,
+    /// The names of the named arguments of each CallNamed
+    pub keywords: Vec<Vec<String>>,
+    /// Nested function prototypes referred to by DefineFn
+    pub protos: Vec<Rc<Proto>>,
+    /// Struct declarations referred to by Struct
+    pub structs: Vec<Struct>,
+    /// Enum declarations referred to by Enum
+    pub enums: Vec<Enum>,
+    /// Impl blocks referred to by Impl
+    pub impls: Vec<Impl>,
+    /// Patterns referred to by Match, along with the first local slot of the names they bind
+    pub patterns: Vec<(Pattern, usize)>,
+    /// The source function, if this is not a top-level script
+    pub function: Option<Function>,
+}
+
+impl Proto {
+    ///
+    /// Adds a constant to the table, returning its index.
+    ///
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        self.constants.push(value);
+        self.constants.len() - 1
+    }
+
+    ///
+    /// Returns the index of the given name, adding it to the
+    /// table if it has not been seen before.
+    ///
+    pub fn add_name(&mut self, name: &str) -> usize {
+        match self.names.iter().position(|n| n == name) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(name.to_owned());
+                self.names.len() - 1
+            }
+        }
+    }
+}
@@ -0,0 +1,838 @@
+use std::rc::Rc;
+
+use ast::{Arm, Block, Expr, ExprKind, Fragment, Function, Ident, Stmt, StmtKind, Value};
+use common::Position;
+
+use crate::closure;
+use crate::pattern;
+use crate::error::Error;
+use crate::error::ErrorKind::{BreakInWrongContext, ContinueInWrongContext, InvalidAssignment};
+use crate::vm::chunk::{Op, Proto};
+
+type Result<T> = std::result::Result<T, Error>;
+
+struct Local {
+    name: String,
+    depth: usize,
+    /// Whether the local is held in a cell, which closures capturing it share
+    boxed: bool,
+}
+
+struct Loop {
+    label: Option<String>,
+    /// Whether this is a for loop, with an iterator to discard when leaving it
+    iterates: bool,
+    /// Instruction to jump to on continue
+    start: usize,
+    /// Jumps that need patching to the end of the loop
+    breaks: Vec<usize>,
+    /// Stack depth at the start of the loop, so that break
+    /// and continue can discard any temporaries
+    stack_depth: usize,
+    /// Number of enclosing try blocks at the start of the loop, so that
+    /// break and continue can leave any try blocks within it
+    tries: usize,
+}
+
+///
+/// A place that an assignment stores into: a variable, and the steps from it to the
+/// part of it being assigned
+///
+struct Place<'a> {
+    root: &'a Ident,
+    steps: Vec<Step>,
+}
+
+enum Step {
+    /// An index, which has been evaluated into the given local slot
+    Index(usize),
+    /// A field, with the index of its name
+    Field(usize),
+}
+
+///
+/// Compiles a single function (or top-level script) into a Proto.
+///
+/// Locals are resolved to slots at compile time, anything that cannot
+/// be resolved is assumed to be a global and is looked up by name at
+/// runtime. Locals that nested functions may capture are boxed, so that
+/// the frame and the closures capturing them share a cell.
+///
+pub struct Compiler {
+    proto: Proto,
+    locals: Vec<Local>,
+    /// Names referred to by nested functions, whose locals are boxed
+    captured: Vec<String>,
+    scope_depth: usize,
+    /// Number of temporaries on the stack above the locals
+    stack_depth: usize,
+    loops: Vec<Loop>,
+    /// Number of try blocks enclosing the code being compiled
+    tries: usize,
+    is_script: bool,
+    /// Position of the innermost statement or expression being compiled
+    position: Position,
+}
+
+impl Compiler {
+    fn new(is_script: bool, captured: Vec<String>) -> Self {
+        Self {
+            proto: Proto::default(),
+            locals: Vec::new(),
+            captured,
+            scope_depth: 0,
+            stack_depth: 0,
+            loops: Vec::new(),
+            tries: 0,
+            is_script,
+            position: Position::default(),
+        }
+    }
+
+    ///
+    /// Compiles a list of top-level statements into a script Proto.
+    /// The script evaluates to the value of its last statement.
+    ///
+    pub fn compile_script(stmts: &[Stmt]) -> Result<Proto> {
+        let mut compiler = Compiler::new(true, closure::script_captured_names(stmts));
+        compiler.stmts(stmts)?;
+        compiler.emit(Op::Return);
+        Ok(compiler.finish())
+    }
+
+    ///
+    /// Compiles a function definition into a Proto, with its arguments
+    /// occupying the first local slots, followed by the cells of any captures.
+    ///
+    pub fn compile_function(func: &Function, captures: &[String]) -> Result<Proto> {
+        let mut compiler = Compiler::new(false, closure::captured_names(func));
+        compiler.proto.arity = func.args.len();
+        compiler.proto.flexible_args = func.args.iter().any(|arg| arg.default.is_some() || arg.variadic);
+        compiler.proto.function = Some(func.clone());
+        compiler.proto.captures = captures.to_vec();
+        for arg in &func.args {
+            compiler.declare(&arg.name.name);
+        }
+        for name in captures {
+            let slot = compiler.declare(name);
+            compiler.locals[slot].boxed = true;
+        }
+        // arguments that the call left unbound take their defaults, in order
+        for (slot, arg) in func.args.iter().enumerate() {
+            if let Some(default) = &arg.default {
+                let skip = compiler.emit(Op::JumpIfBound(slot, 0));
+                compiler.expr(default)?;
+                compiler.emit_pop(Op::StoreArg(slot));
+                compiler.patch(skip);
+            }
+            compiler.box_local(slot);
+        }
+        compiler.block(&func.block)?;
+        compiler.emit(Op::Return);
+        Ok(compiler.finish())
+    }
+
+    fn finish(mut self) -> Proto {
+        self.proto.num_locals = self.proto.num_locals.max(self.locals.len());
+        self.proto
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.proto.code.push(op);
+        self.proto.positions.push(self.position);
+        self.proto.code.len() - 1
+    }
+
+    ///
+    /// Emits an instruction that pushes a single value onto the stack.
+    ///
+    fn emit_push(&mut self, op: Op) {
+        self.emit(op);
+        self.stack_depth += 1;
+    }
+
+    ///
+    /// Emits an instruction that pops a single value from the stack.
+    ///
+    fn emit_pop(&mut self, op: Op) {
+        self.emit(op);
+        self.stack_depth -= 1;
+    }
+
+    fn patch(&mut self, at: usize) {
+        let target = self.proto.code.len();
+        self.proto.code[at] = match self.proto.code[at] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::IterNext(_, pair) => Op::IterNext(target, pair),
+            Op::JumpIfFalseOrPop(_) => Op::JumpIfFalseOrPop(target),
+            Op::JumpIfTrueOrPop(_) => Op::JumpIfTrueOrPop(target),
+            Op::JumpIfBound(slot, _) => Op::JumpIfBound(slot, target),
+            Op::Match(idx, slot, _) => Op::Match(idx, slot, target),
+            Op::Try(_) => Op::Try(target),
+            Op::JumpIfNotError(_) => Op::JumpIfNotError(target),
+            op => panic!("attempted to patch non-jump instruction: {:?}", op)
+        };
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+        self.proto.num_locals = self.proto.num_locals.max(self.locals.len());
+        while let Some(local) = self.locals.last() {
+            if local.depth <= self.scope_depth {
+                break;
+            }
+            self.locals.pop();
+        }
+    }
+
+    ///
+    /// Whether a binding at the current point would be a global
+    /// i.e. it is at the top level of a script.
+    ///
+    fn is_global_scope(&self) -> bool {
+        self.is_script && self.scope_depth == 0
+    }
+
+    fn declare(&mut self, name: &str) -> usize {
+        self.locals.push(Local {
+            name: name.to_owned(),
+            depth: self.scope_depth,
+            boxed: self.captured.iter().any(|captured| captured == name),
+        });
+        self.proto.num_locals = self.proto.num_locals.max(self.locals.len());
+        self.locals.len() - 1
+    }
+
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local.name == name)
+    }
+
+    ///
+    /// The instruction that pops a value into a newly declared local slot,
+    /// which is given a new cell if it is boxed.
+    ///
+    fn init_local(&self, slot: usize) -> Op {
+        if self.locals[slot].boxed {
+            Op::MakeCell(slot)
+        } else {
+            Op::StoreLocal(slot)
+        }
+    }
+
+    ///
+    /// Moves the value of a newly declared local slot, which was stored in it
+    /// directly, into a cell if it is boxed.
+    ///
+    fn box_local(&mut self, slot: usize) {
+        if self.locals[slot].boxed {
+            self.emit_push(Op::LoadLocal(slot));
+            self.emit_pop(Op::MakeCell(slot));
+        }
+    }
+
+    ///
+    /// Pops the value on the top of the stack into a new binding,
+    /// either a global or a local slot depending on the current scope.
+    ///
+    fn bind(&mut self, name: &str) {
+        if self.is_global_scope() {
+            let idx = self.proto.add_name(name);
+            self.emit_pop(Op::DefineGlobal(idx));
+        } else {
+            let slot = self.declare(name);
+            self.emit_pop(self.init_local(slot));
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> Result<()> {
+        if stmts.is_empty() {
+            self.emit_push(Op::None);
+        }
+        for (i, stmt) in stmts.iter().enumerate() {
+            self.stmt(stmt)?;
+            if i + 1 < stmts.len() {
+                self.emit_pop(Op::Pop);
+            }
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<()> {
+        self.position = stmt.position;
+        match &stmt.kind {
+            StmtKind::Expr(expr) => self.expr(expr),
+            StmtKind::Fn(func) if self.is_global_scope() => {
+                self.function(func)?;
+                self.bind(&func.ident.name);
+                self.emit_push(Op::None);
+                Ok(())
+            }
+            StmtKind::Fn(func) => {
+                // the local is declared first, so that the function can capture it to call itself
+                self.emit_push(Op::None);
+                self.bind(&func.ident.name);
+                self.function(func)?;
+                let slot = self.resolve(&func.ident.name).unwrap();
+                self.emit_pop(self.store_local(slot));
+                self.emit_push(Op::None);
+                Ok(())
+            }
+            StmtKind::Struct(def) => {
+                self.proto.structs.push(def.clone());
+                self.emit_push(Op::Struct(self.proto.structs.len() - 1));
+                self.bind(&def.ident.name);
+                self.emit_push(Op::None);
+                Ok(())
+            }
+            StmtKind::Enum(def) => {
+                self.proto.enums.push(def.clone());
+                self.emit_push(Op::Enum(self.proto.enums.len() - 1));
+                self.bind(&def.ident.name);
+                self.emit_push(Op::None);
+                Ok(())
+            }
+            StmtKind::Impl(imp) => {
+                self.proto.impls.push(imp.clone());
+                self.emit_push(Op::Impl(self.proto.impls.len() - 1));
+                Ok(())
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) -> Result<()> {
+        let outer = self.position;
+        self.begin_scope();
+        self.stmts(&block.stmts)?;
+        self.end_scope();
+        self.position = outer;
+        Ok(())
+    }
+
+    ///
+    /// Compiles an expression, attributing its instructions to its position, so that
+    /// errors they raise can be given it.
+    ///
+    fn expr(&mut self, expr: &Expr) -> Result<()> {
+        let outer = std::mem::replace(&mut self.position, expr.position);
+        self.expr_kind(expr)?;
+        self.position = outer;
+        Ok(())
+    }
+
+    fn expr_kind(&mut self, expr: &Expr) -> Result<()> {
+        use ExprKind::*;
+        match &expr.kind {
+            Let(ident, rhs) => {
+                match rhs {
+                    Some(rhs) => self.expr(rhs)?,
+                    None => self.emit_push(Op::None),
+                }
+                self.bind(&ident.name);
+                self.emit_push(Op::None);
+            }
+            Assign(lhs, rhs) => {
+                self.expr(rhs)?;
+                // assignment evaluates to the assigned value, so that it can be chained
+                self.emit_push(Op::Dup);
+                self.begin_scope();
+                let place = self.place(lhs)?;
+                self.store(&place);
+                self.end_scope();
+            }
+            AssignOp(lhs, op, rhs) => {
+                // the place is evaluated once, for both loading its value and storing the result
+                self.begin_scope();
+                let place = self.place(lhs)?;
+                self.load(&place, place.steps.len());
+                self.expr(rhs)?;
+                self.emit_pop(Op::BinOp(*op));
+                self.emit_push(Op::Dup);
+                self.store(&place);
+                self.end_scope();
+            }
+            BinOp(lhs, op @ (ast::BinOp::And | ast::BinOp::Or), rhs) => {
+                // short-circuit, leaving the operand that decided the result
+                self.expr(lhs)?;
+                let jump = match op {
+                    ast::BinOp::And => self.emit(Op::JumpIfFalseOrPop(0)),
+                    _ => self.emit(Op::JumpIfTrueOrPop(0)),
+                };
+                // either lhs is left on the stack, or it is replaced by rhs
+                self.stack_depth -= 1;
+                self.expr(rhs)?;
+                self.patch(jump);
+            }
+            BinOp(lhs, op, rhs) => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                // pops both operands and pushes the result
+                self.emit_pop(Op::BinOp(*op));
+            }
+            Unary(op, operand) => {
+                self.expr(operand)?;
+                // replaces the operand with the result
+                self.emit(Op::Unary(*op));
+            }
+            Lambda(func) => self.function(func)?,
+            Call(callee, args, named) => self.call(callee, args, named)?,
+            Literal(value) => {
+                let idx = self.proto.add_constant(value.clone());
+                self.emit_push(Op::Const(idx));
+            }
+            Interpolate(fragments) => {
+                for fragment in fragments {
+                    match fragment {
+                        Fragment::Text(text) => {
+                            let idx = self.proto.add_constant(Value::String(text.clone()));
+                            self.emit_push(Op::Const(idx));
+                        }
+                        Fragment::Expr(expr) => self.expr(expr)?,
+                    }
+                }
+                self.stack_depth -= fragments.len();
+                self.emit_push(Op::Concat(fragments.len()));
+            }
+            Ident(ident) => self.load_ident(ident),
+            List(items) => {
+                for item in items {
+                    self.expr(item)?;
+                }
+                self.stack_depth -= items.len();
+                self.emit_push(Op::List(items.len()));
+            }
+            Map(entries) => {
+                for (key, value) in entries {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                }
+                self.stack_depth -= entries.len() * 2;
+                self.emit_push(Op::Map(entries.len()));
+            }
+            Index(target, index) => {
+                self.expr(target)?;
+                self.expr(index)?;
+                // pops both and pushes the indexed value
+                self.emit_pop(Op::Index);
+            }
+            Field(target, field) => {
+                self.expr(target)?;
+                let idx = self.proto.add_name(&field.name);
+                // replaces the target with its field
+                self.emit(Op::Field(idx));
+            }
+            While(label, cond, block) => self.while_loop(label, cond, block)?,
+            For(label, first, second, iterable, block) => self.for_loop(label, first, second, iterable, block)?,
+            Match(subject, arms) => self.match_arms(subject, arms, expr.position)?,
+            Throw(value) => {
+                self.expr(value)?;
+                // Throw unwinds to the nearest handler, but the value is accounted
+                // for as if the expression completed normally.
+                self.emit(Op::Throw(expr.position.line, expr.position.column));
+            }
+            Try(block, ident, catch) => self.try_catch(block, ident, catch)?,
+            Propagate(value) => {
+                self.expr(value)?;
+                // an error is returned as it is, otherwise the value is left in place
+                let skip = self.emit(Op::JumpIfNotError(0));
+                self.emit(Op::Return);
+                self.patch(skip);
+            }
+            If(cond, block, maybe_else) => {
+                self.expr(cond)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.stack_depth -= 1;
+                self.block(block)?;
+                let to_end = self.emit(Op::Jump(0));
+                // only one of the branches is taken
+                self.stack_depth -= 1;
+                self.patch(to_else);
+                match maybe_else {
+                    Some(expr) => self.expr(expr)?,
+                    None => self.emit_push(Op::None),
+                }
+                self.patch(to_end);
+            }
+            Block(block) => self.block(block)?,
+            Ret(expr) => {
+                self.expr(expr)?;
+                // Return unwinds the whole frame, but the value is accounted
+                // for as if the expression completed normally.
+                self.emit(Op::Return);
+            }
+            Break(label, value) => {
+                let (idx, discard) = self.unwind_loop(label, BreakInWrongContext)?;
+                if self.loops[idx].iterates {
+                    self.emit(Op::IterEnd);
+                }
+                // the loop evaluates to the value it was broken out of with
+                match value {
+                    Some(value) => self.expr(value)?,
+                    None => self.emit_push(Op::None),
+                }
+                let jump = self.emit(Op::Jump(0));
+                self.loops[idx].breaks.push(jump);
+                self.stack_depth += discard;
+            }
+            Continue(label) => {
+                let (idx, discard) = self.unwind_loop(label, ContinueInWrongContext)?;
+                let start = self.loops[idx].start;
+                self.emit(Op::Jump(start));
+                self.stack_depth += discard + 1;
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Compiles a nested function definition, pushing its value. Any locals that
+    /// it refers to are captured, sharing their cells, in which case the value is a closure.
+    ///
+    fn function(&mut self, func: &Function) -> Result<()> {
+        let captures: Vec<String> = closure::free_names(func).into_iter()
+            .filter(|name| self.resolve(name).is_some())
+            .collect();
+        let mut proto = Compiler::compile_function(func, &captures)?;
+        proto.capture_slots = captures.iter().map(|name| self.resolve(name).unwrap()).collect();
+        self.proto.protos.push(Rc::new(proto));
+        let idx = self.proto.protos.len() - 1;
+        if captures.is_empty() {
+            self.emit_push(Op::DefineFn(idx));
+        } else {
+            self.emit_push(Op::Closure(idx));
+        }
+        Ok(())
+    }
+
+    ///
+    /// The instruction that pops a value into an existing local slot.
+    ///
+    fn store_local(&self, slot: usize) -> Op {
+        if self.locals[slot].boxed {
+            Op::StoreCell(slot)
+        } else {
+            Op::StoreLocal(slot)
+        }
+    }
+
+    fn load_ident(&mut self, ident: &Ident) {
+        match self.resolve(&ident.name) {
+            Some(slot) if self.locals[slot].boxed => self.emit_push(Op::LoadCell(slot)),
+            Some(slot) => self.emit_push(Op::LoadLocal(slot)),
+            None if ident.name.starts_with('$') => {
+                let idx = self.proto.add_name(&ident.name);
+                self.emit_push(Op::LoadGroup(idx));
+            }
+            None => {
+                let idx = self.proto.add_name(&ident.name);
+                self.emit_push(Op::LoadGlobal(idx));
+            }
+        }
+    }
+
+    ///
+    /// Evaluates the place described by target, which is either an existing variable
+    /// or an index or field of one. Indexes are evaluated into hidden locals in the
+    /// current scope, so that the place can be both loaded and stored into without
+    /// evaluating them again.
+    ///
+    fn place<'a>(&mut self, target: &'a Expr) -> Result<Place<'a>> {
+        match &target.kind {
+            ExprKind::Ident(ident) => Ok(Place { root: ident, steps: Vec::new() }),
+            ExprKind::Index(container, index) => {
+                let mut place = self.place(container)?;
+                self.expr(index)?;
+                let slot = self.declare("<index>");
+                self.emit_pop(Op::StoreLocal(slot));
+                place.steps.push(Step::Index(slot));
+                Ok(place)
+            }
+            ExprKind::Field(container, field) => {
+                let mut place = self.place(container)?;
+                place.steps.push(Step::Field(self.proto.add_name(&field.name)));
+                Ok(place)
+            }
+            _ => Err(Error::new(InvalidAssignment)),
+        }
+    }
+
+    ///
+    /// Pushes the value found by following the first depth steps of the place's path.
+    ///
+    fn load(&mut self, place: &Place, depth: usize) {
+        self.load_ident(place.root);
+        for step in &place.steps[..depth] {
+            match *step {
+                Step::Index(slot) => {
+                    self.emit_push(Op::LoadLocal(slot));
+                    // pops the index and container, and pushes the element
+                    self.emit_pop(Op::Index);
+                }
+                Step::Field(idx) => {
+                    self.emit(Op::Field(idx));
+                }
+            }
+        }
+    }
+
+    ///
+    /// Pops the value on the top of the stack into the place. Indexing stores into
+    /// a copy of the container, which is then stored back in turn.
+    ///
+    fn store(&mut self, place: &Place) {
+        for (depth, step) in place.steps.iter().enumerate().rev() {
+            self.load(place, depth);
+            match *step {
+                Step::Index(slot) => {
+                    self.emit_push(Op::LoadLocal(slot));
+                    // pops the index, container and value, and pushes the updated container
+                    self.emit(Op::SetIndex);
+                    self.stack_depth -= 2;
+                }
+                // pops the container and value, and pushes the updated container
+                Step::Field(idx) => self.emit_pop(Op::SetField(idx)),
+            }
+        }
+        match self.resolve(&place.root.name) {
+            Some(slot) => self.emit_pop(self.store_local(slot)),
+            None => {
+                let idx = self.proto.add_name(&place.root.name);
+                self.emit_pop(Op::StoreGlobal(idx));
+            }
+        }
+    }
+
+    ///
+    /// Finds the loop with the given label (or the innermost loop), and discards any
+    /// temporaries pushed since its start and the iterators of any loops within it,
+    /// ready to jump out of (or back to the start of) that loop. Returns the loop's
+    /// index and the number of discarded values, so that the caller can restore the
+    /// compile time stack depth for the (unreachable) code that follows the jump.
+    ///
+    fn unwind_loop(&mut self, label: &Option<Ident>, err: crate::error::ErrorKind) -> Result<(usize, usize)> {
+        let idx = match label {
+            Some(label) => self.loops.iter().rposition(|lp| lp.label.as_ref() == Some(&label.name)),
+            None => self.loops.len().checked_sub(1),
+        };
+        let idx = idx.ok_or_else(|| Error::new(err))?;
+        let discard = self.stack_depth - self.loops[idx].stack_depth;
+        if discard > 0 {
+            self.emit(Op::PopN(discard));
+        }
+        self.stack_depth -= discard;
+        let inner = self.loops[idx + 1..].iter().filter(|lp| lp.iterates).count();
+        for _ in 0..inner {
+            self.emit(Op::IterEnd);
+        }
+        for _ in self.loops[idx].tries..self.tries {
+            self.emit(Op::EndTry);
+        }
+        Ok((idx, discard))
+    }
+
+    ///
+    /// Compiles a try block, which runs with a handler installed. If the block raises an
+    /// error, the VM unwinds to the handler's stack depth and pushes the error as a value,
+    /// jumping to the catch block, which binds it to its name (if any) in its own scope.
+    ///
+    fn try_catch(&mut self, block: &Block, ident: &Option<Ident>, catch: &Block) -> Result<()> {
+        let handler = self.emit(Op::Try(0));
+        self.tries += 1;
+        self.block(block)?;
+        self.tries -= 1;
+        self.emit(Op::EndTry);
+        let end = self.emit(Op::Jump(0));
+        // only one of the blocks leaves a value, and the catch block starts with the error
+        self.patch(handler);
+        self.begin_scope();
+        match ident {
+            Some(ident) => {
+                let slot = self.declare(&ident.name);
+                self.emit(self.init_local(slot));
+            }
+            None => {
+                self.emit(Op::Pop);
+            }
+        }
+        self.stack_depth -= 1;
+        self.block(catch)?;
+        self.end_scope();
+        self.patch(end);
+        Ok(())
+    }
+
+    ///
+    /// Compiles a while loop, which evaluates to none unless it is broken out of
+    /// with a value. Breaks push their value and jump past the none.
+    ///
+    fn while_loop(&mut self, label: &Option<Ident>, cond: &Expr, block: &Block) -> Result<()> {
+        let start = self.proto.code.len();
+        self.loops.push(Loop {
+            label: label.as_ref().map(|label| label.name.clone()),
+            iterates: false,
+            start,
+            breaks: Vec::new(),
+            stack_depth: self.stack_depth,
+            tries: self.tries,
+        });
+        self.expr(cond)?;
+        let exit = self.emit(Op::JumpIfFalse(0));
+        self.stack_depth -= 1;
+        self.block(block)?;
+        self.emit_pop(Op::Pop);
+        self.emit(Op::Jump(start));
+        self.patch(exit);
+        self.emit_push(Op::None);
+
+        let lp = self.loops.pop().unwrap();
+        for jump in lp.breaks {
+            self.patch(jump);
+        }
+        Ok(())
+    }
+
+    ///
+    /// Compiles a match expression. The subject is kept in a hidden local, which each arm
+    /// matches against its pattern, binding its names into the arm's own local slots. Only
+    /// the arm that runs leaves a value, or none is pushed if no arm matches.
+    ///
+    fn match_arms(&mut self, subject: &Expr, arms: &[Arm], position: Position) -> Result<()> {
+        self.begin_scope();
+        self.expr(subject)?;
+        let subject = self.declare("<match>");
+        self.emit_pop(Op::StoreLocal(subject));
+        let mut ends = Vec::new();
+        for arm in arms {
+            self.begin_scope();
+            let first = self.locals.len();
+            for ident in arm.pattern.bindings() {
+                self.declare(&ident.name);
+            }
+            // regex capture groups are bound alongside the pattern's names, following them
+            for name in pattern::captures(&arm.pattern) {
+                self.declare(&name);
+            }
+            self.proto.patterns.push((arm.pattern.clone(), first));
+            let next = self.emit(Op::Match(self.proto.patterns.len() - 1, subject, 0));
+            for slot in first..self.locals.len() {
+                self.box_local(slot);
+            }
+            let guard = match &arm.guard {
+                Some(guard) => {
+                    self.expr(guard)?;
+                    let failed = self.emit(Op::JumpIfFalse(0));
+                    self.stack_depth -= 1;
+                    Some(failed)
+                }
+                None => None,
+            };
+            self.expr(&arm.body)?;
+            ends.push(self.emit(Op::Jump(0)));
+            self.stack_depth -= 1;
+            self.patch(next);
+            if let Some(failed) = guard {
+                self.patch(failed);
+            }
+            self.end_scope();
+        }
+        self.emit_push(Op::Unmatched(subject, position.line, position.column));
+        for end in ends {
+            self.patch(end);
+        }
+        self.end_scope();
+        Ok(())
+    }
+
+    fn for_loop(&mut self, label: &Option<Ident>, first: &Ident, second: &Option<Ident>, iterable: &Expr, block: &Block) -> Result<()> {
+        self.expr(iterable)?;
+        // the iterator lives on the VM's iterator stack, rather than the value stack
+        self.emit_pop(Op::IterStart);
+        let start = self.proto.code.len();
+        self.loops.push(Loop {
+            label: label.as_ref().map(|label| label.name.clone()),
+            iterates: true,
+            start,
+            breaks: Vec::new(),
+            stack_depth: self.stack_depth,
+            tries: self.tries,
+        });
+        let next = self.emit(Op::IterNext(0, second.is_some()));
+
+        // the loop variables live in their own scope, around the block's
+        self.begin_scope();
+        let mut names = vec![first];
+        names.extend(second);
+        self.stack_depth += names.len();
+        for ident in names.iter().rev() {
+            let slot = self.declare(&ident.name);
+            self.emit_pop(self.init_local(slot));
+        }
+        self.block(block)?;
+        self.emit_pop(Op::Pop);
+        self.end_scope();
+        self.emit(Op::Jump(start));
+        self.patch(next);
+        self.emit(Op::IterEnd);
+        self.emit_push(Op::None);
+
+        // breaks have already discarded the iterator
+        let lp = self.loops.pop().unwrap();
+        for jump in lp.breaks {
+            self.patch(jump);
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Box<Expr>], named: &[(Ident, Expr)]) -> Result<()> {
+        if !named.is_empty() {
+            return self.call_named(callee, args, named);
+        }
+        // named calls to anything other than a local are resolved at runtime,
+        // so that they can refer to builtins and to functions defined later.
+        // A placeholder takes the place of the callee on the stack.
+        let global = match &callee.kind {
+            ExprKind::Ident(ident) if self.resolve(&ident.name).is_none() => {
+                self.emit_push(Op::None);
+                Some(self.proto.add_name(&ident.name))
+            }
+            _ => {
+                self.expr(callee)?;
+                None
+            }
+        };
+        for arg in args {
+            self.expr(arg)?;
+        }
+        self.stack_depth -= args.len();
+        // the callee is replaced by the result of the call
+        match global {
+            Some(idx) => self.emit(Op::CallGlobal(idx, args.len())),
+            None => self.emit(Op::Call(args.len())),
+        };
+        Ok(())
+    }
+
+    ///
+    /// Compiles a call with named arguments, which are pushed after the positional
+    /// ones. Their names are recorded in the keyword table for the VM to bind.
+    ///
+    fn call_named(&mut self, callee: &Expr, args: &[Box<Expr>], named: &[(Ident, Expr)]) -> Result<()> {
+        self.expr(callee)?;
+        for arg in args {
+            self.expr(arg)?;
+        }
+        for (_, arg) in named {
+            self.expr(arg)?;
+        }
+        self.stack_depth -= args.len() + named.len();
+        self.proto.keywords.push(named.iter().map(|(name, _)| name.name.clone()).collect());
+        let idx = self.proto.keywords.len() - 1;
+        // the callee is replaced by the result of the call
+        self.emit(Op::CallNamed(args.len(), idx));
+        Ok(())
+    }
+}
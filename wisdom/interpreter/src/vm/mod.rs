@@ -0,0 +1,721 @@
+//!
+//! A bytecode compiler and stack machine, implementing the same
+//! language semantics as the SlowInterpreter without walking the
+//! AST on every evaluation.
+//!
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use ast::{BinOp, Capture, Closure, Function, Stmt, Value};
+use common::Position;
+use tokenizer::{FromTokens, TokenStream};
+
+use crate::{args, builtin, pattern, types, Interpreter};
+use crate::error::{Error, Warning};
+use crate::error::ErrorKind::{InvalidType, NotCallable, UndefinedVar, UnknownArg};
+use crate::iter::ValueIter;
+use crate::regex_cache::{self, RegexCache};
+use crate::structs::Structs;
+use crate::value::Operations;
+use crate::vm::chunk::{Op, Proto};
+use crate::vm::compiler::Compiler;
+
+mod chunk;
+mod compiler;
+
+type Result<T> = std::result::Result<T, Error>;
+
+///
+/// A single activation of a Proto. Its locals live on the value
+/// stack, starting at base. The slot below base holds the callee.
+///
+struct Frame {
+    proto: Rc<Proto>,
+    ip: usize,
+    base: usize,
+    /// Height of the iterator stack on entry, so that returning
+    /// from within a for loop can discard its iterators
+    iters: usize,
+    /// Argument slots that the call left unbound, which take their defaults
+    unbound: Vec<usize>,
+    /// The cells of boxed local slots, by slot, which include the captures of
+    /// the closure being called in the slots following the arguments
+    cells: Vec<Option<Capture>>,
+    /// The capture groups of the last successful regex match in this frame
+    groups: Vec<(String, Value)>,
+}
+
+///
+/// An error handler installed by a try block, recording the state to unwind
+/// to if an error is raised within it.
+///
+struct Handler {
+    /// Number of frames, including the one with the try block
+    frames: usize,
+    stack: usize,
+    iters: usize,
+    /// Instruction of the catch block
+    catch: usize,
+}
+
+pub struct VmInterpreter {
+    globals: HashMap<String, Value>,
+    /// Compiled functions, by the id given to the function values created from
+    /// them. Function values are resolved to their compiled form through this
+    /// table when called.
+    functions: HashMap<usize, Rc<Proto>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// Iterators of the for loops currently being executed
+    iters: Vec<ValueIter>,
+    /// Handlers of the try blocks currently being executed
+    handlers: Vec<Handler>,
+    /// Shared with builtins, which may call back into the VM
+    regexes: Rc<RegexCache>,
+    structs: Structs,
+    /// Warnings raised while running, until they are taken
+    warnings: Vec<Warning>,
+}
+
+impl Default for VmInterpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VmInterpreter {
+    pub fn new() -> Self {
+        Self {
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            iters: Vec::new(),
+            handlers: Vec::new(),
+            regexes: Rc::new(RegexCache::new()),
+            structs: Structs::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    ///
+    /// Runs a compiled script to completion, returning the value
+    /// of its final statement.
+    ///
+    fn run(&mut self, script: Proto) -> Result<Value> {
+        // placeholder for the callee slot
+        self.stack.push(Value::None);
+        self.push_frame(Rc::new(script), 0);
+        let result = self.execute(0);
+        self.stack.clear();
+        self.frames.clear();
+        self.iters.clear();
+        self.handlers.clear();
+        result
+    }
+
+    ///
+    /// Pushes a new frame for the given Proto, whose arguments have
+    /// already been pushed, and reserves the remaining local slots.
+    ///
+    fn push_frame(&mut self, proto: Rc<Proto>, argc: usize) {
+        let base = self.stack.len() - argc;
+        for _ in argc..proto.num_locals {
+            self.stack.push(Value::None);
+        }
+        self.frames.push(Frame { proto, ip: 0, base, iters: self.iters.len(), unbound: Vec::new(), cells: Vec::new(), groups: Vec::new() });
+    }
+
+    ///
+    /// Registers a compiled function, returning its source function identified
+    /// by it, from which function values that resolve to it are created. A
+    /// function is identified by its prototype, so that functions which share
+    /// a name are never confused.
+    ///
+    fn register(&mut self, proto: Rc<Proto>) -> Function {
+        let id = Rc::as_ptr(&proto) as usize;
+        let mut source = proto.function.clone().expect("function prototype without a source function");
+        source.id = Some(id);
+        self.functions.entry(id).or_insert(proto);
+        source
+    }
+
+    ///
+    /// Finds the compiled form of a function value. Any function that was not
+    /// created by the VM is compiled on demand.
+    ///
+    fn resolve_function(&mut self, callee: &Value) -> Result<Rc<Proto>> {
+        let func = match callee {
+            Value::Func(func) => func,
+            Value::Closure(closure) => &closure.func,
+            Value::Method(method) => &method.func,
+            _ => return Err(Error::new(NotCallable)),
+        };
+        if let Some(proto) = func.id.and_then(|id| self.functions.get(&id)) {
+            return Ok(proto.clone());
+        }
+        let captures: Vec<String> = captures_of(callee).iter().map(|(name, _)| name.clone()).collect();
+        Ok(Rc::new(Compiler::compile_function(func, &captures)?))
+    }
+
+    ///
+    /// Sets up a call to proto, with argc positional arguments on the top of the stack,
+    /// followed by a value for each of the given names. Arguments are bound to their
+    /// slots and checked against any type annotations. The cells of any captures occupy
+    /// the local slots following the arguments.
+    ///
+    fn call(&mut self, proto: Rc<Proto>, argc: usize, names: &[String], captures: &[(String, Capture)]) -> Result<()> {
+        let func = proto.function.as_ref().expect("function prototype without a source function");
+        let mut unbound = Vec::new();
+        if names.is_empty() && !proto.flexible_args && proto.arity == argc {
+            if func.args.iter().any(|arg| arg.typ.is_some()) {
+                let args = self.stack.split_off(self.stack.len() - argc);
+                for (spec, arg) in func.args.iter().zip(args) {
+                    let arg = types::check_arg(spec, arg, &self.regexes, &self.structs)?;
+                    self.stack.push(arg);
+                }
+            }
+        } else {
+            let mut args = self.stack.split_off(self.stack.len() - argc - names.len());
+            let named = names.iter().cloned().zip(args.split_off(argc)).collect();
+            let args = types::check_args(func, args::bind(func, args, named)?, &self.regexes, &self.structs)?;
+            for (slot, arg) in args.into_iter().enumerate() {
+                if arg.is_none() {
+                    unbound.push(slot);
+                }
+                self.stack.push(arg.unwrap_or(Value::None));
+            }
+        }
+        let argc = proto.arity;
+        self.push_frame(proto, argc);
+        let frame = self.frames.last_mut().unwrap();
+        frame.unbound = unbound;
+        if !captures.is_empty() {
+            frame.cells = vec![None; argc];
+            frame.cells.extend(captures.iter().map(|(_, cell)| Some(cell.clone())));
+        }
+        Ok(())
+    }
+
+    ///
+    /// Finds the cell of a boxed local slot of the current frame.
+    ///
+    fn cell(&self, slot: usize) -> &Capture {
+        self.frames.last().expect("no active frame").cells[slot].as_ref().expect("boxed local without a cell")
+    }
+
+    ///
+    /// Calls callee, which is below argc positional arguments and any named arguments
+    /// on the top of the stack. Functions get a new frame, whereas builtins are run (and
+    /// structs and enum variants constructed) immediately, replacing the callee slot and arguments with
+    /// the result. A method's instance is passed in as its first argument.
+    ///
+    fn invoke(&mut self, callee: &Value, argc: usize, names: &[String]) -> Result<()> {
+        match callee {
+            Value::Builtin(_) if !names.is_empty() => Err(Error::new(UnknownArg(names[0].clone()))),
+            Value::Builtin(name) => self.call_builtin(name, argc),
+            Value::Struct(_) | Value::Variant(_, _) => {
+                let mut args = self.stack.split_off(self.stack.len() - argc - names.len());
+                let named = names.iter().cloned().zip(args.split_off(argc)).collect();
+                let instance = self.structs.construct(callee, args, named, &self.regexes)?;
+                *self.stack.last_mut().unwrap() = instance;
+                Ok(())
+            }
+            Value::Method(method) => {
+                let at = self.stack.len() - argc - names.len();
+                self.stack.insert(at, method.receiver.clone());
+                let func = self.resolve_function(callee)?;
+                self.call(func, argc + 1, names, &[])
+            }
+            _ => {
+                let func = self.resolve_function(callee)?;
+                self.call(func, argc, names, captures_of(callee))
+            }
+        }
+    }
+
+    ///
+    /// Calls a function value on behalf of a builtin, running it to completion.
+    ///
+    fn call_value(&mut self, callee: &Value, args: Vec<Value>) -> Result<Value> {
+        if let Value::Builtin(name) = callee {
+            return self.run_builtin(name, args);
+        }
+        let depth = self.frames.len();
+        let argc = args.len();
+        // placeholder for the callee slot
+        self.stack.push(Value::None);
+        self.stack.extend(args);
+        self.invoke(callee, argc, &[])?;
+        // structs are constructed without a new frame
+        if self.frames.len() == depth {
+            return Ok(self.pop());
+        }
+        self.execute(depth)
+    }
+
+    ///
+    /// Calls a builtin, replacing the callee slot and arguments with the result.
+    ///
+    fn call_builtin(&mut self, name: &str, argc: usize) -> Result<()> {
+        let args = self.stack.split_off(self.stack.len() - argc);
+        let result = self.run_builtin(name, args)?;
+        *self.stack.last_mut().unwrap() = result;
+        Ok(())
+    }
+
+    fn run_builtin(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let regexes = self.regexes.clone();
+        builtin::run(name, args, &regexes, &mut |func, args| self.call_value(func, args))
+    }
+
+    ///
+    /// Matches a string against a regex (or a string pattern) and on success,
+    /// binds the capture groups in the current frame.
+    ///
+    fn regex_match(&mut self, lhs: Value, rhs: Value) -> Result<Value> {
+        let rhs = self.regexes.coerce(rhs)?;
+        if let (Value::String(text), Value::Regex(re)) = (&lhs, &rhs) {
+            return match regex_cache::captures(re, text) {
+                Some(groups) => {
+                    self.store_captures(groups);
+                    Ok(Value::Bool(true))
+                }
+                None => Ok(Value::Bool(false)),
+            };
+        }
+        lhs.try_binop(BinOp::TildeEq, &rhs)
+    }
+
+    ///
+    /// Binds the capture groups of a successful regex match in the current frame,
+    /// replacing any left over from a previous match there.
+    ///
+    fn store_captures(&mut self, groups: Vec<(String, Value)>) {
+        self.frames.last_mut().expect("no active frame").groups = groups;
+    }
+
+    ///
+    /// Looks up a capture group by name in the current frame. Groups belong to
+    /// the call that matched them, and are not seen by the functions it calls.
+    ///
+    fn lookup_group(&self, name: &str) -> Option<Value> {
+        self.frames.last().expect("no active frame").groups.iter()
+            .find(|(group, _)| group == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("value stack underflow")
+    }
+
+    ///
+    /// Executes the current frame (and any that it calls) until it returns,
+    /// leaving depth frames below it. Errors raised along the way are caught
+    /// by the innermost try block, if it is within this execution.
+    ///
+    fn execute(&mut self, depth: usize) -> Result<Value> {
+        loop {
+            match self.dispatch(depth) {
+                Err(err) => self.catch(err, depth)?,
+                result => return result,
+            }
+        }
+    }
+
+    ///
+    /// Unwinds to the innermost handler, pushing the error as a value and resuming
+    /// at its catch block. Handlers of an outer execution are left to catch the
+    /// error once it has been returned to them.
+    ///
+    fn catch(&mut self, err: Error, depth: usize) -> Result<()> {
+        match self.handlers.last() {
+            Some(handler) if handler.frames > depth => {
+                let handler = self.handlers.pop().unwrap();
+                self.frames.truncate(handler.frames);
+                self.stack.truncate(handler.stack);
+                self.iters.truncate(handler.iters);
+                self.stack.push(err.into_value());
+                self.frames.last_mut().expect("no active frame").ip = handler.catch;
+                Ok(())
+            }
+            _ => Err(err),
+        }
+    }
+
+    fn dispatch(&mut self, depth: usize) -> Result<Value> {
+        let (mut proto, mut ip, mut base) = self.current_frame();
+
+        loop {
+            let at = ip;
+            ip += 1;
+            match self.step(proto.code[at], &proto, &mut ip, base, depth) {
+                Ok(Flow::Next) => {}
+                Ok(Flow::Reload) => (proto, ip, base) = self.current_frame(),
+                Ok(Flow::Return(result)) => return Ok(result),
+                // errors are given the position of the instruction that raised them
+                Err(err) => return Err(err.or_position(proto.positions[at])),
+            }
+        }
+    }
+
+    ///
+    /// Executes a single instruction of the current frame, whose prototype is proto.
+    ///
+    #[inline(always)]
+    fn step(&mut self, op: Op, proto: &Proto, ip: &mut usize, base: usize, depth: usize) -> Result<Flow> {
+        match op {
+            Op::Const(idx) => self.stack.push(proto.constants[idx].clone()),
+            Op::None => self.stack.push(Value::None),
+            Op::Dup => {
+                let top = self.stack.last().expect("value stack underflow").clone();
+                self.stack.push(top);
+            }
+            Op::Pop => {
+                self.pop();
+            }
+            Op::PopN(n) => {
+                let len = self.stack.len();
+                self.stack.truncate(len - n);
+            }
+            Op::LoadLocal(slot) => self.stack.push(self.stack[base + slot].clone()),
+            Op::StoreLocal(slot) => {
+                let value = self.pop();
+                self.stack[base + slot] = value;
+            }
+            Op::MakeCell(slot) => {
+                let value = self.pop();
+                let cells = &mut self.frames.last_mut().expect("no active frame").cells;
+                if cells.len() <= slot {
+                    cells.resize(slot + 1, None);
+                }
+                cells[slot] = Some(Rc::new(RefCell::new(value)));
+            }
+            Op::LoadCell(slot) => {
+                let value = self.cell(slot).borrow().clone();
+                self.stack.push(value);
+            }
+            Op::StoreCell(slot) => {
+                let value = self.pop();
+                *self.cell(slot).borrow_mut() = value;
+            }
+            Op::LoadGlobal(idx) => {
+                let name = &proto.names[idx];
+                let value = match self.globals.get(name) {
+                    Some(value) => value.clone(),
+                    // builtins can be shadowed by globals of the same name
+                    None if builtin::exists(name) => Value::Builtin(name.clone()),
+                    None => return Err(Error::new(UndefinedVar(name.clone()))),
+                };
+                self.stack.push(value);
+            }
+            Op::LoadGroup(idx) => {
+                let name = &proto.names[idx];
+                match self.lookup_group(name) {
+                    Some(value) => self.stack.push(value),
+                    None => return Err(Error::new(UndefinedVar(name.clone()))),
+                }
+            }
+            Op::StoreGlobal(idx) => {
+                let name = &proto.names[idx];
+                let value = self.pop();
+                match self.globals.get_mut(name) {
+                    Some(global) => *global = value,
+                    None => return Err(Error::new(UndefinedVar(name.clone()))),
+                }
+            }
+            Op::DefineGlobal(idx) => {
+                let value = self.pop();
+                self.globals.insert(proto.names[idx].clone(), value);
+            }
+            Op::DefineFn(idx) => {
+                let source = self.register(proto.protos[idx].clone());
+                self.stack.push(Value::Func(source));
+            }
+            Op::Closure(idx) => {
+                let func = proto.protos[idx].clone();
+                let captures = func.captures.iter().cloned()
+                    .zip(func.capture_slots.iter().map(|&slot| self.cell(slot).clone()))
+                    .collect();
+                let source = self.register(func);
+                self.stack.push(Value::Closure(Closure { func: source, captures }));
+            }
+            Op::Struct(idx) => {
+                let def = &proto.structs[idx];
+                self.structs.declare_struct(def);
+                self.stack.push(Value::Struct(def.clone()));
+            }
+            Op::Enum(idx) => {
+                let def = &proto.enums[idx];
+                self.structs.declare_enum(def);
+                self.stack.push(Value::Enum(def.clone()));
+            }
+            Op::Impl(idx) => {
+                // methods are compiled up front, so that they are identified like any other function
+                let mut imp = proto.impls[idx].clone();
+                for method in &mut imp.methods {
+                    let compiled = Compiler::compile_function(method, &[])?;
+                    *method = self.register(Rc::new(compiled));
+                }
+                self.structs.implement(&imp)?;
+                self.stack.push(Value::None);
+            }
+            Op::BinOp(BinOp::TildeEq) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                let result = self.regex_match(lhs, rhs)?;
+                self.stack.push(result);
+            }
+            Op::BinOp(op) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                self.stack.push(lhs.try_binop(op, &rhs)?);
+            }
+            Op::Unary(op) => {
+                let operand = self.pop();
+                self.stack.push(operand.try_unop(op)?);
+            }
+            Op::Concat(n) => {
+                let parts = self.stack.split_off(self.stack.len() - n);
+                let mut result = String::new();
+                for part in parts {
+                    result.push_str(&part.to_string());
+                }
+                self.stack.push(Value::String(result));
+            }
+            Op::List(n) => {
+                let items = self.stack.split_off(self.stack.len() - n);
+                self.stack.push(Value::List(items));
+            }
+            Op::Map(n) => {
+                let flat = self.stack.split_off(self.stack.len() - n * 2);
+                let mut entries = Vec::new();
+                for pair in flat.chunks(2) {
+                    match &pair[0] {
+                        Value::String(key) => entries.push((key.clone(), pair[1].clone())),
+                        _ => return Err(Error::new(InvalidType)),
+                    }
+                }
+                self.stack.push(Value::Map(entries.into_iter().collect()));
+            }
+            Op::Index => {
+                let index = self.pop();
+                let target = self.pop();
+                self.stack.push(target.try_index(&index)?);
+            }
+            Op::Field(idx) => {
+                let target = self.pop();
+                self.stack.push(self.structs.field(target, &proto.names[idx])?);
+            }
+            Op::SetField(idx) => {
+                let mut target = self.pop();
+                let value = self.pop();
+                self.structs.set_field(&mut target, &proto.names[idx], value, &self.regexes)?;
+                self.stack.push(target);
+            }
+            Op::SetIndex => {
+                let index = self.pop();
+                let mut target = self.pop();
+                let value = self.pop();
+                target.try_set_index(&index, value)?;
+                self.stack.push(target);
+            }
+            Op::Jump(target) => *ip = target,
+            Op::Match(idx, slot, target) => {
+                let (pattern, first) = &proto.patterns[idx];
+                match pattern::matches(pattern, &self.stack[base + slot]) {
+                    Some(matched) => {
+                        let values = matched.bindings.into_iter().chain(matched.captures);
+                        for (i, (_, value)) in values.enumerate() {
+                            self.stack[base + first + i] = value;
+                        }
+                    }
+                    None => *ip = target,
+                }
+            }
+            Op::Unmatched(slot, line, column) => {
+                pattern::warn_unmatched(&self.stack[base + slot], Position { line, column }, &mut self.warnings);
+                self.stack.push(Value::None);
+            }
+            Op::JumpIfFalse(target) => {
+                if !self.pop().into_bool() {
+                    *ip = target;
+                }
+            }
+            Op::JumpIfFalseOrPop(target) => {
+                if self.stack.last().expect("value stack underflow").into_bool() {
+                    self.pop();
+                } else {
+                    *ip = target;
+                }
+            }
+            Op::JumpIfTrueOrPop(target) => {
+                if self.stack.last().expect("value stack underflow").into_bool() {
+                    *ip = target;
+                } else {
+                    self.pop();
+                }
+            }
+            Op::JumpIfNotError(target) => {
+                if !matches!(self.stack.last().expect("value stack underflow"), Value::Error(_)) {
+                    *ip = target;
+                }
+            }
+            Op::IterStart => {
+                let value = self.pop();
+                self.iters.push(ValueIter::new(value)?);
+            }
+            Op::IterNext(exit, pair) => {
+                let iter = self.iters.last_mut().expect("no active iterator");
+                if pair {
+                    match iter.next_pair() {
+                        Some((key, value)) => {
+                            self.stack.push(key);
+                            self.stack.push(value);
+                        }
+                        None => *ip = exit,
+                    }
+                } else {
+                    match iter.next_single() {
+                        Some(value) => self.stack.push(value),
+                        None => *ip = exit,
+                    }
+                }
+            }
+            Op::IterEnd => {
+                self.iters.pop();
+            }
+            Op::Call(argc) => {
+                let callee = self.stack[self.stack.len() - argc - 1].clone();
+                self.frames.last_mut().unwrap().ip = *ip;
+                self.invoke(&callee, argc, &[])?;
+                return Ok(Flow::Reload);
+            }
+            Op::CallNamed(argc, idx) => {
+                let names = &proto.keywords[idx];
+                let callee = self.stack[self.stack.len() - argc - names.len() - 1].clone();
+                self.frames.last_mut().unwrap().ip = *ip;
+                self.invoke(&callee, argc, names)?;
+                return Ok(Flow::Reload);
+            }
+            Op::JumpIfBound(slot, target) => {
+                if !self.frames.last().expect("no active frame").unbound.contains(&slot) {
+                    *ip = target;
+                }
+            }
+            Op::StoreArg(slot) => {
+                let value = self.pop();
+                let func = proto.function.as_ref().expect("function prototype without a source function");
+                self.stack[base + slot] = types::check_arg(&func.args[slot], value, &self.regexes, &self.structs)?;
+            }
+            Op::CallGlobal(idx, argc) => {
+                let name = &proto.names[idx];
+                let cached = match self.globals.get(name) {
+                    Some(Value::Func(func)) => func.id.and_then(|id| self.functions.get(&id)).cloned(),
+                    Some(_) => None,
+                    None if builtin::exists(name) => {
+                        self.call_builtin(name, argc)?;
+                        return Ok(Flow::Next);
+                    }
+                    None => return Err(Error::new(UndefinedVar(name.clone()))),
+                };
+                self.frames.last_mut().unwrap().ip = *ip;
+                match cached {
+                    Some(func) => self.call(func, argc, &[], &[])?,
+                    None => {
+                        let callee = self.globals[name].clone();
+                        self.invoke(&callee, argc, &[])?;
+                    }
+                }
+                return Ok(Flow::Reload);
+            }
+            Op::Try(catch) => {
+                self.handlers.push(Handler {
+                    frames: self.frames.len(),
+                    stack: self.stack.len(),
+                    iters: self.iters.len(),
+                    catch,
+                });
+            }
+            Op::EndTry => {
+                self.handlers.pop();
+            }
+            Op::Throw(line, column) => {
+                let value = self.pop();
+                return Err(Error::thrown(value, Position { line, column }));
+            }
+            Op::Return => {
+                // returning from within a try block leaves it
+                let frames = self.frames.len();
+                while self.handlers.last().is_some_and(|handler| handler.frames >= frames) {
+                    self.handlers.pop();
+                }
+                let mut result = self.pop();
+                if let Some(func) = &proto.function {
+                    result = types::check_return(func, result, &self.regexes, &self.structs)?;
+                }
+                let frame = self.frames.pop().expect("no active frame");
+                // discard locals, temporaries and the callee
+                self.stack.truncate(base - 1);
+                self.iters.truncate(frame.iters);
+                if self.frames.len() == depth {
+                    return Ok(Flow::Return(result));
+                }
+                self.stack.push(result);
+                return Ok(Flow::Reload);
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn current_frame(&self) -> (Rc<Proto>, usize, usize) {
+        let frame = self.frames.last().expect("no active frame");
+        (frame.proto.clone(), frame.ip, frame.base)
+    }
+}
+
+///
+/// Where the dispatch loop continues after an instruction
+///
+enum Flow {
+    /// The next instruction of the same frame
+    Next,
+    /// The current frame, which a call or return has changed
+    Reload,
+    /// Nowhere, as the execution has returned this value
+    Return(Value),
+}
+
+///
+/// The values captured by a function value, if it is a closure.
+///
+fn captures_of(callee: &Value) -> &[(String, Capture)] {
+    match callee {
+        Value::Closure(closure) => &closure.captures,
+        _ => &[],
+    }
+}
+
+impl Interpreter<Value, Error> for VmInterpreter {
+    fn eval_file<P: Into<PathBuf>>(&mut self, path: P) -> Result<Value> {
+        let script = std::fs::read_to_string(path.into())?;
+        self.eval_script(&script)
+    }
+
+    fn eval_script(&mut self, script: &str) -> Result<Value> {
+        let tokens = TokenStream::new(script);
+        let mut stmts = Vec::new();
+        while !tokens.is_empty() {
+            stmts.push(Stmt::from_tokens(&tokens)?);
+        }
+        let script = Compiler::compile_script(&stmts)?;
+        self.run(script)
+    }
+
+    fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+}
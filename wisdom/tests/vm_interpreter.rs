@@ -0,0 +1,922 @@
+use interpreter::error::Error;
+use wisdom::ast::{ErrorValue, Value};
+use wisdom::interpreter::*;
+use wisdom::interpreter::error::{ErrorKind, Warning};
+use wisdom::interpreter::error::ErrorKind::{BreakInWrongContext, DuplicateArg, IndexOutOfBounds, InvalidType, KeyNotFound, MissingArg, NotCallable, Thrown, TypeMismatch, UndefinedVar, UnexpectedArgs, UnknownArg, UnknownField, UnknownType};
+use wisdom::common::Position;
+
+fn run_script(script: &str, expect: std::result::Result<Value, Error>) {
+    let mut itp = VmInterpreter::new();
+    let result = itp.eval_script(script);
+    assert_eq!(result, expect);
+}
+
+fn at(kind: ErrorKind, line: usize, column: usize) -> std::result::Result<Value, Error> {
+    Err(Error::new_at(kind, Position { line, column }))
+}
+
+#[test]
+fn test_simple_expression() {
+    run_script("1 + 1;", Ok(Value::Int(2)));
+}
+
+#[test]
+fn test_assignment() {
+    let script = r#"
+let a = 123;
+a
+"#;
+    run_script(script, Ok(Value::Int(123)));
+}
+
+#[test]
+fn test_loop() {
+    let script = r#"
+let a = 1;
+while a < 10 {
+    a = a + 1;
+}
+a
+"#;
+    run_script(script, Ok(Value::Int(10)));
+}
+
+#[test]
+fn test_scope() {
+    let script = r#"
+let a = 10;
+while a > 0 {
+    let b = 1;
+    a = a - b;
+}
+b
+"#;
+    run_script(script, at(UndefinedVar("b".to_string()), 7, 1));
+
+    // functions see the locals of where they are defined, not those of their caller
+    run_script("fn g() { x }\nfn f() { let x = 5; g() }\nf()", at(UndefinedVar("x".to_string()), 1, 10));
+}
+
+#[test]
+fn test_return_stmt() {
+    let script = r#"
+fn max(a, b) {
+    if a > b {
+        return a;
+    }
+    return b;
+}
+"#.to_owned();
+    let mut max_a = script.clone();
+    max_a.push_str("max(20, 10)");
+    let mut max_b = script.clone();
+    max_b.push_str("max(10, 20);");
+    run_script(&max_a, Ok(Value::Int(20)));
+    run_script(&max_b, Ok(Value::Int(20)));
+}
+
+#[test]
+fn test_scope_let_bindings() {
+    let mut intp = VmInterpreter::new();
+    let script = r#"
+let a = 20;
+fn func() {
+    let a = 1337;
+    return a;
+}
+"#;
+    intp.eval_script(script).unwrap();
+    assert_eq!(Ok(Value::Int(20)), intp.eval_script("a"));
+    assert_eq!(Ok(Value::Int(1337)), intp.eval_script("func()"));
+    assert_eq!(Ok(Value::Int(20)), intp.eval_script("a"));
+}
+
+#[test]
+fn test_no_let_local_assignment() {
+    run_script("a = 10;", at(UndefinedVar("a".to_string()), 1, 1));
+}
+
+#[test]
+fn test_nested_continue() {
+    let script = r#"
+let a = 0;
+let n = 0;
+while n < 10 {
+    n = n + 1;
+    if n > 5 {
+        continue;
+    }
+    a = a + 1;
+}
+a
+"#;
+    run_script(script, Ok(Value::Int(5)));
+}
+
+#[test]
+fn test_nested_break() {
+    let script = r#"
+let a = 0;
+while a < 10 {
+    if a == 5 {
+        break;
+    }
+    a = a + 1;
+}
+a
+"#;
+    run_script(script, Ok(Value::Int(5)));
+}
+
+#[test]
+fn test_break_outside_loop() {
+    run_script("break;", Err(Error::new(BreakInWrongContext)));
+}
+
+#[test]
+fn test_recursion() {
+    let script = r#"
+fn fib(n) {
+    if n < 2 {
+        return n;
+    }
+    return fib(n - 1) + fib(n - 2);
+}
+fib(15)
+"#;
+    run_script(script, Ok(Value::Int(610)));
+}
+
+#[test]
+fn test_return_from_loop() {
+    let script = r#"
+fn first_over(limit) {
+    let n = 0;
+    while true {
+        let m = n * n;
+        if m > limit {
+            return n;
+        }
+        n = n + 1;
+    }
+}
+first_over(50)
+"#;
+    run_script(script, Ok(Value::Int(8)));
+}
+
+#[test]
+fn test_function_modifies_global() {
+    let script = r#"
+let count = 0;
+fn incr() {
+    count = count + 1;
+}
+incr();
+incr();
+count
+"#;
+    run_script(script, Ok(Value::Int(2)));
+}
+
+#[test]
+fn test_unexpected_args() {
+    let script = r#"
+fn foo(a) {
+    a
+}
+foo(1, 2)
+"#;
+    run_script(script, at(UnexpectedArgs(1, 2), 5, 1));
+}
+
+#[test]
+fn test_interpolation() {
+    let script = r#"
+fn greet(name) {
+    return "hello ${name}";
+}
+let n = 2;
+"${greet("world")}, ${n} + ${n} = ${n + n}"
+"#;
+    run_script(script, Ok(Value::String("hello world, 2 + 2 = 4".to_string())));
+}
+
+#[test]
+fn test_interpolation_error() {
+    use wisdom::ast::error::{ErrorKind, ParserError};
+    use wisdom::tokenizer::{Base, LiteralKind, TokenKind};
+    let err = ParserError::new(ErrorKind::UnmatchedExpr, Some(Position { line: 1, column: 15 }));
+    run_script(r#"print("a ${1 +} b")"#, Err(Error::from(err)));
+    let err = ParserError::new(ErrorKind::InvalidToken(TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } }), Some(Position { line: 2, column: 8 }));
+    run_script("let a = 1;\n\"a ${a 2}\"", Err(Error::from(err)));
+}
+
+#[test]
+fn test_regex_literal() {
+    run_script(r#""Abc/123" ~= /^[a-z]+\/\d+$/i"#, Ok(Value::Bool(true)));
+    run_script(r#"let re = "^\\d+$"; "12" ~= re"#, Ok(Value::Bool(true)));
+    run_script("let x = if true {4} else {2} / 2; x", Ok(Value::Int(2)));
+}
+
+#[test]
+fn test_regex_captures() {
+    let script = r#"
+fn key(line) {
+    if line ~= /(\w+)=(\d+)/ {
+        return $1;
+    }
+    "none"
+}
+key("x=1") + key("y")
+"#;
+    run_script(script, Ok(Value::String("xnone".to_string())));
+
+    // groups bound inside a function don't outlive its call
+    run_script(&format!("{}; $2", script), at(UndefinedVar("$2".to_string()), 9, 3));
+    // nor are they seen by the functions it calls
+    run_script("fn first() { $1 }\nfn f(s) { s ~= /(\\w+)/; first() }\nf(\"a\")", at(UndefinedVar("$1".to_string()), 1, 14));
+}
+
+#[test]
+fn test_regex_builtins() {
+    run_script(r#"replace_all("a=1 b=2", /(\w)=(\d)/, "$2=$1")"#, Ok(Value::String("1=a 2=b".to_string())));
+    run_script(r#"find_all("x1y22", "\\d+")"#, Ok(Value::List(vec![
+        Value::String("1".to_string()),
+        Value::String("22".to_string()),
+    ])));
+}
+
+#[test]
+fn test_lists() {
+    let script = r#"
+fn sum(items) {
+    let total = 0;
+    let i = 0;
+    while i < len(items) {
+        total = total + items[i];
+        i = i + 1;
+    }
+    total
+}
+let a = [[1, 2], [3]];
+a[0][1] = 20;
+sum(a[0] + a[1])
+"#;
+    run_script(script, Ok(Value::Int(24)));
+    run_script("let a = []; a[0]", at(IndexOutOfBounds(0, 0), 1, 13));
+}
+
+#[test]
+fn test_maps() {
+    let script = r#"
+let m = {"z": 1, "a": 2};
+m["m"] = 3;
+m["z"] = m["z"] + 10;
+"${keys(m)} ${values(m)}"
+"#;
+    run_script(script, Ok(Value::String("[z, a, m] [11, 2, 3]".to_string())));
+}
+
+#[test]
+fn test_for_loop() {
+    let script = r#"
+fn find(items, target) {
+    for i, item in items {
+        for c in item {
+            if c == target {
+                return i;
+            }
+        }
+    }
+    0 - 1
+}
+let total = 0;
+for i in 1..=4 {
+    if i == 2 {
+        continue;
+    }
+    total = total + i;
+}
+for i in 0..100 {
+    let temp = [i];
+    if i == 3 {
+        break;
+    }
+}
+"${total} ${find(["ab", "cd"], "d")} ${find([], "x")}"
+"#;
+    run_script(script, Ok(Value::String("8 1 -1".to_string())));
+    run_script("for x in 1 {}", at(InvalidType, 1, 1));
+}
+
+#[test]
+fn test_unary_ops() {
+    let script = r#"
+fn abs(n) {
+    if n < 0 {
+        return -n;
+    }
+    n
+}
+let mask = ~0xff;
+"${abs(-4)} ${-abs(-4)} ${!true} ${mask & 0x100}"
+"#;
+    run_script(script, Ok(Value::String("4 -4 false 256".to_string())));
+}
+
+#[test]
+fn test_shift_and_pow() {
+    run_script("let bits = 0; for i in 0..4 { bits = bits | 1 << i * 2; } bits", Ok(Value::Int(0b1010101)));
+    run_script("2 ** 62 >> 60", Ok(Value::Int(4)));
+    run_script("-2 ** 2", Ok(Value::Int(-4)));
+    run_script("let a = 2; -a ** 2 + (-a) ** 2", Ok(Value::Int(0)));
+}
+
+#[test]
+fn test_associativity() {
+    run_script("let a = [0, 0]; let b = 0; a[1] = b = 3; a[1] + b + (10 - 4 - 3)", Ok(Value::Int(9)));
+}
+
+#[test]
+fn test_compound_assignment() {
+    let script = r#"
+fn count(words) {
+    let counts = {};
+    for word in words {
+        if contains(counts, word) {
+            counts[word] += 1;
+        } else {
+            counts[word] = 1;
+        }
+    }
+    counts
+}
+let mask = 0xff;
+mask &= 0x0f;
+mask ^= 1;
+"${count(["a", "b", "a"])} ${mask}"
+"#;
+    run_script(script, Ok(Value::String("{a: 2, b: 1} 14".to_string())));
+
+    // the target's container and indexes are evaluated only once
+    let script = r#"
+struct P { x }
+let n = 0;
+fn i() { n += 1; 0 }
+let a = [5];
+let ps = [P(1)];
+let grid = [[0, 0]];
+a[i()] += 1;
+ps[i()].x += 1;
+grid[i()][i()] = 7;
+"${n} ${a} ${ps[0].x} ${grid}"
+"#;
+    run_script(script, Ok(Value::String("4 [6] 2 [[7, 0]]".to_string())));
+}
+
+#[test]
+fn test_short_circuit() {
+    let script = r#"
+let calls = 0;
+fn expensive(x) {
+    calls += 1;
+    x
+}
+let a = false && expensive(1);
+let b = true || expensive(2);
+let c = none || expensive(3);
+"${a} ${b} ${c} ${calls}"
+"#;
+    run_script(script, Ok(Value::String("false true 3 1".to_string())));
+}
+
+#[test]
+fn test_closures() {
+    let script = r#"
+fn adder(n) {
+    |x| x + n
+}
+fn apply(f, x) {
+    f(x)
+}
+fn outer(a) {
+    |b| |c| a + b + c
+}
+let add2 = adder(2);
+let ops = [adder(5), fn(x) { x * 10 }];
+let table = {"double": |x| x * 2};
+let double = table["double"];
+let f = outer(1);
+let g = f(2);
+"${add2(1)} ${apply(ops[0], 1)} ${apply(ops[1], 3)} ${double(4)} ${g(3)}"
+"#;
+    run_script(script, Ok(Value::String("3 6 30 8 6".to_string())));
+
+    let script = r#"
+fn f() {
+    fn g(n) {
+        if n == 0 {
+            return 0;
+        }
+        n + g(n - 1)
+    }
+    g(3)
+}
+f()
+"#;
+    // a nested function can call itself
+    run_script(script, Ok(Value::Int(6)));
+}
+
+#[test]
+fn test_captures() {
+    let script = r#"
+let k = 10;
+let add_k = |x| x + k;
+k = 20;
+fn counter() {
+    let n = 0;
+    let inc = || n += 1;
+    "${inc()} ${inc()} ${n}"
+}
+"${add_k(1)} ${counter()}"
+"#;
+    // globals are looked up when used, and locals are shared with the closures capturing them
+    run_script(script, Ok(Value::String("21 1 2 2".to_string())));
+
+    let script = r#"
+fn f() {
+    let n = 0;
+    let inc = || { n = n + 1; n };
+    inc();
+    inc();
+    let get = || n;
+    n = 10;
+    "${n} ${get()} ${inc()} ${n}"
+}
+f()
+"#;
+    // changes made by either the closure or the scope that defined it are seen by both
+    run_script(script, Ok(Value::String("10 10 11 11".to_string())));
+
+    let script = r#"
+fn make() {
+    let n = 0;
+    return || { n += 1; n };
+}
+fn failing() {
+    let n = 0;
+    return || { n += 1; throw n };
+}
+let c = make();
+let d = c;
+let e = make();
+let f = failing();
+try { f() } catch { none };
+"${c()} ${c()} ${d()} ${e()} ${map([1, 2], |x| c())} ${try { f() } catch err { err.message }}"
+"#;
+    // changes to captured values are kept between calls, by every copy of the closure
+    run_script(script, Ok(Value::String("1 2 3 1 [4, 5] 2".to_string())));
+}
+
+#[test]
+fn test_higher_order_builtins() {
+    let script = r#"
+fn scale(items, factor) {
+    map(items, |x| x * factor)
+}
+let trimmed = map(lines("  a\n b "), |l| trim(l));
+"${trimmed} ${scale(1..4, 3)} ${filter([1, 2, 3, 4], |x| x % 2 == 0)}"
+"#;
+    run_script(script, Ok(Value::String("[a, b] [3, 6, 9] [2, 4]".to_string())));
+    run_script("let x = 5; x(1)", at(NotCallable, 1, 12));
+    run_script("let f = |x| x; f(1, 2)", at(UnexpectedArgs(1, 2), 1, 16));
+}
+
+#[test]
+fn test_callable_expressions() {
+    let script = r#"
+fn make_adder(n) {
+    |x| x + n
+}
+fn twice(f) {
+    |x| f(f(x))
+}
+let fns = [|x| x + 1, |x| x * 2];
+let handlers = {"greet": |name| "hello ${name}"};
+let size = len;
+"${fns[1](5)} ${make_adder(3)(4)} ${twice(fns[0])(0)} ${handlers.greet("bob")} ${size("abc")} ${map(["ab"], len)}"
+"#;
+    run_script(script, Ok(Value::String("10 7 2 hello bob 3 [2]".to_string())));
+    run_script("let p = print; p", Ok(Value::Builtin("print".to_string())));
+    run_script("let len = 1; len", Ok(Value::Int(1)));
+    run_script("[1](0)", at(NotCallable, 1, 1));
+}
+
+#[test]
+fn test_fields() {
+    let script = r#"
+let config = {"name": "a", "count": 1};
+config.count += 2;
+config.extra = [0];
+config.extra[0] = 5;
+"${config} ${config.name}"
+"#;
+    run_script(script, Ok(Value::String("{name: a, count: 3, extra: [5]} a".to_string())));
+    run_script(r#"let m = {"a": 1}; m.b"#, at(KeyNotFound("b".to_string()), 1, 19));
+    run_script("let a = 1; a.b", at(InvalidType, 1, 12));
+}
+
+#[test]
+fn test_type_annotations() {
+    let script = r#"
+fn count(pattern: regex, text: str) -> int {
+    len(find_all(text, pattern))
+}
+let half = fn(n: int) -> float { n / 2.0 };
+let anything = |x: any| x;
+"${count("a+", "aa b aaa")} ${count(/b/, "abba")} ${half(3)} ${anything(none)}"
+"#;
+    run_script(script, Ok(Value::String("2 2 1.5 none".to_string())));
+
+    run_script("fn f(a: int) { a }\nf(\"1\")", at(TypeMismatch("int".to_string(), "str".to_string()), 1, 9));
+    run_script("fn g() -> int { \"no\" }\ng()", at(TypeMismatch("int".to_string(), "str".to_string()), 1, 11));
+    run_script("let h = |a: integer| a;\nh(1)", at(UnknownType("integer".to_string()), 1, 13));
+}
+
+#[test]
+fn test_default_args() {
+    let script = r#"
+fn join(items, sep: str = ",", end = sep + sep) {
+    let result = "";
+    for i, item in items {
+        if i > 0 {
+            result += sep;
+        }
+        result += "${item}";
+    }
+    result + end
+}
+"${join([1, 2])} ${join([1, 2], ";")} ${join([3], end = "!")} ${join(sep = "-", items = [4, 5])}"
+"#;
+    run_script(script, Ok(Value::String("1,2,, 1;2;; 3! 4-5--".to_string())));
+    run_script("fn f(a: int = \"1\") { a }\nf()", Err(Error::new_at(TypeMismatch("int".to_string(), "str".to_string()), Position { line: 1, column: 9 })));
+}
+
+#[test]
+fn test_variadic_args() {
+    let script = r#"
+fn total(first, ...rest: int) {
+    for n in rest {
+        first += n;
+    }
+    first
+}
+let count = |...all| len(all);
+"${total(1)} ${total(1, 2, 3)} ${count()} ${count(1, "a")}"
+"#;
+    run_script(script, Ok(Value::String("1 6 0 2".to_string())));
+    run_script("fn f(...rest: int) { rest }\nf(1, \"2\")", Err(Error::new_at(TypeMismatch("int".to_string(), "str".to_string()), Position { line: 1, column: 15 })));
+}
+
+#[test]
+fn test_named_args() {
+    let f = "fn f(a, b = 2) { a * 10 + b }\n";
+    run_script(&format!("{}f(b = 3, a = 1)", f), Ok(Value::Int(13)));
+    run_script(&format!("{}f(1, c = 3)", f), at(UnknownArg("c".to_string()), 2, 1));
+    run_script(&format!("{}f(1, a = 3)", f), at(DuplicateArg("a".to_string()), 2, 1));
+    run_script(&format!("{}f(b = 1)", f), at(MissingArg("a".to_string()), 2, 1));
+    run_script(&format!("{}f(1, 2, 3)", f), at(UnexpectedArgs(2, 3), 2, 1));
+    run_script("len(\"abc\", x = 1)", at(UnknownArg("x".to_string()), 1, 1));
+}
+
+#[test]
+fn test_labeled_loops() {
+    let script = r#"
+let found = [];
+'rows: for i in 0..4 {
+    let j = 0;
+    'cols: while true {
+        j += 1;
+        if j > i {
+            continue 'rows;
+        }
+        if i * j == 9 {
+            break 'rows;
+        }
+        for k in [1] {
+            if j == 1 {
+                continue 'cols;
+            }
+        }
+        found += [i * 10 + j];
+    }
+}
+found
+"#;
+    run_script(script, Ok(Value::List(vec![Value::Int(22), Value::Int(32)])));
+}
+
+#[test]
+fn test_break_value() {
+    let script = r#"
+let i = 0;
+let first = while true {
+    i += 1;
+    if i * i > 20 {
+        break i;
+    }
+};
+let none = for x in [1, 2] {};
+let outer = 'a: for x in 0..3 {
+    for y in 0..3 {
+        if x + y == 3 {
+            break 'a [x, y];
+        }
+    }
+};
+"${first} ${none} ${outer}"
+"#;
+    run_script(script, Ok(Value::String("5 none [1, 2]".to_string())));
+}
+
+#[test]
+fn test_unknown_label() {
+    use wisdom::ast::error::{ErrorKind, ParserError};
+    let err = ParserError::new(ErrorKind::UnknownLabel, Some(Position { line: 2, column: 24 }));
+    run_script("'a: while true {\n    let f = || { break 'a };\n}", Err(Error::from(err)));
+    let err = ParserError::new(ErrorKind::UnknownLabel, Some(Position { line: 2, column: 23 }));
+    run_script("'a: for x in [] {}\nwhile true { continue 'b }", Err(Error::from(err)));
+}
+
+#[test]
+fn test_invalid_character() {
+    use wisdom::ast::error::{ErrorKind, ParserError};
+    let err = ParserError::new(ErrorKind::InvalidChar('#'), Some(Position { line: 1, column: 12 }));
+    run_script("let a = 1; # b", Err(Error::from(err)));
+    let err = ParserError::new(ErrorKind::InvalidChar('$'), Some(Position { line: 1, column: 9 }));
+    run_script("let a = $;", Err(Error::from(err)));
+    let err = ParserError::new(ErrorKind::UnterminatedComment, Some(Position { line: 1, column: 12 }));
+    run_script("let a = 1; /* b", Err(Error::from(err)));
+}
+
+#[test]
+fn test_structs() {
+    let script = r#"
+struct Point { x: int, y: int }
+impl Point {
+    fn origin() { Point(0, 0) }
+    fn len2(self) { self.x ** 2 + self.y ** 2 }
+    fn moved(self, dx, dy = 0) {
+        self.x += dx;
+        self.y += dy;
+        self
+    }
+}
+let p = Point(3, y = 4);
+let q = p.moved(1);
+q.y = 10;
+let len2 = p.len2;
+"${p.len2()} ${len2()} ${p} ${q} ${Point.origin()}"
+"#;
+    run_script(script, Ok(Value::String("25 25 Point { x: 3, y: 4 } Point { x: 4, y: 10 } Point { x: 0, y: 0 }".to_string())));
+
+    let script = r#"
+struct Record { level: str, message: str }
+impl Record {
+    fn is_error(self) { self.level == "error" }
+}
+let records = map(["info: started", "error: failed"], |line| {
+    line ~= r"(\w+): (.*)";
+    Record($1, $2)
+});
+map(filter(records, |r| r.is_error()), |r| r.message)
+"#;
+    run_script(script, Ok(Value::List(vec![Value::String("failed".to_string())])));
+}
+
+#[test]
+fn test_struct_errors() {
+    let point = "struct Point { x: int, y: int }\n";
+    run_script(&format!("{}Point(1)", point), at(MissingArg("y".to_string()), 2, 1));
+    run_script(&format!("{}Point(1, \"2\")", point), at(TypeMismatch("int".to_string(), "str".to_string()), 1, 27));
+    run_script(&format!("{}Point(1, 2).z", point), at(UnknownField("z".to_string()), 2, 1));
+    run_script(&format!("{}let p = Point(1, 2);\np.z = 3", point), at(UnknownField("z".to_string()), 3, 1));
+    run_script(&format!("{}let p = Point(1, 2);\np.y = \"3\"", point), at(TypeMismatch("int".to_string(), "str".to_string()), 1, 27));
+    run_script(&format!("{}let p = Point(1, 2);\np.y += 1;\np.y", point), Ok(Value::Int(3)));
+    run_script(&format!("{}fn f(p: Point) {{ p }}\nf(1)", point), at(TypeMismatch("Point".to_string(), "int".to_string()), 2, 9));
+    run_script("impl Point {}", at(UnknownType("Point".to_string()), 1, 6));
+}
+
+#[test]
+fn test_enums() {
+    let script = r#"
+enum Shape { Circle(r: float), Rect(w, h), Empty }
+impl Shape {
+    fn area(self) {
+        match self {
+            Shape.Circle(r) => 3.0 * r * r,
+            Shape.Rect(w, h) => w * h,
+            Shape.Empty => 0,
+        }
+    }
+}
+let shapes = [Shape.Circle(1.0), Shape.Rect(2, h = 3), Shape.Empty];
+"${map(shapes, |s| s.area())} ${shapes[1]} ${Shape.Empty} ${Shape.Empty == shapes[2]} ${shapes[0] == Shape.Circle(2.0)}"
+"#;
+    run_script(script, Ok(Value::String("[3, 6, 0] Shape.Rect { w: 2, h: 3 } Shape.Empty true false".to_string())));
+
+    let shape = "enum Shape { Circle(r: float), Empty }\n";
+    run_script(&format!("{}Shape.Circle(\"1\")", shape), at(TypeMismatch("float".to_string(), "str".to_string()), 1, 24));
+    run_script(&format!("{}Shape.Square", shape), at(UnknownField("Square".to_string()), 2, 1));
+    run_script(&format!("{}let c = Shape.Circle(1.0);\nc.r = 2", shape), at(TypeMismatch("float".to_string(), "int".to_string()), 1, 24));
+    run_script(&format!("{}fn f(s: Shape) {{ s }}\nf(1)", shape), at(TypeMismatch("Shape".to_string(), "int".to_string()), 2, 9));
+}
+
+#[test]
+fn test_match() {
+    let script = r#"
+fn describe(v) {
+    match v {
+        0 => "zero",
+        1..=9 => "digit",
+        -1 => "minus one",
+        /^(\w+)@(\w+)$/ => "${$1} at ${$2}",
+        [] => "empty",
+        [first, ...rest] => "${first} then ${rest}",
+        Point { x: 0, y } => "on the y axis at ${y}",
+        Point { x, y } if x == y => "diagonal",
+        _ => "other",
+    }
+}
+struct Point { x, y }
+map([0, 7, -1, "me@home", [], [1, 2, 3], Point(0, 4), Point(2, 2), Point(1, 2), 10], describe)
+"#;
+    let expected = [
+        "zero", "digit", "minus one", "me at home", "empty", "1 then [2, 3]",
+        "on the y axis at 4", "diagonal", "other", "other",
+    ];
+    run_script(script, Ok(Value::List(expected.iter().map(|s| Value::String(s.to_string())).collect())));
+
+    let script = r#"
+let x = "outer";
+let total = 0;
+for i in 0..6 {
+    total += match i {
+        0 => 10,
+        x if x % 2 == 0 => { if x == 4 { break } x },
+        _ => continue,
+    }
+}
+fn first_even(items) {
+    for i in items {
+        match i { n if n % 2 == 0 => return n, _ => none }
+    }
+}
+"${total} ${x} ${first_even([1, 3, 4, 5])} ${match 3 { 1 => 1 }}"
+"#;
+    run_script(script, Ok(Value::String("12 outer 4 none".to_string())));
+
+    // capture groups are bound only within the arm
+    let script = r#"let r = match "a1" { /a(\d)/ => $1, _ => 0 }"#;
+    run_script(&format!("{}; r", script), Ok(Value::String("1".to_string())));
+    run_script(&format!("{}; $1", script), at(UndefinedVar("$1".to_string()), 1, 47));
+}
+
+#[test]
+fn test_unmatched_warning() {
+    let mut itp = VmInterpreter::new();
+    let script = "enum Shape { Circle, Square }\nfor s in [Shape.Square, Shape.Circle, Shape.Square] {\n    match s { Shape.Circle => 1 }\n}";
+    assert_eq!(itp.eval_script(script), Ok(Value::None));
+    // a match that is run repeatedly is warned about once
+    let warning = Warning { message: "match on 'Shape' has no arm for variant 'Square'".to_string(), position: Position { line: 3, column: 5 } };
+    assert_eq!(itp.take_warnings(), vec![warning]);
+    assert!(itp.take_warnings().is_empty());
+}
+
+#[test]
+fn test_try_catch() {
+    let script = r#"
+fn risky(n) {
+    if n > 2 { throw "too big: ${n}" }
+    n
+}
+let out = "";
+for i in 0..6 {
+    let r = try { risky(i) } catch e {
+        if i == 5 { break }
+        "${e.kind} '${e.message}' at ${e.position.line}:${e.position.column}"
+    };
+    out = "${out}${r}; ";
+}
+out
+"#;
+    let expected = "0; 1; 2; Error 'too big: 3' at 3:16; Error 'too big: 4' at 3:16; ";
+    run_script(script, Ok(Value::String(expected.to_string())));
+
+    let script = r#"
+fn risky(n) {
+    if n > 2 { raise n * 10 }
+    n
+}
+fn first_ok(items) {
+    for i in items {
+        try { return risky(i) } catch { continue }
+    }
+}
+let rethrown = try {
+    try { throw "inner" } catch e { throw e }
+} catch e { e.message };
+let from_builtin = try { map([1, 2, 3], risky) } catch e { e.message };
+let kinds = [
+    try { [1, 2][5] } catch e { e.kind },
+    try { "a" ~= "(" } catch e { e.kind },
+    try { undefined } catch e { e.message },
+    try { 1 } catch e { e.kind },
+];
+"${rethrown} ${from_builtin} ${kinds} ${first_ok([5, 4, 1, 2])}"
+"#;
+    let expected = "inner 30 [IndexOutOfBounds, InvalidRegex, Undefined variable 'undefined', 1] 1";
+    run_script(script, Ok(Value::String(expected.to_string())));
+
+    // errors raised by the interpreter are caught with the position of the expression that raised them,
+    // whereas those returned by fallible builtins have none
+    let script = r#"
+fn f(l) {
+    l[5]
+}
+let e = try { f([1]) } catch e { e };
+"${e.kind} at ${e.position.line}:${e.position.column} ${parse_int("x").position}"
+"#;
+    run_script(script, Ok(Value::String("IndexOutOfBounds at 3:5 none".to_string())));
+
+    // handlers are left behind by returning from within a try block
+    run_script("fn f() { try { return 1 } catch { 2 } }\nlet a = [f()]\na[3]", at(IndexOutOfBounds(3, 1), 3, 1));
+    let position = Position { line: 2, column: 8 };
+    let thrown = ErrorValue { kind: "Error".to_string(), message: "boom".to_string(), position: Some(position) };
+    run_script("let a = 1\nif a { throw \"boom\" }", Err(Error::new_at(Thrown(Box::new(thrown)), position)));
+}
+
+#[test]
+fn test_propagate() {
+    let script = r#"
+fn total(items) {
+    let sum = 0;
+    for item in items {
+        sum += parse_int(item)?;
+    }
+    sum
+}
+fn doubled(s) -> int { parse_int(s)? * 2 }
+let ok = total(["1", " 2", "3"]);
+let bad = total(["1", "x", "3"]);
+"${ok} ${bad.kind}: ${bad.message} ${is_error(bad)} ${is_error(ok)} ${doubled("4")} ${doubled("y").kind}"
+"#;
+    run_script(script, Ok(Value::String("6 InvalidNumber: Invalid number 'x' true false 8 InvalidNumber".to_string())));
+
+    let script = r#"
+fn load(path) {
+    let text = read(path)?;
+    lines(text)
+}
+fn first_number(s) {
+    // ? returns from the function, rather than being caught
+    try { parse_float(s)? } catch { 0.0 }
+}
+let caught = try { throw load("/no/such/file") } catch e { e.kind };
+let re = regex("(");
+"${caught} ${re.kind} ${regex("a+")} ${first_number("1.5")} ${first_number("a").kind} ${parse_float("2.5")?}"
+"#;
+    run_script(script, Ok(Value::String("IOError InvalidRegex a+ 1.5 InvalidNumber 2.5".to_string())));
+
+    let invalid = ErrorValue { kind: "InvalidNumber".to_string(), message: "Invalid number 'z'".to_string(), position: None };
+    run_script("parse_int(\"z\")?\n1", Ok(Value::Error(Box::new(invalid))));
+    run_script("parse_int(1)?", at(InvalidType, 1, 1));
+}
+
+#[test]
+fn test_function_identity() {
+    let script = r#"
+fn a() { fn inner() { return 1; } return inner; }
+fn b() { fn inner() { return 2; } return inner; }
+let x = a();
+let y = b();
+[x(), y(), x()]
+"#;
+    run_script(script, Ok(Value::List(vec![Value::Int(1), Value::Int(2), Value::Int(1)])));
+    run_script("fn f() { 1 }\nlet g = f;\nfn f() { 2 }\n[g(), f()]", Ok(Value::List(vec![Value::Int(1), Value::Int(2)])));
+
+    let script = r#"
+struct A { }
+struct B { }
+impl A { fn name(self) { "a" } }
+impl B { fn name(self) { "b" } }
+let a = A();
+impl A { fn name(self) { "new a" } }
+"${a.name()} ${B().name()}"
+"#;
+    run_script(script, Ok(Value::String("new a b".to_string())));
+    // anonymous functions all share a name
+    run_script("let f = |x| x + 1;\nlet g = |x| x * 2;\n\"${f} ${f(1)} ${g(3)}\"", Ok(Value::String("<lambda> 2 6".to_string())));
+}
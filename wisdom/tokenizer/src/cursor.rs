@@ -3,7 +3,7 @@ use std::str::Chars;
 use common::Position;
 
 use crate::Base;
-use crate::token::{LiteralKind, Token, TokenKind};
+use crate::token::{LiteralKind, Segment, Token, TokenKind};
 
 ///
 /// A Cursor is responsible for breaking up an input
@@ -24,8 +24,11 @@ pub struct Cursor<'a> {
     consumed: Vec<char>,
     /// Current position in the source code
     position: Position,
-    /// Whether or not to emit Whitespace tokens
+    /// Whether or not to emit Whitespace and Comment tokens
     emit_whitespace: bool,
+    /// Whether the previous token means that an operand is expected next,
+    /// rather than an operator. Used to tell regex literals apart from division.
+    expect_operand: bool,
 }
 
 impl<'a> Cursor<'a> {
@@ -33,14 +36,23 @@ impl<'a> Cursor<'a> {
     /// Constructs a new Cursor from the input string.
     ///
     pub fn new(input: &'a str, emit_whitespace: bool) -> Self {
+        Self::new_at(input, emit_whitespace, Default::default())
+    }
+
+    ///
+    /// Constructs a new Cursor from an input string that begins at the
+    /// given position, for input that is embedded in a larger source.
+    ///
+    pub fn new_at(input: &'a str, emit_whitespace: bool, position: Position) -> Self {
         Self {
             _size: input.len(),
             prev: 0,
             idx: 0,
             chars: input.chars(),
             consumed: Vec::new(),
-            position: Default::default(),
+            position,
             emit_whitespace,
+            expect_operand: true,
         }
     }
 
@@ -59,7 +71,7 @@ impl<'a> Cursor<'a> {
     }
 
     ///
-    ///
+    /// Whether the Cursor has consumed all of its (meaningful) input
     ///
     pub fn is_eof(&self) -> bool {
         self.chars.as_str().is_empty() || (!self.emit_whitespace && self.chars.clone().all(|c| c.is_whitespace()))
@@ -70,6 +82,7 @@ impl<'a> Cursor<'a> {
     /// If there are no more characters, this function will return
     /// None
     ///
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<char> {
         if let Some(ch) = self.chars.next() {
             self.idx += 1;
@@ -123,14 +136,13 @@ impl<'a> Cursor<'a> {
         use crate::token::TokenKind::*;
         use crate::token::BinOpKind::*;
 
-        self.prev = self.idx;
-        let saved_position = self.position.clone();
-
         if !self.emit_whitespace {
-            self.consume_while(|c| c.is_whitespace());
-            self.consumed.clear();
+            self.skip_trivia();
         }
 
+        self.prev = self.idx;
+        let saved_position = self.position;
+
         let ch = self.next().unwrap_or('\0');
         let kind = match ch {
             ch if ch.is_whitespace() => {
@@ -144,30 +156,97 @@ impl<'a> Cursor<'a> {
                 self.consume_number_literal()
             }
 
+            'r' if self.is_raw_string_start() => self.consume_raw_string(),
+
             ch if self.is_ident_start(ch) => {
                 self.consume_until(|c| !(c.is_alphanumeric() || c == '_'));
                 Identifier
             }
 
+            // regex capture groups, e.g. $0, $1 or $name
+            '$' if self.first().is_alphanumeric() || self.first() == '_' => {
+                self.consume_until(|c| !(c.is_alphanumeric() || c == '_'));
+                Identifier
+            }
+
             '"' => self.consume_string_literal(),
 
+            '\'' if self.is_ident_start(self.first()) => {
+                self.consume_until(|c| !(c.is_alphanumeric() || c == '_'));
+                Label
+            }
+
+            '>' if self.first() == '>' => {
+                self.next();
+                BinOp(ShiftRight)
+            }
+            '<' if self.first() == '<' => {
+                self.next();
+                BinOp(ShiftLeft)
+            }
             '>' => self.expect_equals(GtEq, Gt),
             '<' => self.expect_equals(LtEq, Lt),
 
             // Handle all the single-character tokens
-            '+' => Add,
-            '-' => Sub,
-            '*' => Mul,
-            '/' => Div,
+            '+' => self.expect_equals(AddEq, Add),
+            '-' if self.first() == '>' => {
+                self.next();
+                Arrow
+            }
+            '-' => self.expect_equals(SubEq, Sub),
+            '*' if self.first() == '*' => {
+                self.next();
+                BinOp(Pow)
+            }
+            '*' => self.expect_equals(MulEq, Mul),
+            '/' => match self.first() {
+                '/' => {
+                    self.consume_line_comment();
+                    Comment
+                }
+                '*' => {
+                    if self.consume_block_comment() {
+                        Comment
+                    } else {
+                        UnterminatedComment
+                    }
+                }
+                _ if self.expect_operand => self.consume_regex(),
+                _ => self.expect_equals(DivEq, Div)
+            },
 
+            '=' if self.first() == '>' => {
+                self.next();
+                FatArrow
+            }
             '=' => self.expect_equals(EqEq, Eq),
             '~' => self.expect_equals(TildeEq, Tilde),
-            '|' => self.expect_next('|', OrOr, BinOp(Or)),
-            '&' => self.expect_next('&', AndAnd, BinOp(And)),
-            '^' => BinOp(Xor),
+            '|' if self.first() == '|' => {
+                self.next();
+                OrOr
+            }
+            '&' if self.first() == '&' => {
+                self.next();
+                AndAnd
+            }
+            '|' => self.expect_equals(OrEq, BinOp(Or)),
+            '&' => self.expect_equals(AndEq, BinOp(And)),
+            '^' => self.expect_equals(XorEq, BinOp(Xor)),
             '!' => self.expect_equals(NotEq, BinOp(Not)),
-            '%' => BinOp(Mod),
+            '%' => self.expect_equals(ModEq, BinOp(Mod)),
+
+            '.' if self.first() == '.' => {
+                self.next();
+                if self.first() == '.' {
+                    self.next();
+                    Ellipsis
+                } else {
+                    self.expect_equals(DotDotEq, DotDot)
+                }
+            }
+            '.' => Dot,
 
+            '?' => Question,
             ';' => SemiColon,
             ':' => Colon,
             ',' => Comma,
@@ -175,8 +254,10 @@ impl<'a> Cursor<'a> {
             ')' => RightParen,
             '{' => LeftBrace,
             '}' => RightBrace,
+            '[' => LeftBracket,
+            ']' => RightBracket,
 
-            _ => panic!("invalid character: {:?}", ch)
+            _ => Unknown,
         };
 
         let token = Token {
@@ -185,35 +266,226 @@ impl<'a> Cursor<'a> {
             position: saved_position,
         };
         self.consumed.clear();
+        self.expect_operand = match token.kind {
+            Whitespace | Comment => self.expect_operand,
+            // a block can end an expression, as in `if a {b} else {c} / 2`, so a
+            // '/' after one is division
+            Literal { .. } | RightParen | RightBracket | RightBrace | Question => false,
+            Identifier => Cursor::is_operand_keyword(&token.literal),
+            _ => true,
+        };
         token
     }
 
-    fn consume_string_literal(&mut self) -> TokenKind {
-        let mut escaped = false;
-        let mut c = self.first();
+    ///
+    /// Keywords that are followed by an operand, rather than an operator
+    ///
+    fn is_operand_keyword(ident: &str) -> bool {
+        matches!(ident, "return" | "if" | "else" | "while" | "let" | "for" | "in" | "match" | "throw" | "raise")
+    }
+
+    ///
+    /// Consumes a regex literal, up to the closing '/' and any trailing flags.
+    /// Expects the cursor to be after the opening '/'. Within the pattern, '/'
+    /// can be escaped with a backslash.
+    ///
+    fn consume_regex(&mut self) -> TokenKind {
+        while let Some(c) = self.next() {
+            match c {
+                '\\' => {
+                    self.next();
+                }
+                '/' => break,
+                _ => {}
+            }
+        }
+        self.consume_while(|c| c.is_alphabetic());
+        TokenKind::Literal { kind: LiteralKind::Regex }
+    }
+
+    ///
+    /// Consumes any whitespace and comments, up to the start of the next
+    /// meaningful token. The consumed characters are discarded.
+    ///
+    fn skip_trivia(&mut self) {
         loop {
-            if match c {
-                '"' => {
-                    if escaped {
-                        escaped = false;
-                        false
-                    } else {
-                        true
+            self.consume_while(|c| c.is_whitespace());
+            match (self.first(), self.second()) {
+                ('/', '/') => self.consume_line_comment(),
+                ('/', '*') => {
+                    let (chars, idx, position) = (self.chars(), self.idx, self.position);
+                    self.next();
+                    if !self.consume_block_comment() {
+                        // leave an unterminated comment for next_token to report
+                        self.chars = chars;
+                        self.idx = idx;
+                        self.position = position;
+                        break;
                     }
                 }
-                '\\' => {
-                    escaped = true;
-                    false
+                _ => break,
+            }
+        }
+        self.consumed.clear();
+    }
+
+    ///
+    /// Consumes a line comment, up to (but not including) the end of the line.
+    /// Expects the cursor to be after the first '/'.
+    ///
+    fn consume_line_comment(&mut self) {
+        while self.first() != '\n' && self.next().is_some() {}
+    }
+
+    ///
+    /// Consumes a block comment, including any nested block comments.
+    /// Expects the cursor to be after the opening '/'. Returns whether the
+    /// comment was closed, rather than running to the end of the input.
+    ///
+    fn consume_block_comment(&mut self) -> bool {
+        // consume the opening '*'
+        self.next();
+        let mut depth = 1;
+        while let Some(c) = self.next() {
+            match (c, self.first()) {
+                ('/', '*') => {
+                    self.next();
+                    depth += 1;
                 }
-                _ => false
-            } {
-                break;
+                ('*', '/') => {
+                    self.next();
+                    depth -= 1;
+                    if depth == 0 {
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    ///
+    /// Consumes a string literal, up to and including the closing quote.
+    /// Expects the cursor to be after the opening quote.
+    ///
+    fn consume_string_literal(&mut self) -> TokenKind {
+        let mut interpolated = false;
+        loop {
+            match self.next() {
+                None => return TokenKind::Literal { kind: LiteralKind::Unterminated },
+                Some('"') => break,
+                Some('\\') => {
+                    // skip whatever has been escaped
+                    self.next();
+                }
+                Some('$') if self.first() == '{' => {
+                    self.next();
+                    self.consume_interpolation();
+                    interpolated = true;
+                }
+                _ => {}
             }
+        }
+
+        if interpolated {
+            TokenKind::Literal { kind: LiteralKind::InterpolatedString }
+        } else {
+            TokenKind::Literal { kind: LiteralKind::String }
+        }
+    }
+
+    ///
+    /// Whether the cursor is at the start of a raw string, i.e. after
+    /// the 'r' of `r"..."` or `r#"..."#`
+    ///
+    fn is_raw_string_start(&self) -> bool {
+        self.chars().find(|c| *c != '#') == Some('"')
+    }
+
+    ///
+    /// Consumes a raw string literal, in which there are no escapes or
+    /// interpolations. The string is terminated by a quote followed by
+    /// as many hashes as preceded the opening quote.
+    ///
+    fn consume_raw_string(&mut self) -> TokenKind {
+        let mut hashes = 0;
+        while self.first() == '#' {
             self.next();
-            c = self.first();
+            hashes += 1;
         }
+        // consume the opening quote
         self.next();
-        TokenKind::Literal { kind: LiteralKind::String }
+        while let Some(c) = self.next() {
+            if c == '"' && self.chars().take(hashes).filter(|c| *c == '#').count() == hashes {
+                for _ in 0..hashes {
+                    self.next();
+                }
+                return TokenKind::Literal { kind: LiteralKind::RawString { hashes } };
+            }
+        }
+        TokenKind::Literal { kind: LiteralKind::Unterminated }
+    }
+
+    ///
+    /// Consumes an interpolated expression, up to and including the closing
+    /// brace. Expects the cursor to be after the opening `${`. Braces and
+    /// string literals within the expression are balanced.
+    ///
+    fn consume_interpolation(&mut self) {
+        let mut depth = 1;
+        while let Some(c) = self.next() {
+            match c {
+                '"' => {
+                    self.consume_string_literal();
+                }
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    ///
+    /// Splits the remaining input, which is the contents of an interpolated string
+    /// literal, into text and expression segments.
+    ///
+    fn segments(&mut self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut text_start = self.position;
+        loop {
+            match self.next() {
+                None => break,
+                Some('\\') => {
+                    self.next();
+                }
+                Some('$') if self.first() == '{' => {
+                    // drop the '$' from the preceding text
+                    self.consumed.pop();
+                    if !self.consumed.is_empty() {
+                        segments.push(Segment::Text(self.consumed.drain(..).collect(), text_start));
+                    }
+                    self.next();
+                    self.consumed.clear();
+                    let position = self.position;
+                    self.consume_interpolation();
+                    // drop the closing '}'
+                    self.consumed.pop();
+                    segments.push(Segment::Expr(self.consumed.drain(..).collect(), position));
+                    text_start = self.position;
+                }
+                _ => {}
+            }
+        }
+        if !self.consumed.is_empty() {
+            segments.push(Segment::Text(self.consumed.drain(..).collect(), text_start));
+        }
+        segments
     }
 
     ///
@@ -250,7 +522,8 @@ impl<'a> Cursor<'a> {
             }
             _ => {
                 self.consume_while(|c| c.is_numeric());
-                if self.first() == '.' {
+                // `1..2` is a range, rather than a float followed by a dot
+                if self.first() == '.' && self.second() != '.' {
                     self.next().unwrap(); // this is safe
                     self.consume_while(|c| c.is_numeric());
                     TokenKind::Literal { kind: LiteralKind::Float }
@@ -347,8 +620,20 @@ impl<'a> Cursor<'a> {
 /// ```
 ///
 pub fn tokenize(input: &str, with_whitespace: bool) -> impl Iterator<Item=Token> + '_ {
-    let mut c = Cursor::new(input, with_whitespace);
+    tokenize_at(input, with_whitespace, Default::default())
+}
+
+///
+/// Creates a Token iterator from an input string which begins
+/// at the given position in some larger source.
+///
+pub fn tokenize_at(input: &str, with_whitespace: bool, position: Position) -> impl Iterator<Item=Token> + '_ {
+    let mut c = Cursor::new_at(input, with_whitespace, position);
     std::iter::from_fn(move || {
+        if !with_whitespace {
+            // so that trailing comments are not mistaken for another token
+            c.skip_trivia();
+        }
         if c.is_eof() {
             None
         } else {
@@ -357,14 +642,41 @@ pub fn tokenize(input: &str, with_whitespace: bool) -> impl Iterator<Item=Token>
     })
 }
 
+///
+/// Splits an interpolated string literal token into its text and
+/// expression segments. Text segments are left as they appear in the
+/// source, escape sequences and all.
+///
+/// ```
+/// use tokenizer::{interpolation_segments, tokenize, Segment};
+///
+/// let tok = tokenize(r#""a ${b} c""#, false).next().unwrap();
+/// let segments = interpolation_segments(&tok);
+/// assert!(matches!(&segments[0], Segment::Text(text, _) if text == "a "));
+/// assert!(matches!(&segments[1], Segment::Expr(src, _) if src == "b"));
+/// assert!(matches!(&segments[2], Segment::Text(text, _) if text == " c"));
+/// ```
+///
+pub fn interpolation_segments(token: &Token) -> Vec<Segment> {
+    let literal = token.literal.as_str();
+    let contents = literal
+        .strip_prefix('"')
+        .map(|l| l.strip_suffix('"').unwrap_or(l))
+        .unwrap_or(literal);
+    let mut position = token.position;
+    // skip over the opening quote
+    position.column += 1;
+    Cursor::new_at(contents, true, position).segments()
+}
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::token::BinOpKind;
 
     fn pos(line: usize, col: usize) -> Position {
         Position {
-            line: line,
+            line,
             column: col,
         }
     }
@@ -380,7 +692,7 @@ mod test {
             Token { kind: TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } }, literal: "1".to_string(), position: pos(1, 5) },
         ];
 
-        assert_eq!(&tokens[..], &expected[..]);
+        assert_eq!(tokens, expected);
     }
 
     #[test]
@@ -390,7 +702,7 @@ mod test {
             Token { kind: TokenKind::Identifier, literal: "identifier".to_string(), position: pos(1, 1) }
         ];
 
-        assert_eq!(&tokens[..], &expected[..]);
+        assert_eq!(tokens, expected);
     }
 
     #[test]
@@ -401,4 +713,229 @@ mod test {
         ];
         assert_eq!(tokens, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_line_comment() {
+        let tokens = tokenize("1 // one\n2 // two", false).collect::<Vec<Token>>();
+        let expected = vec![
+            Token { kind: TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } }, literal: "1".to_string(), position: pos(1, 1) },
+            Token { kind: TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } }, literal: "2".to_string(), position: pos(2, 1) },
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_nested_block_comment() {
+        let tokens = tokenize("1 /* a /* b */ c */ / 2", false).collect::<Vec<Token>>();
+        let kinds = tokens.iter().map(|t| t.kind).collect::<Vec<TokenKind>>();
+        assert_eq!(kinds, vec![
+            TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } },
+            TokenKind::Div,
+            TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } },
+        ]);
+    }
+
+    #[test]
+    fn test_emit_comments() {
+        let tokens = tokenize("a/* b */\n// c", true).collect::<Vec<Token>>();
+        let expected = vec![
+            Token { kind: TokenKind::Identifier, literal: "a".to_string(), position: pos(1, 1) },
+            Token { kind: TokenKind::Comment, literal: "/* b */".to_string(), position: pos(1, 2) },
+            Token { kind: TokenKind::Whitespace, literal: "\n".to_string(), position: pos(1, 9) },
+            Token { kind: TokenKind::Comment, literal: "// c".to_string(), position: pos(2, 1) },
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_interpolated_string() {
+        let tokens = tokenize(r#""a ${f("}")} b" 1"#, false).collect::<Vec<Token>>();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].kind, TokenKind::Literal { kind: LiteralKind::InterpolatedString });
+        assert_eq!(tokens[0].literal, r#""a ${f("}")} b""#);
+
+        let segments = interpolation_segments(&tokens[0]);
+        assert_eq!(segments, vec![
+            Segment::Text("a ".to_string(), pos(1, 2)),
+            Segment::Expr(r#"f("}")"#.to_string(), pos(1, 6)),
+            Segment::Text(" b".to_string(), pos(1, 13)),
+        ]);
+    }
+
+    #[test]
+    fn test_escaped_interpolation() {
+        let tokens = tokenize(r#""\${a}""#, false).collect::<Vec<Token>>();
+        assert_eq!(tokens[0].kind, TokenKind::Literal { kind: LiteralKind::String });
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let tokens = tokenize(r###"r"a\d" r#"say "hi""# rust"###, false).collect::<Vec<Token>>();
+        let expected = vec![
+            Token { kind: TokenKind::Literal { kind: LiteralKind::RawString { hashes: 0 } }, literal: r#"r"a\d""#.to_string(), position: pos(1, 1) },
+            Token { kind: TokenKind::Literal { kind: LiteralKind::RawString { hashes: 1 } }, literal: r##"r#"say "hi""#"##.to_string(), position: pos(1, 8) },
+            Token { kind: TokenKind::Identifier, literal: "rust".to_string(), position: pos(1, 22) },
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_unterminated_string() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let unterminated = TokenKind::Literal { kind: LiteralKind::Unterminated };
+
+        assert_eq!(kinds(r#"r"abc"#), vec![unterminated]);
+        assert_eq!(kinds(r##"r#"abc""##), vec![unterminated]);
+        assert_eq!(kinds(r#""abc"#), vec![unterminated]);
+        assert_eq!(kinds(r#""a ${b}"#), vec![unterminated]);
+        assert_eq!(kinds(r#""abc\""#), vec![unterminated]);
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+
+        assert_eq!(kinds("1 /* x"), vec![int, TokenKind::UnterminatedComment]);
+        assert_eq!(kinds("1 /* /* x */"), vec![int, TokenKind::UnterminatedComment]);
+        assert_eq!(kinds("1 /* x */ 2"), vec![int, int]);
+
+        let tokens = tokenize("print(1) /* x", false).collect::<Vec<Token>>();
+        assert_eq!(tokens[4], Token { kind: TokenKind::UnterminatedComment, literal: "/* x".to_string(), position: pos(1, 10) });
+    }
+
+    #[test]
+    fn test_unknown_character() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+
+        assert_eq!(kinds("a # b"), vec![TokenKind::Identifier, TokenKind::Unknown, TokenKind::Identifier]);
+        assert_eq!(kinds("$"), vec![TokenKind::Unknown]);
+        assert_eq!(kinds("$ 1")[0], TokenKind::Unknown);
+
+        let tokens = tokenize("print(1) # x", false).collect::<Vec<Token>>();
+        assert_eq!(tokens[4], Token { kind: TokenKind::Unknown, literal: "#".to_string(), position: pos(1, 10) });
+    }
+
+    #[test]
+    fn test_regex_or_div() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let regex = TokenKind::Literal { kind: LiteralKind::Regex };
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+
+        assert_eq!(kinds("a / 2 / b"), vec![TokenKind::Identifier, TokenKind::Div, int, TokenKind::Div, TokenKind::Identifier]);
+        assert_eq!(kinds("a ~= /a\\/b/i"), vec![TokenKind::Identifier, TokenKind::TildeEq, regex]);
+        assert_eq!(kinds("return /x/"), vec![TokenKind::Identifier, regex]);
+        assert_eq!(kinds("(1) / 2"), vec![TokenKind::LeftParen, int, TokenKind::RightParen, TokenKind::Div, int]);
+        assert_eq!(kinds("a[1] / 2"), vec![TokenKind::Identifier, TokenKind::LeftBracket, int, TokenKind::RightBracket, TokenKind::Div, int]);
+        assert_eq!(kinds("{4} / 2"), vec![TokenKind::LeftBrace, int, TokenKind::RightBrace, TokenKind::Div, int]);
+
+        let tokens = tokenize("f(/[a-z]+/ix)", false).collect::<Vec<Token>>();
+        assert_eq!(tokens[2].literal, "/[a-z]+/ix");
+    }
+
+    #[test]
+    fn test_range() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+        let float = TokenKind::Literal { kind: LiteralKind::Float };
+
+        assert_eq!(kinds("0..10"), vec![int, TokenKind::DotDot, int]);
+        assert_eq!(kinds("0..=n"), vec![int, TokenKind::DotDotEq, TokenKind::Identifier]);
+        assert_eq!(kinds("1.5..2"), vec![float, TokenKind::DotDot, int]);
+    }
+
+    #[test]
+    fn test_field_access() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let float = TokenKind::Literal { kind: LiteralKind::Float };
+
+        assert_eq!(kinds("a.b..c"), vec![
+            TokenKind::Identifier, TokenKind::Dot, TokenKind::Identifier, TokenKind::DotDot, TokenKind::Identifier,
+        ]);
+        assert_eq!(kinds("1.5.a"), vec![float, TokenKind::Dot, TokenKind::Identifier]);
+    }
+
+    #[test]
+    fn test_arrow() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+
+        assert_eq!(kinds(") -> int"), vec![TokenKind::RightParen, TokenKind::Arrow, TokenKind::Identifier]);
+        assert_eq!(kinds("a-1"), vec![TokenKind::Identifier, TokenKind::Sub, int]);
+        assert_eq!(kinds("a -= 1"), vec![TokenKind::Identifier, TokenKind::SubEq, int]);
+    }
+
+    #[test]
+    fn test_label() {
+        let tokens: Vec<Token> = tokenize("'outer: while", false).collect();
+        assert_eq!(tokens[0].kind, TokenKind::Label);
+        assert_eq!(tokens[0].literal, "'outer");
+        assert_eq!(tokens[1].kind, TokenKind::Colon);
+        assert_eq!(tokens[2].kind, TokenKind::Identifier);
+    }
+
+    #[test]
+    fn test_ellipsis() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+
+        assert_eq!(kinds("...rest"), vec![TokenKind::Ellipsis, TokenKind::Identifier]);
+        assert_eq!(kinds("a..b"), vec![TokenKind::Identifier, TokenKind::DotDot, TokenKind::Identifier]);
+    }
+
+    #[test]
+    fn test_fat_arrow() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let regex = TokenKind::Literal { kind: LiteralKind::Regex };
+
+        assert_eq!(kinds("_ => 1"), vec![TokenKind::Identifier, TokenKind::FatArrow, TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } }]);
+        assert_eq!(kinds("a >= b"), vec![TokenKind::Identifier, TokenKind::GtEq, TokenKind::Identifier]);
+        assert_eq!(kinds("match /a/ { }"), vec![TokenKind::Identifier, regex, TokenKind::LeftBrace, TokenKind::RightBrace]);
+    }
+
+    #[test]
+    fn test_question() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+
+        assert_eq!(kinds("f(a)?"), vec![TokenKind::Identifier, TokenKind::LeftParen, TokenKind::Identifier, TokenKind::RightParen, TokenKind::Question]);
+        assert_eq!(kinds("a? / 2"), vec![TokenKind::Identifier, TokenKind::Question, TokenKind::Div, int]);
+    }
+
+    #[test]
+    fn test_shift_and_pow() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let int = TokenKind::Literal { kind: LiteralKind::Int { base: Base::Dec } };
+
+        assert_eq!(kinds("1 << 2"), vec![int, TokenKind::BinOp(BinOpKind::ShiftLeft), int]);
+        assert_eq!(kinds("1 >> 2"), vec![int, TokenKind::BinOp(BinOpKind::ShiftRight), int]);
+        assert_eq!(kinds("1 <= 2"), vec![int, TokenKind::LtEq, int]);
+        assert_eq!(kinds("2 ** 3 * 4"), vec![int, TokenKind::BinOp(BinOpKind::Pow), int, TokenKind::Mul, int]);
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let kinds = |input| tokenize(input, false).map(|t| t.kind).collect::<Vec<TokenKind>>();
+        let ops: Vec<TokenKind> = kinds("a += a -= a *= a /= a %= a |= a &= a ^= a ** a || a && a")
+            .into_iter()
+            .filter(|k| *k != TokenKind::Identifier)
+            .collect();
+        assert_eq!(ops, vec![
+            TokenKind::AddEq, TokenKind::SubEq, TokenKind::MulEq, TokenKind::DivEq, TokenKind::ModEq,
+            TokenKind::OrEq, TokenKind::AndEq, TokenKind::XorEq,
+            TokenKind::BinOp(BinOpKind::Pow), TokenKind::OrOr, TokenKind::AndAnd,
+        ]);
+        // a regex may start with '='
+        assert_eq!(kinds("a ~= /=/"), vec![TokenKind::Identifier, TokenKind::TildeEq, TokenKind::Literal { kind: LiteralKind::Regex }]);
+    }
+
+    #[test]
+    fn test_capture_ident() {
+        let tokens: Vec<Token> = tokenize("$1 $name", false).collect();
+        let expected = vec![
+            Token { kind: TokenKind::Identifier, literal: "$1".to_string(), position: pos(1, 1) },
+            Token { kind: TokenKind::Identifier, literal: "$name".to_string(), position: pos(1, 4) },
+        ];
+
+        assert_eq!(tokens, expected);
+    }
+}
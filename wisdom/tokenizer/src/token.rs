@@ -22,7 +22,17 @@ pub struct Token {
 #[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
 pub enum TokenKind {
     Identifier,
+    /// A loop label `'outer`
+    Label,
     Whitespace,
+    /// A line (`// ...`) or block (`/* ... */`) comment. Only emitted
+    /// when whitespace is requested, otherwise comments are skipped.
+    Comment,
+    /// A block comment that runs to the end of the input without
+    /// being closed
+    UnterminatedComment,
+    /// A character that doesn't begin any token, e.g. `#`
+    Unknown,
     Literal {
         kind: LiteralKind,
     },
@@ -38,6 +48,8 @@ pub enum TokenKind {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Lt,
     LtEq,
     Gt,
@@ -48,6 +60,29 @@ pub enum TokenKind {
     NotEq,
     Tilde,
     TildeEq,
+    /// Compound assignment operators `+=`, `-=` etc
+    AddEq,
+    SubEq,
+    MulEq,
+    DivEq,
+    ModEq,
+    OrEq,
+    AndEq,
+    XorEq,
+    /// Field access `a.b`
+    Dot,
+    /// Precedes the return type of a function `-> int`
+    Arrow,
+    /// Separates the pattern of a match arm from its body `x => ...`
+    FatArrow,
+    /// A half-open range `a..b`
+    DotDot,
+    /// An inclusive range `a..=b`
+    DotDotEq,
+    /// Precedes a variadic parameter `...rest`
+    Ellipsis,
+    /// Propagates an error value out of the enclosing function `a?`
+    Question,
     BinOp(BinOpKind),
 }
 
@@ -58,6 +93,17 @@ pub enum LiteralKind {
     },
     Float,
     String,
+    /// A string literal containing one or more `${expr}` interpolations
+    InterpolatedString,
+    /// A raw string literal `r"..."`, optionally delimited by hashes `r#"..."#`
+    RawString {
+        hashes: usize,
+    },
+    /// A regex literal `/pattern/flags`
+    Regex,
+    /// A string or raw string literal that runs to the end of the input
+    /// without its closing quote
+    Unterminated,
 }
 
 #[derive(Debug, PartialOrd, PartialEq, Copy, Clone)]
@@ -77,6 +123,21 @@ pub enum BinOpKind {
     Mod,
     ShiftLeft,
     ShiftRight,
+    /// Exponentiation `**`
+    Pow,
+}
+
+///
+/// A piece of an interpolated string literal, as split
+/// by `interpolation_segments`
+///
+#[derive(Debug, PartialOrd, PartialEq, Clone)]
+pub enum Segment {
+    /// Raw text between interpolations, including any escape sequences,
+    /// and where it starts
+    Text(String, Position),
+    /// The source of an interpolated expression, and where it starts
+    Expr(String, Position),
 }
 
 impl TokenKind {
@@ -86,16 +147,16 @@ impl TokenKind {
     ///
     pub fn is_operator(&self) -> bool {
         use TokenKind::*;
-        match *self {
+        matches!(
+            *self,
             Add | Sub | Mul | Div |
             Lt | LtEq |
             Gt | GtEq |
             AndAnd | OrOr | EqEq | NotEq | TildeEq | Eq |
-            BinOp(..) => {
-                true
-            }
-            _ => false
-        }
+            AddEq | SubEq | MulEq | DivEq | ModEq | OrEq | AndEq | XorEq |
+            DotDot | DotDotEq |
+            BinOp(..)
+        )
     }
 }
 
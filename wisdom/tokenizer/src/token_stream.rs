@@ -3,7 +3,7 @@ use std::collections::VecDeque;
 
 use common::Position;
 
-use crate::{Token, tokenize, TokenKind};
+use crate::{Token, tokenize, tokenize_at, TokenKind};
 use crate::TokenKind::Whitespace;
 
 ///
@@ -28,6 +28,16 @@ impl TokenStream {
         }
     }
 
+    ///
+    /// Constructs a new Tokens structure from an input string, which
+    /// starts at the given position in some larger source.
+    ///
+    pub fn new_at(input: &str, position: Position) -> Self {
+        Self {
+            tokens: RefCell::new(tokenize_at(input, false, position).collect::<VecDeque<Token>>()),
+        }
+    }
+
     ///
     /// Whether there are any tokens left.
     ///
@@ -52,7 +62,7 @@ impl TokenStream {
     ///
     pub fn first(&self) -> Option<Token> {
         let tokens = self.tokens.borrow_mut();
-        tokens.get(0).cloned()
+        tokens.front().cloned()
     }
 
     ///
@@ -63,6 +73,14 @@ impl TokenStream {
         tokens.get(1).cloned()
     }
 
+    ///
+    /// Looks ahead at the third next token, without consuming it.
+    ///
+    pub fn third(&self) -> Option<Token> {
+        let tokens = self.tokens.borrow_mut();
+        tokens.get(2).cloned()
+    }
+
     ///
     /// Seeks forward in the tokens until there is a non-whitespace token.
     ///